@@ -0,0 +1,135 @@
+use crate::Point;
+use crate::Vector;
+use crate::Ray;
+use crate::Interval;
+use crate::interval;
+
+//An axis aligned bounding box, one interval per axis.
+#[derive(Debug, Clone, Copy)]
+pub struct Aabb {
+	pub x: Interval,
+	pub y: Interval,
+	pub z: Interval,
+}
+
+//flat boxes get padded to this thickness so the slab test still has something to hit.
+const MIN_THICKNESS: f64 = 1e-4;
+
+impl Aabb {
+	pub fn new(x: Interval, y: Interval, z: Interval) -> Aabb {
+		let mut aabb = Aabb {
+			x,
+			y,
+			z,
+		};
+		aabb.pad_to_minimums();
+		return aabb;
+	}
+	//the box with a and b as opposite corners, in any order.
+	pub fn from_points(a: &Point, b: &Point) -> Aabb {
+		return Aabb::new(
+			Interval::new(a.x.min(b.x), a.x.max(b.x)),
+			Interval::new(a.y.min(b.y), a.y.max(b.y)),
+			Interval::new(a.z.min(b.z), a.z.max(b.z)),
+		);
+	}
+	pub fn enclosing(a: &Aabb, b: &Aabb) -> Aabb {
+		return Aabb::new(
+			Interval::enclosing(&a.x, &b.x),
+			Interval::enclosing(&a.y, &b.y),
+			Interval::enclosing(&a.z, &b.z),
+		);
+	}
+	pub fn empty() -> Aabb {
+		Aabb {
+			x: interval::EMPTY,
+			y: interval::EMPTY,
+			z: interval::EMPTY,
+		}
+	}
+
+	pub fn axis_interval(&self, axis: u32) -> &Interval {
+		match axis {
+			1 => return &self.y,
+			2 => return &self.z,
+			_ => return &self.x,
+		}
+	}
+	//0, 1 or 2 for x, y or z.
+	pub fn longest_axis(&self) -> u32 {
+		if self.x.size() > self.y.size() {
+			if self.x.size() > self.z.size() {
+				return 0;
+			}
+			return 2;
+		}
+		if self.y.size() > self.z.size() {
+			return 1;
+		}
+		return 2;
+	}
+	pub fn centroid(&self) -> Point {
+		return Point::new(
+			0.5 * (self.x.min + self.x.max),
+			0.5 * (self.y.min + self.y.max),
+			0.5 * (self.z.min + self.z.max),
+		);
+	}
+	pub fn corners(&self) -> [Point; 8] {
+		let mut corners = [Point::null_vector(); 8];
+		for (index, corner) in corners.iter_mut().enumerate() {
+			corner.x = if index & 1 == 0 { self.x.min } else { self.x.max };
+			corner.y = if index & 2 == 0 { self.y.min } else { self.y.max };
+			corner.z = if index & 4 == 0 { self.z.min } else { self.z.max };
+		}
+		return corners;
+	}
+
+	//The slab test, narrows ray_t down to where the ray is inside all three slabs.
+	pub fn hit(&self, r: &Ray, ray_t: Interval) -> bool {
+		let inverse_dir = Vector::new(1.0 / r.dir.x, 1.0 / r.dir.y, 1.0 / r.dir.z);
+		return self.hit_inverse(&r.origin, &inverse_dir, ray_t);
+	}
+	//the same test with one over the ray direction worked out by the caller,
+	//for when one ray gets tested against many boxes.
+	pub fn hit_inverse(&self, origin: &Point, inverse_dir: &Vector, ray_t: Interval) -> bool {
+		let (x0, x1) = Self::slab(&self.x, origin.x, inverse_dir.x);
+		let (y0, y1) = Self::slab(&self.y, origin.y, inverse_dir.y);
+		let (z0, z1) = Self::slab(&self.z, origin.z, inverse_dir.z);
+		let t_min = ray_t.min.max(x0).max(y0).max(z0);
+		let t_max = ray_t.max.min(x1).min(y1).min(z1);
+		return t_min < t_max;
+	}
+	//the part of ray_t where the ray is inside the box, None when it misses.
+	pub fn clip(&self, r: &Ray, ray_t: Interval) -> Option<Interval> {
+		let (x0, x1) = Self::slab(&self.x, r.origin.x, 1.0 / r.dir.x);
+		let (y0, y1) = Self::slab(&self.y, r.origin.y, 1.0 / r.dir.y);
+		let (z0, z1) = Self::slab(&self.z, r.origin.z, 1.0 / r.dir.z);
+		let inside = Interval::new(ray_t.min.max(x0).max(y0).max(z0), ray_t.max.min(x1).min(y1).min(z1));
+		if inside.min < inside.max {
+			return Some(inside);
+		}
+		return None;
+	}
+	//where the ray enters and leaves one slab.
+	fn slab(slab: &Interval, origin: f64, inverse: f64) -> (f64, f64) {
+		let t0 = (slab.min - origin) * inverse;
+		let t1 = (slab.max - origin) * inverse;
+		if t0 < t1 {
+			return (t0, t1);
+		}
+		return (t1, t0);
+	}
+
+	fn pad_to_minimums(&mut self) {
+		if self.x.size() < MIN_THICKNESS {
+			self.x = self.x.expand(MIN_THICKNESS);
+		}
+		if self.y.size() < MIN_THICKNESS {
+			self.y = self.y.expand(MIN_THICKNESS);
+		}
+		if self.z.size() < MIN_THICKNESS {
+			self.z = self.z.expand(MIN_THICKNESS);
+		}
+	}
+}
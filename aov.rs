@@ -0,0 +1,82 @@
+use crate::Color;
+use crate::Vector;
+
+//The extra passes a render produces next to the beauty image, for compositing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aov {
+	//distance from the camera to the first hit.
+	Depth,
+	//world space normal at the first hit, facing the camera.
+	Normal,
+	Albedo,
+	//index of the first object hit in the HittableList.
+	ObjectId,
+	MaterialId,
+	//light reaching the first hit straight from a light or the background.
+	Direct,
+	//light reaching the first hit after bouncing off something else.
+	Indirect,
+	//light the camera sees directly, from emitters and the background.
+	Emission,
+	SampleCount,
+}
+
+impl Aov {
+	pub const ALL: [Aov; 9] = [
+		Aov::Depth,
+		Aov::Normal,
+		Aov::Albedo,
+		Aov::ObjectId,
+		Aov::MaterialId,
+		Aov::Direct,
+		Aov::Indirect,
+		Aov::Emission,
+		Aov::SampleCount,
+	];
+
+	pub fn name(&self) -> &'static str {
+		match self {
+			Aov::Depth => "depth",
+			Aov::Normal => "normal",
+			Aov::Albedo => "albedo",
+			Aov::ObjectId => "object_id",
+			Aov::MaterialId => "material_id",
+			Aov::Direct => "direct",
+			Aov::Indirect => "indirect",
+			Aov::Emission => "emission",
+			Aov::SampleCount => "sample_count",
+		}
+	}
+}
+
+//What one camera sample recorded about its first hit.
+//emission + direct + indirect adds up to the sample's color.
+#[derive(Debug, Clone, Copy)]
+pub struct AovSample {
+	pub hit: bool,
+	pub depth: f64,
+	pub normal: Vector,
+	pub albedo: Color,
+	//-1 when the sample didn't hit anything.
+	pub object_id: i32,
+	pub material_id: u32,
+	pub emission: Color,
+	pub direct: Color,
+	pub indirect: Color,
+}
+
+impl Default for AovSample {
+	fn default() -> Self {
+		AovSample {
+			hit: false,
+			depth: f64::INFINITY,
+			normal: Vector::null_vector(),
+			albedo: Color::null_vector(),
+			object_id: -1,
+			material_id: 0,
+			emission: Color::null_vector(),
+			direct: Color::null_vector(),
+			indirect: Color::null_vector(),
+		}
+	}
+}
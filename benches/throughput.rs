@@ -0,0 +1,212 @@
+#![allow(clippy::needless_return)]
+//Throughput benchmarks for the intersection code and whole renders, using only std timing.
+//	cargo bench                     runs everything
+//	cargo bench -- bvh              runs the benchmarks with bvh in their name
+//
+//Every benchmark works on inputs made from fixed seeds and renders use one thread,
+//so two runs on the same machine do the same work. Each one is run once to warm up and then
+//RUNS times, the median is reported along with how far the slowest and fastest runs were from it.
+use raytracer::*;
+use raytracer::hittable::Triangle;
+use raytracer::rng::Rng;
+use raytracer::scene::load_scene;
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const RUNS: usize = 7;
+
+//a benchmark's name and the function that runs it.
+type Benchmark = (&'static str, Box<dyn Fn() -> Measurement>);
+
+//One benchmark's timings. ops is how much work one run does, unit names it.
+struct Measurement {
+	name: String,
+	ops: u64,
+	unit: &'static str,
+	times: Vec<Duration>,
+}
+
+impl Measurement {
+	fn median(&self) -> Duration {
+		let mut times = self.times.clone();
+		times.sort();
+		return times[times.len() / 2];
+	}
+	//the spread of the runs as a fraction of the median.
+	fn spread(&self) -> f64 {
+		let median = self.median().as_secs_f64();
+		let fastest = self.times.iter().min().unwrap().as_secs_f64();
+		let slowest = self.times.iter().max().unwrap().as_secs_f64();
+		return (slowest - fastest) / median;
+	}
+	fn throughput(&self) -> f64 {
+		return self.ops as f64 / self.median().as_secs_f64();
+	}
+}
+
+//Runs work once to warm up and then RUNS more times. work returns how many ops it did,
+//which has to be the same every time.
+fn measure(name: &str, unit: &'static str, mut work: impl FnMut() -> u64) -> Measurement {
+	let ops = work();
+	let mut times: Vec<Duration> = Vec::new();
+	for _ in 0..RUNS {
+		let start = Instant::now();
+		let run_ops = work();
+		times.push(start.elapsed());
+		assert_eq!(run_ops, ops, "{name} did different amounts of work between runs");
+	}
+	return Measurement {
+		name: name.to_string(),
+		ops,
+		unit,
+		times,
+	};
+}
+
+fn random_point(rng: &mut Rng, extent: f64) -> Point {
+	return Point::new(
+		rng.random_f64_in_range(-extent, extent),
+		rng.random_f64_in_range(-extent, extent),
+		rng.random_f64_in_range(-extent, extent),
+	);
+}
+
+//rays starting around the origin and heading out in every direction.
+fn random_rays(seed: u64, count: usize, extent: f64) -> Vec<Ray> {
+	let mut rng = Rng::new(seed, 0);
+	return (0..count).map(|_| Ray::new(random_point(&mut rng, extent), random_point(&mut rng, 1.0))).collect();
+}
+
+//many small spheres, for comparing the linear list against the bvh.
+fn sphere_field(count: usize) -> HittableList {
+	let mut rng = Rng::new(11, 0);
+	let mut world = HittableList::new();
+	for _ in 0..count {
+		let center = random_point(&mut rng, 20.0);
+		world.add(center.x, center.y, center.z, rng.random_f64_in_range(0.2, 1.0), MaterialEnum::new_lambertian(0.5, 0.5, 0.5));
+	}
+	return world;
+}
+
+//traces every ray and returns how many there were, the hits are counted so the work can't be optimized away.
+fn trace_all(rays: &[Ray], hit: impl Fn(&Ray, &mut HitRecord) -> bool) -> u64 {
+	let mut rec = HitRecord::default();
+	let mut hits: u64 = 0;
+	for ray in rays {
+		if hit(black_box(ray), &mut rec) {
+			hits += 1;
+		}
+	}
+	black_box(hits);
+	return rays.len() as u64;
+}
+
+fn vector_arithmetic() -> Measurement {
+	let mut rng = Rng::new(1, 0);
+	let vectors: Vec<Vector> = (0..100_000).map(|_| random_point(&mut rng, 10.0)).collect();
+	return measure("vector_arithmetic", "ops/s", || {
+		let mut sum = Vector::null_vector();
+		for pair in vectors.windows(2) {
+			let a = black_box(pair[0]);
+			let b = black_box(pair[1]);
+			sum += a.cross(&b).normalize() * Vector::dot(&a, &b) + (a - b) / 3.0;
+		}
+		black_box(sum);
+		return (vectors.len() - 1) as u64;
+	});
+}
+
+fn sphere_hit() -> Measurement {
+	let sphere = Sphere::new(Point::null_vector(), 1.0, MaterialEnum::new_lambertian(0.5, 0.5, 0.5));
+	let rays = random_rays(2, 200_000, 3.0);
+	return measure("sphere_hit", "rays/s", || {
+		return trace_all(&rays, |ray, rec| sphere.hit(ray, Interval::new(0.001, f64::INFINITY), rec));
+	});
+}
+
+fn triangle_hit() -> Measurement {
+	let triangle = Triangle::new(Point::new(-1.0, -1.0, 0.0), Point::new(1.0, -1.0, 0.0), Point::new(0.0, 1.0, 0.0), MaterialEnum::new_lambertian(0.5, 0.5, 0.5));
+	let rays = random_rays(3, 200_000, 3.0);
+	return measure("triangle_hit", "rays/s", || {
+		return trace_all(&rays, |ray, rec| triangle.hit(ray, Interval::new(0.001, f64::INFINITY), rec));
+	});
+}
+
+fn hittable_list_hit() -> Measurement {
+	let world = sphere_field(500);
+	let rays = random_rays(4, 5_000, 20.0);
+	return measure("hittable_list_hit_500_spheres", "rays/s", || {
+		return trace_all(&rays, |ray, rec| world.hit(ray, Interval::new(0.001, f64::INFINITY), rec));
+	});
+}
+
+fn bvh_hit() -> Measurement {
+	let mut world = sphere_field(500);
+	world.build_bvh();
+	let rays = random_rays(4, 200_000, 20.0);
+	return measure("bvh_hit_500_spheres", "rays/s", || {
+		return trace_all(&rays, |ray, rec| world.hit(ray, Interval::new(0.001, f64::INFINITY), rec));
+	});
+}
+
+fn bvh_instances_hit() -> Measurement {
+	let scene = load_scene("many_instances").unwrap();
+	let camera_rays = random_rays(5, 100_000, 1.0);
+	//every ray starts at the scene's camera, so it sees the field of instances.
+	let rays: Vec<Ray> = camera_rays
+		.iter()
+		.map(|ray| Ray::new(scene.camera.lookfrom, scene.camera.lookat - scene.camera.lookfrom + ray.dir * 20.0))
+		.collect();
+	return measure("bvh_hit_many_instances", "rays/s", || {
+		return trace_all(&rays, |ray, rec| scene.world.hit(ray, Interval::new(0.001, f64::INFINITY), rec));
+	});
+}
+
+//A small single threaded render of one of the built in scenes, counting every ray traced.
+fn render(name: &str, width: i32, samples_per_pixel: i32) -> Measurement {
+	let mut scene = load_scene(name).unwrap();
+	scene.camera.image_width = width;
+	scene.camera.samples_per_pixel = samples_per_pixel;
+	scene.camera.seed = 1;
+	scene.camera.threads = 1;
+	scene.camera.initialize();
+	return measure(&format!("render_{name}"), "rays/s", || {
+		let (framebuffer, stats) = scene.camera.render_with_stats(&scene.world);
+		black_box(framebuffer);
+		return stats.total_rays();
+	});
+}
+
+fn main() {
+	//cargo bench passes --bench, anything else is a filter on the names.
+	let filter: Option<String> = std::env::args().skip(1).find(|arg| !arg.starts_with("--"));
+	let benchmarks: Vec<Benchmark> = vec![
+		("vector_arithmetic", Box::new(vector_arithmetic)),
+		("sphere_hit", Box::new(sphere_hit)),
+		("triangle_hit", Box::new(triangle_hit)),
+		("hittable_list_hit_500_spheres", Box::new(hittable_list_hit)),
+		("bvh_hit_500_spheres", Box::new(bvh_hit)),
+		("bvh_hit_many_instances", Box::new(bvh_instances_hit)),
+		("render_three_spheres", Box::new(|| render("three_spheres", 96, 4))),
+		("render_cornell_box", Box::new(|| render("cornell_box", 64, 4))),
+		("render_random_spheres", Box::new(|| render("random_spheres", 96, 4))),
+	];
+
+	println!("{:<32} {:>12} {:>8} {:>16}", "benchmark", "median", "spread", "throughput");
+	for (name, benchmark) in &benchmarks {
+		if let Some(filter) = &filter
+			&& !name.contains(filter.as_str()) {
+			continue;
+		}
+		let measurement = benchmark();
+		println!(
+			"{:<32} {:>9.3} ms {:>7.1}% {:>12.3e} {}",
+			measurement.name,
+			measurement.median().as_secs_f64() * 1000.0,
+			measurement.spread() * 100.0,
+			measurement.throughput(),
+			measurement.unit
+		);
+	}
+}
@@ -0,0 +1,218 @@
+use crate::Ray;
+use crate::Vector;
+use crate::Interval;
+use crate::aabb::Aabb;
+use crate::Point;
+use crate::hittable::{HitRecord, Hittable, SurfaceSample};
+use crate::sampler::Sampler;
+use crate::stats;
+
+//leaves with this many objects or fewer aren't split any further.
+const MAX_LEAF_SIZE: usize = 2;
+
+//A bounding volume hierarchy over a list of boxes, stored as a flat array of nodes.
+//It only knows the boxes, the caller does the actual hit tests by index,
+//so HittableList and Bvh can share it.
+#[derive(Debug, Clone)]
+pub struct BvhTree {
+	nodes: Vec<BvhNode>,
+	//object indices, each leaf owns a contiguous range of them.
+	order: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct BvhNode {
+	bbox: Aabb,
+	//leaves have a count, their objects are order[first..first + count].
+	//inner nodes have a count of 0, the first child is right after them and the second at second_child.
+	first: usize,
+	count: usize,
+	second_child: usize,
+	//the axis the children were split along.
+	axis: u32,
+}
+
+impl BvhTree {
+	pub fn new(boxes: &[Aabb]) -> BvhTree {
+		let mut tree = BvhTree {
+			nodes: Vec::new(),
+			order: (0..boxes.len()).collect(),
+		};
+		if !boxes.is_empty() {
+			tree.build(boxes, 0, boxes.len());
+		}
+		return tree;
+	}
+
+	//Splits order[start..end] in half along the longest axis of their centers, and returns the node's index.
+	fn build(&mut self, boxes: &[Aabb], start: usize, end: usize) -> usize {
+		let mut bbox = Aabb::empty();
+		let mut centroids = Aabb::empty();
+		for &index in &self.order[start..end] {
+			bbox = Aabb::enclosing(&bbox, &boxes[index]);
+			let centroid = boxes[index].centroid();
+			centroids = Aabb::enclosing(&centroids, &Aabb::from_points(&centroid, &centroid));
+		}
+
+		let node_index = self.nodes.len();
+		self.nodes.push(BvhNode {
+			bbox,
+			first: start,
+			count: end - start,
+			second_child: 0,
+			axis: 0,
+		});
+		if end - start <= MAX_LEAF_SIZE {
+			return node_index;
+		}
+
+		let axis = centroids.longest_axis();
+		self.order[start..end].sort_by(|a, b| boxes[*a].centroid()[axis].total_cmp(&boxes[*b].centroid()[axis]));
+		let middle = start + (end - start) / 2;
+
+		self.build(boxes, start, middle);
+		let second_child = self.build(boxes, middle, end);
+		let node = &mut self.nodes[node_index];
+		node.count = 0;
+		node.second_child = second_child;
+		node.axis = axis;
+		return node_index;
+	}
+
+	pub fn bounding_box(&self) -> Aabb {
+		match self.nodes.first() {
+			Some(root) => return root.bbox,
+			None => return Aabb::empty(),
+		}
+	}
+
+	//Walks the tree front to back and calls hit_object for every object whose box the ray passes,
+	//with the part of ray_t that's still closer than the closest hit so far.
+	//hit_object returns the t of its hit, if there is one. returns whether anything was hit.
+	pub fn closest_hit<F>(&self, r: &Ray, ray_t: Interval, mut hit_object: F) -> bool
+	where
+		F: FnMut(usize, Interval) -> Option<f64>,
+	{
+		if self.nodes.is_empty() {
+			return false;
+		}
+		let mut hit_anything = false;
+		let mut closest_so_far = ray_t.max;
+		let inverse_dir = Vector::new(1.0 / r.dir.x, 1.0 / r.dir.y, 1.0 / r.dir.z);
+		let mut node_visits: u64 = 0;
+		let mut intersection_tests: u64 = 0;
+
+		//the tree is balanced, so this is far deeper than it can get.
+		let mut stack = [0usize; 64];
+		let mut stack_size = 1;
+		while stack_size > 0 {
+			stack_size -= 1;
+			let node_index = stack[stack_size];
+			let node = &self.nodes[node_index];
+			node_visits += 1;
+			if !node.bbox.hit_inverse(&r.origin, &inverse_dir, Interval::new(ray_t.min, closest_so_far)) {
+				continue;
+			}
+
+			if node.count > 0 {
+				intersection_tests += node.count as u64;
+				for &index in &self.order[node.first..node.first + node.count] {
+					if let Some(t) = hit_object(index, Interval::new(ray_t.min, closest_so_far)) {
+						hit_anything = true;
+						closest_so_far = t;
+					}
+				}
+				continue;
+			}
+
+			//visit the child on the side the ray comes from first, its hits can cull the other one.
+			let first_child = node_index + 1;
+			let mut near = first_child;
+			let mut far = node.second_child;
+			if r.dir[node.axis] < 0.0 {
+				(near, far) = (far, near);
+			}
+			stack[stack_size] = far;
+			stack[stack_size + 1] = near;
+			stack_size += 2;
+		}
+
+		stats::count(|counters| {
+			counters.bvh_node_visits += node_visits;
+			counters.intersection_tests += intersection_tests;
+		});
+		return hit_anything;
+	}
+}
+
+//A group of objects behind their own bvh that can be used as a single object,
+//for a mesh that gets instanced or the sides of a box.
+//if anything in it emits, the whole group is one light to the scene, made of the parts that emit.
+pub struct Bvh {
+	objects: Vec<Box<dyn Hittable>>,
+	tree: BvhTree,
+	//indices into objects of everything that emits light.
+	lights: Vec<usize>,
+}
+
+impl Bvh {
+	pub fn new(objects: Vec<Box<dyn Hittable>>) -> Bvh {
+		let boxes: Vec<Aabb> = objects.iter().map(|object| object.bounding_box()).collect();
+		let lights: Vec<usize> = (0..objects.len()).filter(|&index| objects[index].is_emissive()).collect();
+		Bvh {
+			objects,
+			tree: BvhTree::new(&boxes),
+			lights,
+		}
+	}
+
+	//the object the ray hits first, and where.
+	fn closest(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> Option<usize> {
+		let mut temp_rec = HitRecord::default();
+		let mut closest = None;
+		self.tree.closest_hit(r, ray_t, |index, ray_t| {
+			if !self.objects[index].hit(r, ray_t, &mut temp_rec) {
+				return None;
+			}
+			*rec = temp_rec;
+			closest = Some(index);
+			return Some(temp_rec.t);
+		});
+		return closest;
+	}
+}
+
+impl Hittable for Bvh {
+	fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+		return self.closest(r, ray_t, rec).is_some();
+	}
+
+	fn bounding_box(&self) -> Aabb {
+		return self.tree.bounding_box();
+	}
+
+	//the same as HittableList does for the whole scene: one of the parts that emit is picked uniformly.
+	fn is_emissive(&self) -> bool {
+		return !self.lights.is_empty();
+	}
+	fn sample_surface(&self, origin: &Point, sampler: &mut Sampler) -> Option<SurfaceSample> {
+		if self.lights.is_empty() {
+			return None;
+		}
+		let count = self.lights.len();
+		let index = ((sampler.get_1d() * count as f64) as usize).min(count - 1);
+		let mut sample = self.objects[self.lights[index]].sample_surface(origin, sampler)?;
+		sample.pdf /= count as f64;
+		return Some(sample);
+	}
+	fn surface_pdf(&self, origin: &Point, direction: &Vector) -> f64 {
+		let mut rec = HitRecord::default();
+		let Some(hit) = self.closest(&Ray::new(*origin, *direction), Interval::new(0.001, f64::INFINITY), &mut rec) else {
+			return 0.0;
+		};
+		if !self.lights.contains(&hit) {
+			return 0.0;
+		}
+		return self.objects[hit].surface_pdf(origin, direction) / self.lights.len() as f64;
+	}
+}
@@ -6,125 +6,683 @@ use crate::Vector;
 use crate::Interval;
 use crate::material::*;
 
+use crate::framebuffer::{Framebuffer, FilmTile};
+use crate::aov::AovSample;
+use crate::exr::{ExrAttribute, ExrCompression, ExrPixelType};
+use crate::filter::Filter;
+use crate::denoiser::Denoiser;
+use crate::checkpoint::{render_settings, Checkpoint};
+use crate::sampler::{Sampler, SamplerKind};
+use crate::sampling;
+use crate::stats;
+use crate::stats::{RenderStats, TileStats};
+
 use std::fs::File;
-use std::io::prelude::*;
+use std::io::{Error, ErrorKind};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Instant;
 
-use crate::color::write_color;
 use crate::degrees_to_radians;
 
-use crate::random_f64;
+const TILE_SIZE: i32 = 16;
+
+//What a ray sees when it leaves the scene without hitting anything.
+#[derive(Debug, Clone, Copy)]
+pub enum Background {
+	//the white to blue gradient.
+	Sky,
+	Solid(Color),
+}
+
+//Stops sampling a pixel once the 95% confidence interval of its brightness is small enough,
+//so flat areas finish early and noisy ones get up to max_samples.
+#[derive(Debug, Clone, Copy)]
+pub struct AdaptiveSampling {
+	pub min_samples: i32,
+	pub max_samples: i32,
+	//the largest allowed half width of the interval, relative to the pixel's brightness.
+	pub tolerance: f64,
+}
+
+//How many bounces of each kind a path can make, on top of max_depth which limits all of them together.
+//specular covers mirror and glossy reflection, transmission is light going through glass,
+//and volume is scattering inside a medium.
+#[derive(Debug, Clone, Copy)]
+pub struct DepthLimits {
+	pub diffuse: i32,
+	pub specular: i32,
+	pub transmission: i32,
+	pub volume: i32,
+}
+
+impl Default for DepthLimits {
+	//no limits, only max_depth applies.
+	fn default() -> Self {
+		DepthLimits {
+			diffuse: i32::MAX,
+			specular: i32::MAX,
+			transmission: i32::MAX,
+			volume: i32::MAX,
+		}
+	}
+}
+
+//Ends paths at random once they've made min_depth bounces, with a chance that grows as less
+//light can still come back along them. This costs some noise and adds no bias,
+//so max_depth can be raised a lot without deep paths costing much.
+#[derive(Debug, Clone, Copy)]
+pub struct RussianRoulette {
+	pub min_depth: i32,
+}
+
+//Once a path has bounced off something diffuse, mirrors and glass it meets afterwards reflect and
+//refract into a cone angle degrees wide instead of a single direction. Light sampling can then find
+//caustics that would otherwise only show up as fireflies, at the cost of blurring them a little.
+#[derive(Debug, Clone, Copy)]
+pub struct PathRegularization {
+	pub angle: f64,
+}
+
+//even the brightest paths end with at least this much chance, so none can go on forever.
+const MAX_SURVIVAL: f64 = 0.95;
 
 pub struct Camera {
 	pub aspect_ratio: f64,
 	pub image_width: i32,
 	pub samples_per_pixel: i32,
+	pub background: Background,
+	//every render with the same seed gives exactly the same image.
+	pub seed: u64,
+	//0 uses one thread per core.
+	pub threads: usize,
+	pub sampler: SamplerKind,
+	//None takes exactly samples_per_pixel samples everywhere.
+	pub adaptive: Option<AdaptiveSampling>,
+	//how samples are weighted into the pixels around them.
+	pub filter: Filter,
+	//cleans up the finished image, the checkpoints still keep the raw samples.
+	pub denoiser: Option<Denoiser>,
+	//render and the progressive renders write their statistics here as JSON when it's set.
+	pub stats_path: Option<String>,
+	//prints the tiles and passes as they finish and the statistics at the end, off by default so the library stays quiet.
+	pub progress: bool,
+	//the most segments a path can have.
+	pub max_depth: i32,
+	pub depth_limits: DepthLimits,
+	//None traces every path until it's absorbed, escapes or runs into a depth limit.
+	pub russian_roulette: Option<RussianRoulette>,
+	//the brightest any channel of a sample's indirect light can be, None leaves it alone.
+	//clamping trades fireflies for a slightly darker image.
+	pub firefly_clamp: Option<f64>,
+	pub path_regularization: Option<PathRegularization>,
+
+	//where the camera is and what it looks at, call initialize after changing these.
+	pub vfov: f64,
+	pub lookfrom: Point,
+	pub lookat: Point,
+	pub vup: Vector,
+	//the angle in degrees of the cone from each pixel to the lens, 0 keeps everything sharp.
+	pub defocus_angle: f64,
+	//how far away things are in perfect focus, 0 focuses on lookat.
+	pub focus_dist: f64,
 
-	pixel_color_scale: f64,
-	max_depth: i32,
-	vfov: f64,
 	image_height: i32,
 	center: Point,
 	pixel00_loc: Point,
 	pixel_delta_u: Vector,
 	pixel_delta_v: Vector,
+	defocus_disk_u: Vector,
+	defocus_disk_v: Vector,
 }
 
 impl Camera {
+	//Renders into file, and prints a summary of the render statistics when progress is on.
 	pub fn render(&self, world: &HittableList, file: &mut File) {
-		//self.initialize();
+		let (framebuffer, stats) = self.render_with_stats(world);
+		framebuffer.write_ppm(file);
+		if let Err(error) = self.report(&stats) {
+			eprintln!("couldn't write the render statistics: {error}");
+		}
+	}
+
+	pub fn render_framebuffer(&self, world: &HittableList) -> Framebuffer {
+		return self.render_with_stats(world).0;
+	}
+
+	pub fn render_with_stats(&self, world: &HittableList) -> (Framebuffer, RenderStats) {
+		let mut framebuffer = Framebuffer::new(self.image_width, self.image_height);
+		let stats = self.render_pass(world, &mut framebuffer, 0, self.max_samples());
+		return (self.post_process(&framebuffer), stats);
+	}
+
+	//prints the summary table when progress is on, and writes the stats file if there is one.
+	fn report(&self, stats: &RenderStats) -> std::io::Result<()> {
+		if self.progress {
+			print!("{}", stats.summary());
+		}
+		if let Some(path) = &self.stats_path {
+			stats.write_json(path)?;
+		}
+		return Ok(());
+	}
+
+	//Writes the image and all of its passes to one EXR file, with how it was rendered in the header.
+	pub fn write_exr(&self, framebuffer: &Framebuffer, file: &mut File, pixel_type: ExrPixelType, compression: ExrCompression) -> std::io::Result<()> {
+		let mut image = framebuffer.to_exr(pixel_type, compression);
+
+		//the camera looks down its -z axis with y up, the same as EXR expects.
+		let u: Vector = self.pixel_delta_u.normalize();
+		let v: Vector = self.pixel_delta_v.normalize() * -1.0;
+		let w: Vector = Vector::cross(&u, &v);
+		let world_to_camera: [f64; 16] = [
+			u.x, v.x, w.x, 0.0,
+			u.y, v.y, w.y, 0.0,
+			u.z, v.z, w.z, 0.0,
+			-Vector::dot(&self.center, &u), -Vector::dot(&self.center, &v), -Vector::dot(&self.center, &w), 1.0,
+		];
+		image.add_attribute("worldToCamera", ExrAttribute::M44f(world_to_camera.map(|value| value as f32)));
+		image.add_attribute("cameraPosition", ExrAttribute::V3f(self.center));
+		image.add_attribute("verticalFov", ExrAttribute::Float(self.vfov as f32));
+		image.add_attribute("samplesPerPixel", ExrAttribute::Int(self.max_samples()));
+		image.add_attribute("maxDepth", ExrAttribute::Int(self.max_depth));
+		image.add_attribute("sampler", ExrAttribute::String(format!("{:?}", self.sampler)));
+		image.add_attribute("seed", ExrAttribute::String(self.seed.to_string()));
+		//both of these bias the image, so it should say so.
+		if let Some(limit) = self.firefly_clamp {
+			image.add_attribute("fireflyClamp", ExrAttribute::Float(limit as f32));
+		}
+		if let Some(regularization) = self.path_regularization {
+			image.add_attribute("pathRegularizationAngle", ExrAttribute::Float(regularization.angle as f32));
+		}
+		return image.write(file);
+	}
+
+	//the image as it gets written out, denoised if the camera has a denoiser.
+	fn post_process(&self, framebuffer: &Framebuffer) -> Framebuffer {
+		match &self.denoiser {
+			Some(denoiser) => return denoiser.denoise(framebuffer),
+			None => return framebuffer.clone(),
+		}
+	}
+
+	//Renders the whole image in passes of samples_per_pass samples per pixel,
+	//and overwrites the image at path after each one so the render can be watched converging.
+	//stopping early just leaves the last finished pass on disk.
+	pub fn render_progressive(&self, world: &HittableList, path: &str, samples_per_pass: i32) -> std::io::Result<Framebuffer> {
+		let mut framebuffer = Framebuffer::new(self.image_width, self.image_height);
+		self.render_passes(world, &mut framebuffer, 0..self.max_samples(), samples_per_pass, path, None)?;
+		return Ok(self.post_process(&framebuffer));
+	}
+
+	//Like render_progressive, but also saves a checkpoint after every pass.
+	//If checkpoint_path already holds one from the same camera the render picks up from it,
+	//and the finished image is the same as if it had never been stopped.
+	pub fn render_resumable(&self, world: &HittableList, path: &str, checkpoint_path: &str, samples_per_pass: i32) -> std::io::Result<Framebuffer> {
+		return self.render_resumable_for(world, path, checkpoint_path, samples_per_pass, i32::MAX);
+	}
+
+	//Like render_resumable, but stops after at most passes passes and leaves the checkpoint for the
+	//next call to carry on from, for render queues that only give a job so much time at once.
+	pub fn render_resumable_for(&self, world: &HittableList, path: &str, checkpoint_path: &str, samples_per_pass: i32, passes: i32) -> std::io::Result<Framebuffer> {
+		let mut framebuffer = Framebuffer::new(self.image_width, self.image_height);
+		let mut first_sample = 0;
+
+		if Path::new(checkpoint_path).exists() {
+			let checkpoint = Checkpoint::load(checkpoint_path)?;
+			if checkpoint.seed != self.seed
+				|| checkpoint.sampler != self.sampler
+				|| checkpoint.samples_per_pixel != self.max_samples()
+				|| checkpoint.samples_per_pass != samples_per_pass.max(1)
+				|| checkpoint.settings != render_settings(self)
+				|| checkpoint.framebuffer.width != self.image_width
+				|| checkpoint.framebuffer.height != self.image_height {
+				return Err(Error::new(ErrorKind::InvalidInput, "checkpoint was made with different camera settings"));
+			}
+			if self.progress {
+				println!("Resuming From Sample: {}", checkpoint.next_sample);
+			}
+			first_sample = checkpoint.next_sample;
+			framebuffer = checkpoint.framebuffer;
+		}
+
+		let last_sample = first_sample.saturating_add(passes.max(0).saturating_mul(samples_per_pass.max(1))).min(self.max_samples());
+		self.render_passes(world, &mut framebuffer, first_sample..last_sample, samples_per_pass, path, Some(checkpoint_path))?;
+		return Ok(self.post_process(&framebuffer));
+	}
+
+	//adds samples in passes until the framebuffer has had all of the ones in samples.
+	fn render_passes(&self, world: &HittableList, framebuffer: &mut Framebuffer, samples: Range<i32>, samples_per_pass: i32, path: &str, checkpoint_path: Option<&str>) -> std::io::Result<()> {
+		let samples_per_pass = samples_per_pass.max(1);
+		let total = self.max_samples();
+
+		let mut stats = RenderStats::default();
+		let mut first_sample = samples.start;
+		while first_sample < samples.end {
+			let last_sample = (first_sample + samples_per_pass).min(samples.end);
+			stats.add(&self.render_pass(world, framebuffer, first_sample, last_sample));
+			self.post_process(framebuffer).write_ppm(&mut File::create(path)?);
+
+			if let Some(checkpoint_path) = checkpoint_path {
+				let checkpoint = Checkpoint {
+					seed: self.seed,
+					sampler: self.sampler,
+					samples_per_pixel: total,
+					samples_per_pass,
+					next_sample: last_sample,
+					settings: render_settings(self),
+					framebuffer: framebuffer.clone(),
+				};
+				checkpoint.save(checkpoint_path)?;
+			}
+			if self.progress {
+				println!("Samples Done: {} / {}", last_sample, total);
+			}
+			first_sample = last_sample;
+		}
+		return self.report(&stats);
+	}
+
+	//Adds samples first_sample..last_sample to every pixel of the framebuffer.
+	//The image is split into tiles that worker threads pick up as they finish.
+	//each sample gets its own random sequence and the tiles are merged in a fixed order
+	//once they're all done, so the thread count doesn't change the result.
+	pub fn render_pass(&self, world: &HittableList, framebuffer: &mut Framebuffer, first_sample: i32, last_sample: i32) -> RenderStats {
+		let start = Instant::now();
+		let tiles = self.tiles();
+		let next_tile = AtomicUsize::new(0);
+		let finished: Mutex<Vec<Option<(FilmTile, RenderStats)>>> = Mutex::new((0..tiles.len()).map(|_| None).collect());
+		let current: &Framebuffer = framebuffer;
+
+		thread::scope(|scope| {
+			for _ in 0..self.thread_count() {
+				scope.spawn(|| {
+					let mut sampler = Sampler::new(self.sampler, self.seed, self.max_samples());
+					loop {
+						let tile_index = next_tile.fetch_add(1, Ordering::Relaxed);
+						if tile_index >= tiles.len() {
+							break;
+						}
+						let tile = &tiles[tile_index];
+						let mut film_tile = FilmTile::new(current, tile.x0, tile.y0, tile.x1, tile.y1, self.filter.margin());
+						let tile_start = Instant::now();
+						stats::take_counters();
+						self.render_tile(tile, world, &mut sampler, &mut film_tile, first_sample, last_sample);
+						let counters = stats::take_counters();
+						let tile_stats = RenderStats {
+							counters,
+							wall_time: Default::default(),
+							tiles: vec![TileStats {
+								x0: tile.x0,
+								y0: tile.y0,
+								x1: tile.x1,
+								y1: tile.y1,
+								samples: counters.primary_rays,
+								time: tile_start.elapsed(),
+							}],
+						};
+
+						finished.lock().unwrap()[tile_index] = Some((film_tile, tile_stats));
+						if self.progress {
+							println!("Tiles Remaining: {}", tiles.len() - tile_index - 1);
+						}
+					}
+				});
+			}
+		});
 
-		let _ = file.write_all(format!("P3\n{0} {1}\n255\n", self.image_width, self.image_height).as_bytes());
+		let finished = finished.into_inner().unwrap();
+		let mut stats = RenderStats::default();
+		for (_, tile_stats) in finished.iter().flatten() {
+			stats.add(tile_stats);
+		}
+		let film_tiles: Vec<&FilmTile> = finished.iter().flatten().map(|(film_tile, _)| film_tile).collect();
+		framebuffer.merge_tiles(&film_tiles);
+		stats.wall_time = start.elapsed();
+		return stats;
+	}
+
+	fn render_tile(&self, tile: &Tile, world: &HittableList, sampler: &mut Sampler, film_tile: &mut FilmTile, first_sample: i32, last_sample: i32) {
+		for j in tile.y0..tile.y1 {
+			for i in tile.x0..tile.x1 {
+				for sample in first_sample..last_sample {
+					let pixel = film_tile.pixel_mut(i, j);
+					if let Some(adaptive) = self.adaptive
+						&& pixel.count >= adaptive.min_samples
+						&& pixel.converged(adaptive.tolerance) {
+						break;
+					}
+					sampler.start_pixel_sample(i, j, sample);
+					let offset: Point = Self::sample_square(sampler);
+					let r: Ray = self.get_ray(i, j, &offset, sampler);
+					let mut aov = AovSample::default();
+					let sample_color = self.ray_color(&r, world, sampler, Some(&mut aov));
 
-		for j in 0..self.image_height {
-			println!("Scanlines Remaining: {}", (self.image_height - j));
-			for i in 0..self.image_width {
-				let mut pixel_color: Color = Color::new(0.0, 0.0, 0.0);
-				for sample in 0..self.samples_per_pixel {
-					let r: Ray = self.get_ray(i, j);
-					pixel_color += Self::ray_color(&r, self.max_depth, world);
+					film_tile.pixel_mut(i, j).add_sample(sample_color, &aov);
+					film_tile.splat(i as f64 + 0.5 + offset.x, j as f64 + 0.5 + offset.y, sample_color, &aov, &self.filter);
 				}
-				pixel_color = pixel_color * self.pixel_color_scale;
-				write_color(&pixel_color, file);
 			}
 		}
 	}
 
-	pub fn new(aspect_ratio: f64, image_width: i32, samples_per_pixel: i32) -> Camera {
+	fn max_samples(&self) -> i32 {
+		match self.adaptive {
+			Some(adaptive) => return adaptive.max_samples.max(1),
+			None => return self.samples_per_pixel,
+		}
+	}
+
+	fn tiles(&self) -> Vec<Tile> {
+		let mut tiles: Vec<Tile> = Vec::new();
+		for y0 in (0..self.image_height).step_by(TILE_SIZE as usize) {
+			for x0 in (0..self.image_width).step_by(TILE_SIZE as usize) {
+				tiles.push(Tile {
+					x0,
+					y0,
+					x1: (x0 + TILE_SIZE).min(self.image_width),
+					y1: (y0 + TILE_SIZE).min(self.image_height),
+				});
+			}
+		}
+		return tiles;
+	}
 
-		let pixel_color_scale = 1.0 / samples_per_pixel as f64;
+	fn thread_count(&self) -> usize {
+		if self.threads > 0 {
+			return self.threads;
+		}
+		return thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+	}
+
+	pub fn new(aspect_ratio: f64, image_width: i32, samples_per_pixel: i32) -> Camera {
+		let mut camera = Camera {
+			aspect_ratio,
+			image_width,
+			samples_per_pixel,
+			background: Background::Sky,
+			seed: 0,
+			threads: 0,
+			sampler: SamplerKind::Sobol,
+			adaptive: None,
+			filter: Filter::default(),
+			denoiser: None,
+			stats_path: None,
+			progress: false,
+			max_depth: 10,
+			depth_limits: DepthLimits::default(),
+			russian_roulette: None,
+			firefly_clamp: None,
+			path_regularization: None,
+			vfov: 90.0,
+			lookfrom: Point::null_vector(),
+			lookat: Point::new(0.0, 0.0, -1.0),
+			vup: Vector::new(0.0, 1.0, 0.0),
+			defocus_angle: 0.0,
+			focus_dist: 0.0,
+			image_height: 1,
+			center: Point::null_vector(),
+			pixel00_loc: Point::null_vector(),
+			pixel_delta_u: Vector::null_vector(),
+			pixel_delta_v: Vector::null_vector(),
+			defocus_disk_u: Vector::null_vector(),
+			defocus_disk_v: Vector::null_vector(),
+		};
+		camera.initialize();
+		return camera;
+	}
 
-		let mut image_height = (image_width as f64 / aspect_ratio) as i32;
-		if image_height < 1 {
-			image_height = 1;
+	//Works out the image height and where each pixel is from the public settings.
+	pub fn initialize(&mut self) {
+		self.image_height = (self.image_width as f64 / self.aspect_ratio) as i32;
+		if self.image_height < 1 {
+			self.image_height = 1;
 		}
 
-		let center = Point::null_vector();
+		self.center = self.lookfrom;
 
 		// Determine viewport dimensions
-		let focal_length = 1.0;
-		let vfov = 90.0;
-		let theta = degrees_to_radians(vfov);
+		let mut focus_dist = self.focus_dist;
+		if focus_dist <= 0.0 {
+			focus_dist = (self.lookfrom - self.lookat).length();
+		}
+		let theta = degrees_to_radians(self.vfov);
 		let h = f64::tan(theta/2.0);
-		let viewport_height = 2.0 * h * focal_length;
-		let viewport_width = viewport_height * aspect_ratio;
+		let viewport_height = 2.0 * h * focus_dist;
+		//the real ratio of the image, the height got rounded down so it can differ from aspect_ratio.
+		let viewport_width = viewport_height * (self.image_width as f64 / self.image_height as f64);
+
+		// the camera's basis, it looks along -w with v up and u to the right.
+		let w: Vector = (self.lookfrom - self.lookat).normalize();
+		let u: Vector = self.vup.cross(&w).normalize();
+		let v: Vector = w.cross(&u);
 
 		// Calculate the vectors across the horizontal and down the vertical viewport edges
-		let viewport_u = Vector::pos_x_vector() * viewport_width;
-		let viewport_v = Vector::neg_y_vector() * viewport_height;
+		let viewport_u = u * viewport_width;
+		let viewport_v = v * -viewport_height;
 
 		// Calculate the horizontal and vertical delta vectors from pixel to pixel
-		let pixel_delta_u = viewport_u / image_width;
-		let pixel_delta_v = viewport_v / image_height;
+		self.pixel_delta_u = viewport_u / self.image_width;
+		self.pixel_delta_v = viewport_v / self.image_height;
 
-		let viewport_upper_left: Point = center - Point::new(0.0, 0.0, focal_length) - (viewport_u/2) - (viewport_v/2);
-		let pixel00_loc: Point = viewport_upper_left + 0.5 * (pixel_delta_u + pixel_delta_v);
+		let viewport_upper_left: Point = self.center - (focus_dist * w) - (viewport_u/2) - (viewport_v/2);
+		self.pixel00_loc = viewport_upper_left + 0.5 * (self.pixel_delta_u + self.pixel_delta_v);
 
+		let defocus_radius = focus_dist * f64::tan(degrees_to_radians(self.defocus_angle / 2.0));
+		self.defocus_disk_u = u * defocus_radius;
+		self.defocus_disk_v = v * defocus_radius;
+	}
 
-		Camera {
-			aspect_ratio,
-			image_width,
-			samples_per_pixel,
-			pixel_color_scale,
-			image_height,
-			max_depth: 10,
-			vfov,
-			center,
-			pixel00_loc,
-			pixel_delta_u,
-			pixel_delta_v,
+	pub fn image_height(&self) -> i32 {
+		return self.image_height;
+	}
+
+	//The light coming back along a camera ray, with the firefly clamp applied.
+	//aov gets what the camera ray hit and how its light splits up.
+	fn ray_color(&self, r: &Ray, world: &HittableList, sampler: &mut Sampler, mut aov: Option<&mut AovSample>) -> Color {
+		let mut light = self.trace_path(r, world, sampler, aov.as_deref_mut());
+		//only indirect light gets clamped, fireflies come from long unlikely paths
+		//and direct light is already well sampled.
+		if let Some(limit) = self.firefly_clamp {
+			let peak = light.indirect.x.max(light.indirect.y).max(light.indirect.z);
+			if peak > limit {
+				light.indirect *= limit / peak;
+				stats::count(|counters| counters.clamped_samples += 1);
+			}
 		}
+		if let Some(aov) = aov {
+			aov.emission = light.emission;
+			aov.direct = light.direct;
+			aov.indirect = light.indirect;
+		}
+		return light.emission + light.direct + light.indirect;
 	}
 
-	fn ray_color(r: &Ray, depth: i32, world: &HittableList) -> Color {
-		//let t = hit_sphere(&Point::new(0.0 , 0.0, -1.5), 0.5, r);
-		if depth <= 0 {
-			return Color::null_vector();
+	//Follows one path from the camera and returns the light it carries back.
+	//throughput is how much of the light found further along still reaches the camera,
+	//every bounce multiplies it by the sample's weight.
+	fn trace_path(&self, r: &Ray, world: &HittableList, sampler: &mut Sampler, mut aov: Option<&mut AovSample>) -> PathLight {
+		let mut light = PathLight::default();
+		let mut throughput = Color::new(1.0, 1.0, 1.0);
+		let mut ray = *r;
+		//the pdf of the bounce that produced ray, or None when ray came from the camera or a delta lobe
+		//and emission it hits can't have been light sampled.
+		let mut bsdf_pdf: Option<f64> = None;
+		let mut bounces = BounceCounts::default();
+		//set once the path has bounced off something diffuse, see PathRegularization.
+		let mut regularize = false;
+
+		for bounce in 0..self.max_depth {
+			if bounce == 0 {
+				stats::count(|counters| counters.primary_rays += 1);
+			} else {
+				stats::count(|counters| counters.secondary_rays += 1);
+			}
+
+			let mut rec = HitRecord::default();
+			if !world.hit(&ray, Interval::new(0.001, f64::INFINITY), &mut rec) {
+				light.add(bounce, throughput * self.background_color(&ray));
+				return light;
+			}
+			sampler.start_bounce(bounce);
+
+			let mut emitted = rec.material.emitted(&rec);
+			if let Some(pdf) = bsdf_pdf {
+				//this light could also have been reached by light sampling at the previous hit.
+				let light_pdf = world.light_pdf(rec.object_id as usize, &ray.origin, &ray.dir);
+				emitted *= power_heuristic(pdf, light_pdf);
+			}
+			light.add(bounce, throughput * emitted);
+
+			if bounce == 0
+				&& let Some(aov) = aov.as_deref_mut() {
+				aov.hit = true;
+				aov.depth = rec.t * ray.dir.length();
+				aov.normal = rec.normal;
+				aov.albedo = rec.material.albedo(&rec);
+				aov.object_id = rec.object_id;
+				aov.material_id = rec.material.id();
+			}
+
+			if regularize
+				&& let Some(regularization) = self.path_regularization
+				&& let Some(material) = rec.material.regularized(degrees_to_radians(regularization.angle).cos()) {
+				rec.material = material;
+				stats::count(|counters| counters.regularized_bounces += 1);
+			}
+
+			let Some(sample) = rec.material.sample(&ray, &rec, sampler) else {
+				return light;
+			};
+			if sample.lobe.contains(LobeFlags::DIFFUSE) {
+				regularize = true;
+			}
+
+			//light sampled here comes one segment further than this hit, the same as whatever the bsdf
+			//sample hits next. So it's only looked for when the path may carry on, otherwise the light
+			//sampled half of the weighted pair would be counted without the bsdf sampled half.
+			if bounce + 1 >= self.max_depth || !bounces.add(sample.lobe, &self.depth_limits) {
+				stats::count(|counters| counters.max_depth_terminations += 1);
+				return light;
+			}
+
+			bsdf_pdf = None;
+			if !sample.lobe.is_delta() {
+				let mut direct = self.sample_direct(&ray, &rec, world, sampler);
+				direct += self.sample_punctual(&ray, &rec, world, sampler);
+				light.add(bounce + 1, throughput * direct);
+				bsdf_pdf = Some(sample.pdf);
+			}
+			throughput = throughput * sample.weight;
+			ray = Ray::new(rec.hit_point, sample.direction);
+
+			//Russian roulette, paths that can't carry much light any more stop at random and the ones
+			//that go on are made brighter to make up for the ones that didn't, which keeps it unbiased.
+			if let Some(roulette) = self.russian_roulette
+				&& bounce + 1 >= roulette.min_depth {
+				let survival = throughput.x.max(throughput.y).max(throughput.z).min(MAX_SURVIVAL);
+				if survival <= 0.0 || sampler.get_1d() >= survival {
+					stats::count(|counters| counters.roulette_terminations += 1);
+					return light;
+				}
+				throughput /= survival;
+			}
 		}
-		let mut rec = HitRecord::default();
 
-		if world.hit(r, Interval::new(0.001, f64::INFINITY), &mut rec) {
-			let mut scattered = Ray::new(Vector::null_vector(), Vector::null_vector());
-			let mut attenuation = Color::null_vector();
+		stats::count(|counters| counters.max_depth_terminations += 1);
+		return light;
+	}
 
-			if rec.material.scatter(r, &rec, &mut attenuation, &mut scattered) {
-				return attenuation * Self::ray_color(&scattered, depth - 1, world);
+	fn background_color(&self, r: &Ray) -> Color {
+		match self.background {
+			Background::Solid(color) => return color,
+			Background::Sky => {
+				let unit_dir : Vector = Vector::normalize(&r.dir);
+				let a: f64 = 0.5 * (unit_dir.y + 1.0);
+				return ((1.0 - a) * Color::new(1.0,1.0,1.0)) + (a * Color::new(0.5, 0.7, 1.0));
 			}
+		}
+	}
+
+	//Next event estimation, samples a point on a light and traces a shadow ray to it.
+	//weighted against bsdf sampling with the power heuristic.
+	fn sample_direct(&self, r: &Ray, rec: &HitRecord, world: &HittableList, sampler: &mut Sampler) -> Color {
+		let Some(light) = world.sample_light(&rec.hit_point, sampler) else {
+			return Color::null_vector();
+		};
+		if light.pdf <= 0.0 {
+			return Color::null_vector();
+		}
+
+		let to_light: Vector = light.point - rec.hit_point;
+		let distance = to_light.length();
+		let wi: Vector = to_light / distance;
+		let cos_theta = Self::cosine(&wi, rec);
+		if cos_theta <= 0.0 {
 			return Color::null_vector();
+		}
 
-			//let direction: Vector = rec.normal + Vector::random_normal_vector();
-			//return 0.5 * Self::ray_color(&Ray::new(rec.hit_point, direction), depth - 1, world);
+		//the first thing the shadow ray hits has to be the sampled point itself.
+		let shadow = Ray::new(rec.hit_point, wi);
+		stats::count(|counters| counters.shadow_rays += 1);
+		let mut light_rec = HitRecord::default();
+		if !world.hit(&shadow, Interval::new(0.001, distance + 0.001), &mut light_rec) {
+			return Color::null_vector();
+		}
+		if light_rec.t < distance - 0.001 * distance.max(1.0) {
+			return Color::null_vector();
 		}
-		/*if t > 0.0 {
-			let n: Vector = (r.at(t) - Vector::new(0.0,0.0,-1.5)).normalize();
-			return 0.5 * (n + Color::unit_vector());
-		}*/
 
-		let unit_dir : Vector = Vector::normalize(&r.dir);
-		let a: f64 = 0.5 * (unit_dir.y + 1.0);
-		return ((1.0 - a) * Color::new(1.0,1.0,1.0)) + (a * Color::new(0.5, 0.7, 1.0));
+		let emitted = light_rec.material.emitted(&light_rec);
+		let wo: Vector = r.dir.normalize() * -1.0;
+		let f = rec.material.eval(&wo, &wi, rec);
+		let weight = power_heuristic(light.pdf, rec.material.pdf(&wo, &wi, rec));
+		return emitted * f * (cos_theta * weight / light.pdf);
 	}
 
-	fn get_ray(&self, i: i32, j: i32) -> Ray {
+	//Point, spot and sun lights can't be hit by bsdf samples, so every one of them gets a shadow ray.
+	fn sample_punctual(&self, r: &Ray, rec: &HitRecord, world: &HittableList, sampler: &mut Sampler) -> Color {
+		let mut color = Color::null_vector();
+		let wo: Vector = r.dir.normalize() * -1.0;
+
+		for light in world.punctual_lights() {
+			let Some(sample) = light.sample_li(&rec.hit_point, sampler) else {
+				continue;
+			};
+			let cos_theta = Self::cosine(&sample.direction, rec);
+			if cos_theta <= 0.0 {
+				continue;
+			}
+
+			let shadow = Ray::new(rec.hit_point, sample.direction);
+			stats::count(|counters| counters.shadow_rays += 1);
+			let mut shadow_rec = HitRecord::default();
+			if world.hit(&shadow, Interval::new(0.001, sample.distance - 0.001), &mut shadow_rec) {
+				continue;
+			}
+
+			color += sample.li * rec.material.eval(&wo, &sample.direction, rec) * cos_theta;
+		}
+		return color;
+	}
+
+	//the cosine term for light arriving from wi, points in a volume don't have one.
+	//light can come through from behind surfaces that transmit.
+	fn cosine(wi: &Vector, rec: &HitRecord) -> f64 {
+		if rec.material.is_volumetric() {
+			return 1.0;
+		}
+		if rec.material.is_transmissive() {
+			return Vector::dot(wi, &rec.normal).abs();
+		}
+		return Vector::dot(wi, &rec.normal);
+	}
+
+	//offset is where in the pixel the ray goes through, from sample_square.
+	//with a defocus angle the ray starts from a random point on the lens disk instead of the center.
+	fn get_ray(&self, i: i32, j: i32, offset: &Point, sampler: &mut Sampler) -> Ray {
 		/*
 		let pixel_center: Vector = self.pixel00_loc + (i * self.pixel_delta_u) + (j * self.pixel_delta_v);
 		let ray_direction: Vector = pixel_center - self.center;
@@ -134,16 +692,87 @@ impl Camera {
 		let pixel_color: Color = Camera::ray_color(&r, world);
 		write_color(&pixel_color, file);*/
 
-		let offset: Point = Self::sample_square();
 		let pixel_sample = self.pixel00_loc + ((i as f64 + offset.x) * self.pixel_delta_u) + ((j as f64 + offset.y) * self.pixel_delta_v);
 
-		let ray_origin: Point = self.center;
+		let mut ray_origin: Point = self.center;
+		if self.defocus_angle > 0.0 {
+			let (x, y) = sampling::uniform_disk(sampler.get_lens_2d());
+			ray_origin = self.center + (x * self.defocus_disk_u) + (y * self.defocus_disk_v);
+		}
 		let ray_direction: Vector = pixel_sample - ray_origin;
 
 		return Ray::new(ray_origin, ray_direction);
 	}
 
-	fn sample_square() -> Point {
-		return Vector::new(random_f64() - 0.5, random_f64() - 0.5, 0.0) * 0.25;
+	//a random point in the [-0.5, 0.5] square around the pixel center.
+	fn sample_square(sampler: &mut Sampler) -> Point {
+		let (x, y) = sampler.get_pixel_2d();
+		return Vector::new(x - 0.5, y - 0.5, 0.0);
+	}
+}
+
+//The light a path brought back, split up by how many segments it took to reach the camera:
+//none for what the camera sees directly, one for direct lighting and more for indirect.
+#[derive(Default)]
+struct PathLight {
+	emission: Color,
+	direct: Color,
+	indirect: Color,
+}
+
+impl PathLight {
+	fn add(&mut self, segments: i32, light: Color) {
+		match segments {
+			0 => self.emission += light,
+			1 => self.direct += light,
+			_ => self.indirect += light,
+		}
 	}
-}
\ No newline at end of file
+}
+
+//How many bounces of each kind a path has made so far.
+#[derive(Default)]
+struct BounceCounts {
+	diffuse: i32,
+	specular: i32,
+	transmission: i32,
+	volume: i32,
+}
+
+impl BounceCounts {
+	//counts a bounce off lobe, false if that goes over its limit.
+	fn add(&mut self, lobe: LobeFlags, limits: &DepthLimits) -> bool {
+		if lobe.contains(LobeFlags::VOLUME) {
+			self.volume += 1;
+			return self.volume <= limits.volume;
+		}
+		if lobe.contains(LobeFlags::TRANSMISSION) {
+			self.transmission += 1;
+			return self.transmission <= limits.transmission;
+		}
+		if lobe.contains(LobeFlags::DIFFUSE) {
+			self.diffuse += 1;
+			return self.diffuse <= limits.diffuse;
+		}
+		self.specular += 1;
+		return self.specular <= limits.specular;
+	}
+}
+
+//A rectangle of pixels, x1 and y1 are exclusive.
+struct Tile {
+	x0: i32,
+	y0: i32,
+	x1: i32,
+	y1: i32,
+}
+
+//Weights a sample from strategy f against strategy g, both given as pdfs.
+fn power_heuristic(f_pdf: f64, g_pdf: f64) -> f64 {
+	let f2 = f_pdf * f_pdf;
+	let g2 = g_pdf * g_pdf;
+	if f2 + g2 == 0.0 {
+		return 0.0;
+	}
+	return f2 / (f2 + g2);
+}
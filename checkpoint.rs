@@ -0,0 +1,254 @@
+use crate::Color;
+use crate::camera::{Background, Camera};
+use crate::filter::Filter;
+use crate::framebuffer::{Framebuffer, PixelAccumulator};
+use crate::sampler::SamplerKind;
+
+use std::fs;
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::{Error, ErrorKind, Result};
+
+const MAGIC: &[u8; 8] = b"RTCHKPT4";
+
+//Everything needed to carry on a render exactly where it stopped.
+//Samples are seeded from (seed, pixel, sample index), so the seed and the next sample index
+//are the whole random number state. The sums are stored bit for bit, which makes a resumed
+//render come out identical to one that was never interrupted, as long as it keeps the same
+//samples per pass so the sums get added up in the same groups.
+pub struct Checkpoint {
+	pub seed: u64,
+	pub sampler: SamplerKind,
+	pub samples_per_pixel: i32,
+	pub samples_per_pass: i32,
+	//every pixel has had all samples before this one.
+	pub next_sample: i32,
+	//the rest of the camera settings that change what a sample comes out as, from render_settings.
+	pub settings: Vec<u8>,
+	pub framebuffer: Framebuffer,
+}
+
+impl Checkpoint {
+	//writes to a temporary file first so a kill in the middle never leaves a broken checkpoint.
+	pub fn save(&self, path: &str) -> Result<()> {
+		let mut bytes: Vec<u8> = Vec::new();
+		bytes.extend_from_slice(MAGIC);
+		bytes.extend_from_slice(&self.framebuffer.width.to_le_bytes());
+		bytes.extend_from_slice(&self.framebuffer.height.to_le_bytes());
+		bytes.extend_from_slice(&self.seed.to_le_bytes());
+		bytes.push(sampler_to_byte(self.sampler));
+		bytes.extend_from_slice(&self.samples_per_pixel.to_le_bytes());
+		bytes.extend_from_slice(&self.samples_per_pass.to_le_bytes());
+		bytes.extend_from_slice(&self.next_sample.to_le_bytes());
+		bytes.extend_from_slice(&(self.settings.len() as i32).to_le_bytes());
+		bytes.extend_from_slice(&self.settings);
+
+		for j in 0..self.framebuffer.height {
+			for i in 0..self.framebuffer.width {
+				let pixel = self.framebuffer.pixel(i, j);
+				for value in [pixel.weighted_sum.x, pixel.weighted_sum.y, pixel.weighted_sum.z, pixel.weight_sum, pixel.mean, pixel.m2] {
+					bytes.extend_from_slice(&value.to_le_bytes());
+				}
+				bytes.extend_from_slice(&pixel.count.to_le_bytes());
+				for color in [pixel.emission_sum, pixel.direct_sum, pixel.indirect_sum, pixel.normal_sum, pixel.albedo_sum] {
+					for value in [color.x, color.y, color.z] {
+						bytes.extend_from_slice(&value.to_le_bytes());
+					}
+				}
+				bytes.extend_from_slice(&pixel.depth_sum.to_le_bytes());
+				bytes.extend_from_slice(&pixel.hit_count.to_le_bytes());
+				bytes.extend_from_slice(&pixel.object_id.to_le_bytes());
+				bytes.extend_from_slice(&pixel.material_id.to_le_bytes());
+			}
+		}
+
+		let temp_path = format!("{path}.tmp");
+		File::create(&temp_path)?.write_all(&bytes)?;
+		return fs::rename(&temp_path, path);
+	}
+
+	pub fn load(path: &str) -> Result<Checkpoint> {
+		let bytes = fs::read(path)?;
+		let mut reader = ByteReader { bytes: &bytes, position: 0 };
+
+		if reader.take(MAGIC.len())? != MAGIC {
+			return Err(Error::new(ErrorKind::InvalidData, "not a render checkpoint"));
+		}
+		let width = reader.read_i32()?;
+		let height = reader.read_i32()?;
+		let seed = reader.read_u64()?;
+		let sampler = sampler_from_byte(reader.take(1)?[0])?;
+		let samples_per_pixel = reader.read_i32()?;
+		let samples_per_pass = reader.read_i32()?;
+		let next_sample = reader.read_i32()?;
+		let settings_length = reader.read_i32()?;
+		if settings_length < 0 {
+			return Err(Error::new(ErrorKind::InvalidData, "checkpoint has a broken header"));
+		}
+		let settings = reader.take(settings_length as usize)?.to_vec();
+		if width <= 0 || height <= 0 {
+			return Err(Error::new(ErrorKind::InvalidData, "checkpoint has an empty image"));
+		}
+
+		let mut framebuffer = Framebuffer::new(width, height);
+		for j in 0..height {
+			for i in 0..width {
+				let weighted_sum = Color::new(reader.read_f64()?, reader.read_f64()?, reader.read_f64()?);
+				let weight_sum = reader.read_f64()?;
+				let mean = reader.read_f64()?;
+				let m2 = reader.read_f64()?;
+				let count = reader.read_i32()?;
+				let emission_sum = reader.read_color()?;
+				let direct_sum = reader.read_color()?;
+				let indirect_sum = reader.read_color()?;
+				let normal_sum = reader.read_color()?;
+				let albedo_sum = reader.read_color()?;
+				let depth_sum = reader.read_f64()?;
+				let hit_count = reader.read_i32()?;
+				let object_id = reader.read_i32()?;
+				let material_id = reader.read_i32()? as u32;
+				framebuffer.set_pixel(i, j, PixelAccumulator {
+					weighted_sum,
+					weight_sum,
+					count,
+					mean,
+					m2,
+					emission_sum,
+					direct_sum,
+					indirect_sum,
+					hit_count,
+					depth_sum,
+					normal_sum,
+					albedo_sum,
+					object_id,
+					material_id,
+				});
+			}
+		}
+
+		return Ok(Checkpoint {
+			seed,
+			sampler,
+			samples_per_pixel,
+			samples_per_pass,
+			next_sample,
+			settings,
+			framebuffer,
+		});
+	}
+}
+
+//Where the camera looks, the background, and the filter, adaptive sampling and path settings
+//of the camera, packed into bytes. They're only ever compared with the camera's, so a render
+//can't carry on with settings that would mix two different images together.
+pub fn render_settings(camera: &Camera) -> Vec<u8> {
+	let mut bytes: Vec<u8> = Vec::new();
+	for point in [camera.lookfrom, camera.lookat, camera.vup] {
+		for value in [point.x, point.y, point.z] {
+			bytes.extend_from_slice(&value.to_le_bytes());
+		}
+	}
+	for value in [camera.vfov, camera.defocus_angle, camera.focus_dist] {
+		bytes.extend_from_slice(&value.to_le_bytes());
+	}
+	match camera.background {
+		Background::Sky => bytes.push(0),
+		Background::Solid(color) => {
+			bytes.push(1);
+			for value in [color.x, color.y, color.z] {
+				bytes.extend_from_slice(&value.to_le_bytes());
+			}
+		}
+	}
+
+	let filter = match camera.filter {
+		Filter::Box { radius } => (0, [radius, 0.0, 0.0]),
+		Filter::Tent { radius } => (1, [radius, 0.0, 0.0]),
+		Filter::Gaussian { radius, sigma } => (2, [radius, sigma, 0.0]),
+		Filter::Mitchell { radius, b, c } => (3, [radius, b, c]),
+		Filter::Lanczos { radius, tau } => (4, [radius, tau, 0.0]),
+	};
+	bytes.push(filter.0);
+	for value in filter.1 {
+		bytes.extend_from_slice(&value.to_le_bytes());
+	}
+
+	//the optional settings are a 0 when they're off, a 1 followed by their values when on.
+	match camera.adaptive {
+		Some(adaptive) => {
+			bytes.push(1);
+			bytes.extend_from_slice(&adaptive.min_samples.to_le_bytes());
+			bytes.extend_from_slice(&adaptive.max_samples.to_le_bytes());
+			bytes.extend_from_slice(&adaptive.tolerance.to_le_bytes());
+		}
+		None => bytes.push(0),
+	}
+	let limits = camera.depth_limits;
+	for depth in [camera.max_depth, limits.diffuse, limits.specular, limits.transmission, limits.volume] {
+		bytes.extend_from_slice(&depth.to_le_bytes());
+	}
+	match camera.russian_roulette {
+		Some(roulette) => {
+			bytes.push(1);
+			bytes.extend_from_slice(&roulette.min_depth.to_le_bytes());
+		}
+		None => bytes.push(0),
+	}
+	for setting in [camera.firefly_clamp, camera.path_regularization.map(|regularization| regularization.angle)] {
+		match setting {
+			Some(value) => {
+				bytes.push(1);
+				bytes.extend_from_slice(&value.to_le_bytes());
+			}
+			None => bytes.push(0),
+		}
+	}
+	return bytes;
+}
+
+fn sampler_to_byte(sampler: SamplerKind) -> u8 {
+	match sampler {
+		SamplerKind::Independent => 0,
+		SamplerKind::Stratified => 1,
+		SamplerKind::Halton => 2,
+		SamplerKind::Sobol => 3,
+	}
+}
+
+fn sampler_from_byte(byte: u8) -> Result<SamplerKind> {
+	match byte {
+		0 => return Ok(SamplerKind::Independent),
+		1 => return Ok(SamplerKind::Stratified),
+		2 => return Ok(SamplerKind::Halton),
+		3 => return Ok(SamplerKind::Sobol),
+		_ => return Err(Error::new(ErrorKind::InvalidData, "unknown sampler in checkpoint")),
+	}
+}
+
+struct ByteReader<'a> {
+	bytes: &'a [u8],
+	position: usize,
+}
+
+impl ByteReader<'_> {
+	fn take(&mut self, count: usize) -> Result<&[u8]> {
+		if self.position + count > self.bytes.len() {
+			return Err(Error::new(ErrorKind::UnexpectedEof, "checkpoint is truncated"));
+		}
+		let slice = &self.bytes[self.position..self.position + count];
+		self.position += count;
+		return Ok(slice);
+	}
+	fn read_i32(&mut self) -> Result<i32> {
+		return Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()));
+	}
+	fn read_u64(&mut self) -> Result<u64> {
+		return Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()));
+	}
+	fn read_f64(&mut self) -> Result<f64> {
+		return Ok(f64::from_le_bytes(self.take(8)?.try_into().unwrap()));
+	}
+	fn read_color(&mut self) -> Result<Color> {
+		return Ok(Color::new(self.read_f64()?, self.read_f64()?, self.read_f64()?));
+	}
+}
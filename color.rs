@@ -1,12 +1,8 @@
 use crate::Vector;
-use crate::Ray;
 use std::fs::File;
 use std::io::prelude::*;
 //COLOR
 
-use crate::Color;
-use crate::Point;
-use crate::hittable::*;
 use crate::Interval;
 
 pub fn linear_to_gamma(linear_component: f64) -> f64 {
@@ -17,6 +13,11 @@ pub fn linear_to_gamma(linear_component: f64) -> f64 {
 	return 0.0;
 }
 
+//how bright a color looks, using the Rec. 709 weights.
+pub fn luminance(color: &Vector) -> f64 {
+	return 0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z;
+}
+
 pub fn write_color(pixel_color: &Vector, file: &mut File) {
 	let r: f64 = linear_to_gamma(pixel_color.x);
 	let g: f64 = linear_to_gamma(pixel_color.y);
@@ -39,20 +40,6 @@ pub fn write_color(pixel_color: &Vector, file: &mut File) {
 	let _ = file.write_all(temp_string.as_bytes());
 }
 
-fn hit_sphere(center: &Point, radius: f64, r: &Ray) -> f64 {
-	let oc: Vector = *center - r.origin;
-	let a = r.dir.length_squared();
-	let h = Point::dot(&r.dir, &oc);
-	let c = oc.length_squared() - (radius * radius);
-	let discriminant = h * h - a * c;
-
-	if discriminant >= 0.0 {
-		//r.dir.print();
-		return ( h - f64::sqrt(discriminant)) / a; 
-	}
-	return -1.0;
-}
-
 /*
 //color goes [0,1] since it gets multiplied by 255.999 later.
 pub fn ray_color(r: &Ray, world: &HittableList) -> Color {
@@ -0,0 +1,151 @@
+//Constructive solid geometry, solids made by combining two closed shapes.
+//Both children report every span of the ray inside them, the spans are merged by the operation,
+//and the first boundary of the result in front of the ray is the hit.
+//Each surface keeps the material of the shape it came from, so the walls of a hole cut by a
+//difference are whatever the cutting shape is made of.
+use crate::Ray;
+use crate::Interval;
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable, Span};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsgOperation {
+	//inside either one.
+	Union,
+	//inside both.
+	Intersection,
+	//inside a but not b.
+	Difference,
+}
+
+impl CsgOperation {
+	fn inside(&self, in_a: bool, in_b: bool) -> bool {
+		match self {
+			CsgOperation::Union => return in_a || in_b,
+			CsgOperation::Intersection => return in_a && in_b,
+			CsgOperation::Difference => return in_a && !in_b,
+		}
+	}
+}
+
+pub struct Csg {
+	operation: CsgOperation,
+	a: Box<dyn Hittable>,
+	b: Box<dyn Hittable>,
+	bbox: Aabb,
+}
+
+impl Csg {
+	pub fn new(operation: CsgOperation, a: Box<dyn Hittable>, b: Box<dyn Hittable>) -> Csg {
+		let a_box = a.bounding_box();
+		let b_box = b.bounding_box();
+		let bbox = match operation {
+			CsgOperation::Union => Aabb::enclosing(&a_box, &b_box),
+			CsgOperation::Intersection => overlap(&a_box, &b_box),
+			CsgOperation::Difference => a_box,
+		};
+		Csg {
+			operation,
+			a,
+			b,
+			bbox,
+		}
+	}
+	pub fn union(a: Box<dyn Hittable>, b: Box<dyn Hittable>) -> Csg {
+		return Csg::new(CsgOperation::Union, a, b);
+	}
+	pub fn intersection(a: Box<dyn Hittable>, b: Box<dyn Hittable>) -> Csg {
+		return Csg::new(CsgOperation::Intersection, a, b);
+	}
+	pub fn difference(a: Box<dyn Hittable>, b: Box<dyn Hittable>) -> Csg {
+		return Csg::new(CsgOperation::Difference, a, b);
+	}
+}
+
+//One place where the ray goes into or out of a child.
+struct Crossing {
+	rec: HitRecord,
+	from_a: bool,
+	entering: bool,
+}
+
+impl Hittable for Csg {
+	fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+		if !self.bbox.hit(r, ray_t) {
+			return false;
+		}
+		let mut spans: Vec<Span> = Vec::new();
+		self.spans(r, &mut spans);
+		for span in &spans {
+			for boundary in [&span.enter, &span.exit] {
+				if ray_t.surrounds(boundary.t) {
+					*rec = *boundary;
+					return true;
+				}
+			}
+		}
+		return false;
+	}
+
+	fn bounding_box(&self) -> Aabb {
+		return self.bbox;
+	}
+
+	//walks the crossings of both children in order and keeps the ones where the result changes.
+	fn spans(&self, r: &Ray, spans: &mut Vec<Span>) {
+		let mut child_spans: Vec<Span> = Vec::new();
+		self.a.spans(r, &mut child_spans);
+		let a_count = child_spans.len();
+		if a_count == 0 && self.operation != CsgOperation::Union {
+			return;
+		}
+		self.b.spans(r, &mut child_spans);
+
+		let mut crossings: Vec<Crossing> = Vec::with_capacity(child_spans.len() * 2);
+		for (index, span) in child_spans.iter().enumerate() {
+			let from_a = index < a_count;
+			crossings.push(Crossing { rec: span.enter, from_a, entering: true });
+			crossings.push(Crossing { rec: span.exit, from_a, entering: false });
+		}
+		crossings.sort_by(|first, second| first.rec.t.total_cmp(&second.rec.t));
+
+		let mut in_a = false;
+		let mut in_b = false;
+		let mut enter = HitRecord::default();
+		for crossing in &crossings {
+			let was_inside = self.operation.inside(in_a, in_b);
+			if crossing.from_a {
+				in_a = crossing.entering;
+			} else {
+				in_b = crossing.entering;
+			}
+			let inside = self.operation.inside(in_a, in_b);
+			if inside == was_inside {
+				continue;
+			}
+
+			let mut boundary = crossing.rec;
+			//b's surface is turned inside out where it's cut away from a.
+			if self.operation == CsgOperation::Difference && !crossing.from_a {
+				let outward = boundary.outward_normal() * -1.0;
+				boundary.set_face_normal(r, &outward);
+			}
+			if inside {
+				enter = boundary;
+			} else {
+				spans.push(Span { enter, exit: boundary });
+			}
+		}
+	}
+}
+
+//the box two boxes share, a's box when they don't touch since nothing can be inside both then.
+fn overlap(a: &Aabb, b: &Aabb) -> Aabb {
+	let x = Interval::new(a.x.min.max(b.x.min), a.x.max.min(b.x.max));
+	let y = Interval::new(a.y.min.max(b.y.min), a.y.max.min(b.y.max));
+	let z = Interval::new(a.z.min.max(b.z.min), a.z.max.min(b.z.max));
+	if x.size() < 0.0 || y.size() < 0.0 || z.size() < 0.0 {
+		return *a;
+	}
+	return Aabb::new(x, y, z);
+}
@@ -0,0 +1,143 @@
+use crate::Color;
+use crate::Vector;
+use crate::color::luminance;
+use crate::framebuffer::Framebuffer;
+
+//A joint bilateral filter that smooths the noise out of a finished render.
+//Every pixel is averaged with its neighbours, and each neighbour counts less the more its albedo,
+//normal and depth differ, so edges and texture that are visible in the feature passes stay sharp.
+//The color is divided by the albedo before filtering and multiplied back afterwards,
+//so only the lighting gets blurred and surface detail survives.
+#[derive(Debug, Clone, Copy)]
+pub struct Denoiser {
+	//how many pixels to look in every direction.
+	pub radius: i32,
+	//standard deviation in pixels of the falloff with distance.
+	pub sigma_spatial: f64,
+	//how many standard deviations of the pixels' own noise two brightnesses can differ
+	//before the neighbour stops counting, bigger blurs more.
+	pub sigma_color: f64,
+	pub sigma_albedo: f64,
+	//exponent on the cosine between the normals, bigger keeps corners sharper.
+	pub normal_power: f64,
+	//allowed depth difference, relative to the pixel's own depth.
+	pub sigma_depth: f64,
+}
+
+impl Default for Denoiser {
+	fn default() -> Self {
+		Denoiser {
+			radius: 5,
+			sigma_spatial: 3.0,
+			sigma_color: 4.0,
+			sigma_albedo: 0.1,
+			normal_power: 64.0,
+			sigma_depth: 0.05,
+		}
+	}
+}
+
+//what the filter looks at for one pixel.
+#[derive(Clone, Copy)]
+struct Feature {
+	hit: bool,
+	//the color with the albedo divided out.
+	irradiance: Color,
+	//standard deviation of the irradiance's brightness.
+	deviation: f64,
+	albedo: Color,
+	normal: Vector,
+	depth: f64,
+}
+
+//albedos darker than this aren't divided out, there's nothing left to bring back.
+const MIN_ALBEDO: f64 = 0.01;
+
+impl Denoiser {
+	//Returns a copy of framebuffer with the colors filtered. The sample statistics and
+	//the other passes are left as they are, so the result can still be written like any render.
+	pub fn denoise(&self, framebuffer: &Framebuffer) -> Framebuffer {
+		let width = framebuffer.width;
+		let height = framebuffer.height;
+		let features: Vec<Feature> = (0..height)
+			.flat_map(|j| (0..width).map(move |i| (i, j)))
+			.map(|(i, j)| Self::feature(framebuffer, i, j))
+			.collect();
+
+		let mut output = framebuffer.clone();
+		for j in 0..height {
+			for i in 0..width {
+				let center = features[(j * width + i) as usize];
+				//the background and lights seen straight on have nothing to guide the filter.
+				if !center.hit {
+					continue;
+				}
+
+				let mut sum = Color::null_vector();
+				let mut weight_sum = 0.0;
+				for y in (j - self.radius).max(0)..=(j + self.radius).min(height - 1) {
+					for x in (i - self.radius).max(0)..=(i + self.radius).min(width - 1) {
+						let neighbour = features[(y * width + x) as usize];
+						let weight = self.weight(&center, &neighbour, (x - i) as f64, (y - j) as f64);
+						sum += neighbour.irradiance * weight;
+						weight_sum += weight;
+					}
+				}
+
+				if weight_sum == 0.0 {
+					continue;
+				}
+				let color = sum / weight_sum * Self::demodulation_albedo(&center.albedo);
+				let mut pixel = framebuffer.pixel(i, j);
+				pixel.weighted_sum = color * pixel.weight_sum;
+				output.set_pixel(i, j, pixel);
+			}
+		}
+		return output;
+	}
+
+	fn feature(framebuffer: &Framebuffer, i: i32, j: i32) -> Feature {
+		let pixel = framebuffer.pixel(i, j);
+		let albedo = pixel.albedo();
+		let demodulation = Self::demodulation_albedo(&albedo);
+
+		//the variance of the pixel's mean, from the variance of its samples.
+		let mut variance = 0.0;
+		if pixel.count > 1 {
+			variance = pixel.m2 / ((pixel.count - 1) as f64 * pixel.count as f64);
+		}
+
+		return Feature {
+			hit: pixel.hit_count > 0,
+			irradiance: pixel.color() / demodulation,
+			deviation: variance.sqrt() / luminance(&demodulation),
+			albedo,
+			normal: pixel.normal(),
+			depth: pixel.depth(),
+		};
+	}
+
+	fn demodulation_albedo(albedo: &Color) -> Color {
+		return Color::new(albedo.x.max(MIN_ALBEDO), albedo.y.max(MIN_ALBEDO), albedo.z.max(MIN_ALBEDO));
+	}
+
+	fn weight(&self, center: &Feature, neighbour: &Feature, dx: f64, dy: f64) -> f64 {
+		if !neighbour.hit {
+			return 0.0;
+		}
+		let spatial = -(dx * dx + dy * dy) / (2.0 * self.sigma_spatial * self.sigma_spatial);
+
+		let color_difference = (luminance(&center.irradiance) - luminance(&neighbour.irradiance)).abs();
+		let deviation = self.sigma_color * f64::sqrt(center.deviation * center.deviation + neighbour.deviation * neighbour.deviation);
+		let color = -color_difference / (deviation + 1e-4);
+
+		let albedo_difference = (center.albedo - neighbour.albedo).length_squared();
+		let albedo = -albedo_difference / (2.0 * self.sigma_albedo * self.sigma_albedo);
+
+		let depth = -(center.depth - neighbour.depth).abs() / (self.sigma_depth * center.depth + 1e-4);
+
+		let normal = Vector::dot(&center.normal, &neighbour.normal).max(0.0).powf(self.normal_power);
+
+		return f64::exp(spatial + color + albedo + depth) * normal;
+	}
+}
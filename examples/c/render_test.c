@@ -0,0 +1,129 @@
+/* Exercises the C interface: builds a small scene with spheres and a mesh,
+ * renders it, checks the errors come back as documented and writes test.ppm.
+ *
+ *   cargo build --release
+ *   cc examples/c/render_test.c -Iinclude -Ltarget/release -lraytracer -lm -lpthread -ldl -o render_test
+ *   LD_LIBRARY_PATH=target/release ./render_test
+ *
+ * Exits with 0 when every check passes.
+ */
+#include <math.h>
+#include <stdio.h>
+#include <stdlib.h>
+
+#include "raytracer.h"
+
+static int failures = 0;
+
+#define CHECK(condition) do { \
+	if (!(condition)) { \
+		fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, #condition); \
+		failures++; \
+	} \
+} while (0)
+
+#define CHECK_STATUS(call, expected) do { \
+	RtStatus status_ = (call); \
+	if (status_ != (expected)) { \
+		fprintf(stderr, "%s:%d: %s returned %d (%s)\n", __FILE__, __LINE__, #call, status_, rt_status_string(status_)); \
+		failures++; \
+	} \
+} while (0)
+
+static void write_ppm(const char *path, const float *pixels, int width, int height) {
+	FILE *file = fopen(path, "w");
+	if (!file) {
+		return;
+	}
+	fprintf(file, "P3\n%d %d\n255\n", width, height);
+	for (int i = 0; i < width * height * 3; i += 3) {
+		int rgb[3];
+		for (int c = 0; c < 3; c++) {
+			float value = sqrtf(pixels[i + c] > 0.0f ? pixels[i + c] : 0.0f);
+			rgb[c] = (int)(256.0f * (value < 0.999f ? value : 0.999f));
+		}
+		fprintf(file, "%d %d %d\n", rgb[0], rgb[1], rgb[2]);
+	}
+	fclose(file);
+}
+
+int main(void) {
+	const int width = 64;
+	const int height = 48;
+
+	RtScene *scene = rt_scene_new();
+	CHECK(scene != NULL);
+
+	uint32_t ground, red, metal, glass, light;
+	CHECK_STATUS(rt_scene_add_lambertian(scene, 0.7, 0.7, 0.7, &ground), RT_OK);
+	CHECK_STATUS(rt_scene_add_lambertian(scene, 0.7, 0.1, 0.1, &red), RT_OK);
+	CHECK_STATUS(rt_scene_add_metal(scene, 0.8, 0.8, 0.8, 0.1, &metal), RT_OK);
+	CHECK_STATUS(rt_scene_add_dielectric(scene, 1.5, &glass), RT_OK);
+	CHECK_STATUS(rt_scene_add_diffuse_light(scene, 4.0, 4.0, 4.0, &light), RT_OK);
+
+	CHECK_STATUS(rt_scene_add_sphere(scene, 0.0, -100.5, -1.0, 100.0, ground), RT_OK);
+	CHECK_STATUS(rt_scene_add_sphere(scene, 0.0, 0.0, -1.2, 0.5, red), RT_OK);
+	CHECK_STATUS(rt_scene_add_sphere(scene, 1.0, 0.0, -1.0, 0.5, metal), RT_OK);
+	CHECK_STATUS(rt_scene_add_sphere(scene, -1.0, 0.0, -1.0, 0.5, glass), RT_OK);
+
+	/* a square light above the spheres, made of two triangles facing down */
+	const double positions[] = {
+		-0.5, 1.5, -1.5,
+		 0.5, 1.5, -1.5,
+		 0.5, 1.5, -0.5,
+		-0.5, 1.5, -0.5,
+	};
+	const uint32_t indices[] = { 0, 1, 2, 0, 2, 3 };
+	CHECK_STATUS(rt_scene_add_mesh(scene, positions, 4, indices, 6, light), RT_OK);
+
+	/* errors */
+	const uint32_t bad_indices[] = { 0, 1, 7 };
+	CHECK_STATUS(rt_scene_add_mesh(scene, positions, 4, bad_indices, 3, light), RT_INVALID_ARGUMENT);
+	CHECK_STATUS(rt_scene_add_sphere(scene, 0.0, 0.0, 0.0, 1.0, 99), RT_INVALID_ARGUMENT);
+	CHECK_STATUS(rt_scene_add_sphere(NULL, 0.0, 0.0, 0.0, 1.0, red), RT_NULL_POINTER);
+	CHECK_STATUS(rt_scene_add_metal(scene, 0.5, 0.5, 0.5, 2.0, &metal), RT_INVALID_ARGUMENT);
+	CHECK(rt_camera_new(0, 10) == NULL);
+
+	RtCamera *camera = rt_camera_new(width, height);
+	CHECK(camera != NULL);
+	CHECK_STATUS(rt_camera_look_at(camera, 0.0, 0.5, 1.0, 0.0, 0.0, -1.0, 0.0, 1.0, 0.0, 60.0), RT_OK);
+	CHECK_STATUS(rt_camera_look_at(camera, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 60.0), RT_INVALID_ARGUMENT);
+	CHECK_STATUS(rt_camera_set_samples(camera, 16, 8), RT_OK);
+	CHECK_STATUS(rt_camera_set_seed(camera, 7), RT_OK);
+	CHECK_STATUS(rt_camera_set_threads(camera, 2), RT_OK);
+	CHECK_STATUS(rt_camera_set_background(camera, 0.1, 0.1, 0.2), RT_OK);
+
+	size_t length = (size_t)width * height * 3;
+	float *pixels = calloc(length, sizeof(float));
+	float *again = calloc(length, sizeof(float));
+	CHECK_STATUS(rt_render(camera, scene, pixels, length - 1), RT_BUFFER_TOO_SMALL);
+	CHECK_STATUS(rt_render(camera, scene, pixels, length), RT_OK);
+	CHECK_STATUS(rt_render(camera, scene, again, length), RT_OK);
+
+	/* the image is lit, finite and the same every time */
+	double sum = 0.0;
+	int finite = 1;
+	int same = 1;
+	for (size_t i = 0; i < length; i++) {
+		finite &= isfinite(pixels[i]) && pixels[i] >= 0.0f;
+		same &= pixels[i] == again[i];
+		sum += pixels[i];
+	}
+	CHECK(finite);
+	CHECK(same);
+	CHECK(sum / length > 0.05);
+
+	write_ppm("test.ppm", pixels, width, height);
+
+	free(pixels);
+	free(again);
+	rt_camera_free(camera);
+	rt_scene_free(scene);
+
+	if (failures > 0) {
+		fprintf(stderr, "%d checks failed\n", failures);
+		return 1;
+	}
+	printf("all checks passed\n");
+	return 0;
+}
@@ -0,0 +1,523 @@
+use crate::Vector;
+
+use std::fs::File;
+use std::io::prelude::*;
+
+//A minimal OpenEXR writer for single part scanline images.
+//Channels are written in the order EXR wants them (sorted by name), so layers are just
+//channels sharing a "layer." prefix, the way compositing tools expect them.
+
+const MAGIC: [u8; 4] = [0x76, 0x2f, 0x31, 0x01];
+const VERSION: u8 = 2;
+//set in the version field when a name is longer than the original 31 byte limit.
+const LONG_NAMES_FLAG: u32 = 0x400;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExrCompression {
+	None,
+	//run length encoding, cheap and good on flat areas.
+	Rle,
+	//zlib, one scanline per block.
+	Zips,
+	//zlib, 16 scanlines per block, compresses better.
+	Zip,
+}
+
+impl ExrCompression {
+	fn id(&self) -> u8 {
+		match self {
+			ExrCompression::None => 0,
+			ExrCompression::Rle => 1,
+			ExrCompression::Zips => 2,
+			ExrCompression::Zip => 3,
+		}
+	}
+	fn lines_per_block(&self) -> i32 {
+		match self {
+			ExrCompression::Zip => 16,
+			_ => 1,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExrPixelType {
+	Uint,
+	Half,
+	Float,
+}
+
+impl ExrPixelType {
+	fn id(&self) -> i32 {
+		match self {
+			ExrPixelType::Uint => 0,
+			ExrPixelType::Half => 1,
+			ExrPixelType::Float => 2,
+		}
+	}
+	fn size(&self) -> usize {
+		match self {
+			ExrPixelType::Half => 2,
+			_ => 4,
+		}
+	}
+}
+
+//One channel of the image, values are stored row by row from the top left
+//and converted to the pixel type when written.
+pub struct ExrChannel {
+	pub name: String,
+	pub pixel_type: ExrPixelType,
+	pub values: Vec<f64>,
+}
+
+//Extra header entries, for things like the camera the image was rendered with.
+pub enum ExrAttribute {
+	Int(i32),
+	Float(f32),
+	String(String),
+	V3f(Vector),
+	//row major, EXR multiplies row vectors from the left.
+	M44f([f32; 16]),
+}
+
+impl ExrAttribute {
+	fn type_name(&self) -> &'static str {
+		match self {
+			ExrAttribute::Int(_) => "int",
+			ExrAttribute::Float(_) => "float",
+			ExrAttribute::String(_) => "string",
+			ExrAttribute::V3f(_) => "v3f",
+			ExrAttribute::M44f(_) => "m44f",
+		}
+	}
+	fn value(&self) -> Vec<u8> {
+		let mut bytes: Vec<u8> = Vec::new();
+		match self {
+			ExrAttribute::Int(value) => bytes.extend_from_slice(&value.to_le_bytes()),
+			ExrAttribute::Float(value) => bytes.extend_from_slice(&value.to_le_bytes()),
+			ExrAttribute::String(value) => bytes.extend_from_slice(value.as_bytes()),
+			ExrAttribute::V3f(value) => {
+				for component in [value.x, value.y, value.z] {
+					bytes.extend_from_slice(&(component as f32).to_le_bytes());
+				}
+			}
+			ExrAttribute::M44f(values) => {
+				for value in values {
+					bytes.extend_from_slice(&value.to_le_bytes());
+				}
+			}
+		}
+		return bytes;
+	}
+}
+
+pub struct ExrImage {
+	pub width: i32,
+	pub height: i32,
+	pub compression: ExrCompression,
+	pub channels: Vec<ExrChannel>,
+	pub attributes: Vec<(String, ExrAttribute)>,
+}
+
+impl ExrImage {
+	pub fn new(width: i32, height: i32, compression: ExrCompression) -> ExrImage {
+		ExrImage {
+			width,
+			height,
+			compression,
+			channels: Vec::new(),
+			attributes: Vec::new(),
+		}
+	}
+
+	//values has to hold width * height entries.
+	pub fn add_channel(&mut self, name: &str, pixel_type: ExrPixelType, values: Vec<f64>) {
+		assert_eq!(values.len(), (self.width * self.height) as usize, "channel {name} has the wrong number of pixels");
+		self.channels.push(ExrChannel {
+			name: name.to_string(),
+			pixel_type,
+			values,
+		});
+	}
+
+	//Adds one channel per name, called layer.name, filled from the vectors' x, y and z.
+	//an empty layer gives plain channel names, which is where EXR keeps the main image.
+	pub fn add_layer(&mut self, layer: &str, names: [&str; 3], pixel_type: ExrPixelType, values: &[Vector]) {
+		for (index, name) in names.iter().enumerate() {
+			let channel_values: Vec<f64> = values.iter().map(|value| [value.x, value.y, value.z][index]).collect();
+			if layer.is_empty() {
+				self.add_channel(name, pixel_type, channel_values);
+			} else {
+				self.add_channel(&format!("{layer}.{name}"), pixel_type, channel_values);
+			}
+		}
+	}
+
+	pub fn add_attribute(&mut self, name: &str, attribute: ExrAttribute) {
+		self.attributes.push((name.to_string(), attribute));
+	}
+
+	pub fn write(&self, file: &mut File) -> std::io::Result<()> {
+		return file.write_all(&self.encode());
+	}
+
+	pub fn encode(&self) -> Vec<u8> {
+		let mut channels: Vec<&ExrChannel> = self.channels.iter().collect();
+		channels.sort_by(|a, b| a.name.as_bytes().cmp(b.name.as_bytes()));
+
+		let mut long_names = channels.iter().any(|channel| channel.name.len() > 31);
+		long_names |= self.attributes.iter().any(|(name, _)| name.len() > 31);
+
+		let mut bytes: Vec<u8> = Vec::new();
+		bytes.extend_from_slice(&MAGIC);
+		let mut version = VERSION as u32;
+		if long_names {
+			version |= LONG_NAMES_FLAG;
+		}
+		bytes.extend_from_slice(&version.to_le_bytes());
+
+		//the header, a list of (name, type, size, value) closed by an empty name.
+		let mut channel_list: Vec<u8> = Vec::new();
+		for channel in &channels {
+			channel_list.extend_from_slice(channel.name.as_bytes());
+			channel_list.push(0);
+			channel_list.extend_from_slice(&channel.pixel_type.id().to_le_bytes());
+			//pLinear and three reserved bytes.
+			channel_list.extend_from_slice(&[0, 0, 0, 0]);
+			//x and y sampling.
+			channel_list.extend_from_slice(&1i32.to_le_bytes());
+			channel_list.extend_from_slice(&1i32.to_le_bytes());
+		}
+		channel_list.push(0);
+
+		let mut window: Vec<u8> = Vec::new();
+		for value in [0, 0, self.width - 1, self.height - 1] {
+			window.extend_from_slice(&value.to_le_bytes());
+		}
+
+		write_attribute(&mut bytes, "channels", "chlist", &channel_list);
+		write_attribute(&mut bytes, "compression", "compression", &[self.compression.id()]);
+		write_attribute(&mut bytes, "dataWindow", "box2i", &window);
+		write_attribute(&mut bytes, "displayWindow", "box2i", &window);
+		//increasing y.
+		write_attribute(&mut bytes, "lineOrder", "lineOrder", &[0]);
+		write_attribute(&mut bytes, "pixelAspectRatio", "float", &1.0f32.to_le_bytes());
+		write_attribute(&mut bytes, "screenWindowCenter", "v2f", &[0u8; 8]);
+		write_attribute(&mut bytes, "screenWindowWidth", "float", &1.0f32.to_le_bytes());
+		for (name, attribute) in &self.attributes {
+			write_attribute(&mut bytes, name, attribute.type_name(), &attribute.value());
+		}
+		bytes.push(0);
+
+		//the offset table comes before the blocks, so it's filled in once their sizes are known.
+		let lines_per_block = self.compression.lines_per_block();
+		let block_count = (self.height + lines_per_block - 1) / lines_per_block;
+		let table_start = bytes.len();
+		bytes.resize(table_start + 8 * block_count as usize, 0);
+
+		for block in 0..block_count {
+			let y0 = block * lines_per_block;
+			let y1 = (y0 + lines_per_block).min(self.height);
+			let raw = self.block_bytes(&channels, y0, y1);
+			let data = match self.compression {
+				ExrCompression::None => raw,
+				ExrCompression::Rle => smaller(rle_compress(&predict(&interleave(&raw))), raw),
+				ExrCompression::Zips | ExrCompression::Zip => smaller(zlib_compress(&predict(&interleave(&raw))), raw),
+			};
+
+			let offset = bytes.len() as u64;
+			let entry = table_start + 8 * block as usize;
+			bytes[entry..entry + 8].copy_from_slice(&offset.to_le_bytes());
+			bytes.extend_from_slice(&y0.to_le_bytes());
+			bytes.extend_from_slice(&(data.len() as i32).to_le_bytes());
+			bytes.extend_from_slice(&data);
+		}
+		return bytes;
+	}
+
+	//the uncompressed pixels of scanlines y0..y1, every line holds each channel's row in turn.
+	fn block_bytes(&self, channels: &[&ExrChannel], y0: i32, y1: i32) -> Vec<u8> {
+		let mut bytes: Vec<u8> = Vec::new();
+		for y in y0..y1 {
+			for channel in channels {
+				let row = &channel.values[(y * self.width) as usize..((y + 1) * self.width) as usize];
+				bytes.reserve(row.len() * channel.pixel_type.size());
+				for value in row {
+					match channel.pixel_type {
+						ExrPixelType::Uint => bytes.extend_from_slice(&(value.clamp(0.0, u32::MAX as f64) as u32).to_le_bytes()),
+						ExrPixelType::Half => bytes.extend_from_slice(&f32_to_half(*value as f32).to_le_bytes()),
+						ExrPixelType::Float => bytes.extend_from_slice(&(*value as f32).to_le_bytes()),
+					}
+				}
+			}
+		}
+		return bytes;
+	}
+}
+
+fn write_attribute(bytes: &mut Vec<u8>, name: &str, type_name: &str, value: &[u8]) {
+	bytes.extend_from_slice(name.as_bytes());
+	bytes.push(0);
+	bytes.extend_from_slice(type_name.as_bytes());
+	bytes.push(0);
+	bytes.extend_from_slice(&(value.len() as i32).to_le_bytes());
+	bytes.extend_from_slice(value);
+}
+
+//readers take a block that's as big as the uncompressed one to be stored as it is.
+fn smaller(compressed: Vec<u8>, raw: Vec<u8>) -> Vec<u8> {
+	if compressed.len() < raw.len() {
+		return compressed;
+	}
+	return raw;
+}
+
+//Rounds to the nearest half, ties to even. Too large becomes infinity, too small becomes zero.
+pub fn f32_to_half(value: f32) -> u16 {
+	let bits = value.to_bits();
+	let sign = ((bits >> 16) & 0x8000) as u16;
+	let exponent = ((bits >> 23) & 0xff) as i32;
+	let mantissa = bits & 0x7fffff;
+
+	if exponent == 0xff {
+		//infinity stays infinity, every NaN becomes a quiet one.
+		if mantissa != 0 {
+			return sign | 0x7e00;
+		}
+		return sign | 0x7c00;
+	}
+
+	let half_exponent = exponent - 127 + 15;
+	if half_exponent >= 0x1f {
+		return sign | 0x7c00;
+	}
+	if half_exponent <= 0 {
+		//a subnormal half, or zero once the value is below half of the smallest one.
+		if half_exponent < -10 {
+			return sign;
+		}
+		let full = mantissa | 0x800000;
+		let shift = (14 - half_exponent) as u32;
+		let mut half = full >> shift;
+		let remainder = full & ((1 << shift) - 1);
+		let halfway = 1 << (shift - 1);
+		if remainder > halfway || (remainder == halfway && half & 1 == 1) {
+			half += 1;
+		}
+		return sign | half as u16;
+	}
+
+	let mut half = ((half_exponent as u32) << 10) | (mantissa >> 13);
+	let remainder = mantissa & 0x1fff;
+	//rounding up can carry into the exponent, which correctly ends at infinity.
+	if remainder > 0x1000 || (remainder == 0x1000 && half & 1 == 1) {
+		half += 1;
+	}
+	return sign | half as u16;
+}
+
+//COMPRESSION
+
+//Puts the even bytes before the odd ones, so the high and low bytes of each value end up together.
+fn interleave(raw: &[u8]) -> Vec<u8> {
+	let mut out: Vec<u8> = Vec::with_capacity(raw.len());
+	out.extend(raw.iter().step_by(2));
+	out.extend(raw.iter().skip(1).step_by(2));
+	return out;
+}
+
+//Replaces every byte with its difference to the one before, which makes smooth images mostly repeats.
+fn predict(bytes: &[u8]) -> Vec<u8> {
+	let mut out: Vec<u8> = bytes.to_vec();
+	for i in 1..bytes.len() {
+		out[i] = bytes[i].wrapping_sub(bytes[i - 1]).wrapping_add(128);
+	}
+	return out;
+}
+
+//Runs of 3 to 128 equal bytes are a count - 1 and the byte, anything else is
+//-count followed by up to 127 bytes copied as they are.
+fn rle_compress(bytes: &[u8]) -> Vec<u8> {
+	const MIN_RUN: usize = 3;
+	const MAX_RUN: usize = 127;
+	let mut out: Vec<u8> = Vec::new();
+	let mut run_start = 0;
+	let mut run_end = 1;
+
+	while run_start < bytes.len() {
+		while run_end < bytes.len() && bytes[run_start] == bytes[run_end] && run_end - run_start - 1 < MAX_RUN {
+			run_end += 1;
+		}
+		if run_end - run_start >= MIN_RUN {
+			out.push((run_end - run_start - 1) as u8);
+			out.push(bytes[run_start]);
+			run_start = run_end;
+		} else {
+			//copy until the next run of 3 starts.
+			while run_end < bytes.len()
+				&& (run_end + 1 >= bytes.len() || bytes[run_end] != bytes[run_end + 1]
+					|| run_end + 2 >= bytes.len() || bytes[run_end + 1] != bytes[run_end + 2])
+				&& run_end - run_start < MAX_RUN {
+				run_end += 1;
+			}
+			out.push((-((run_end - run_start) as i32)) as u8);
+			out.extend_from_slice(&bytes[run_start..run_end]);
+			run_start = run_end;
+		}
+		run_end += 1;
+	}
+	return out;
+}
+
+//A zlib stream holding a single deflate block with the fixed Huffman codes,
+//matches are found with a hash of the next 3 bytes and a short chain of earlier positions.
+fn zlib_compress(bytes: &[u8]) -> Vec<u8> {
+	const WINDOW: usize = 32768;
+	const MIN_MATCH: usize = 3;
+	const MAX_MATCH: usize = 258;
+	const MAX_CHAIN: usize = 32;
+	const HASH_BITS: u32 = 15;
+
+	let mut writer = BitWriter::new();
+	//deflate, 32K window, default compression, no dictionary.
+	writer.bytes.extend_from_slice(&[0x78, 0x9c]);
+	//last block, fixed codes.
+	writer.write_bits(1, 1);
+	writer.write_bits(1, 2);
+
+	let hash = |i: usize| -> usize {
+		let value = (bytes[i] as u32) | (bytes[i + 1] as u32) << 8 | (bytes[i + 2] as u32) << 16;
+		return (value.wrapping_mul(0x9e3779b1) >> (32 - HASH_BITS)) as usize;
+	};
+	//head is the latest position with each hash, previous links every position to the one before it.
+	let mut head: Vec<usize> = vec![usize::MAX; 1 << HASH_BITS];
+	let mut previous: Vec<usize> = vec![usize::MAX; bytes.len()];
+
+	let mut i = 0;
+	while i < bytes.len() {
+		let mut best_length = 0;
+		let mut best_distance = 0;
+		if i + MIN_MATCH <= bytes.len() {
+			let mut candidate = head[hash(i)];
+			let mut chain = 0;
+			while candidate != usize::MAX && i - candidate <= WINDOW && chain < MAX_CHAIN {
+				let limit = MAX_MATCH.min(bytes.len() - i);
+				let mut length = 0;
+				while length < limit && bytes[candidate + length] == bytes[i + length] {
+					length += 1;
+				}
+				if length > best_length {
+					best_length = length;
+					best_distance = i - candidate;
+					if length == limit {
+						break;
+					}
+				}
+				candidate = previous[candidate];
+				chain += 1;
+			}
+		}
+
+		if best_length >= MIN_MATCH {
+			writer.write_length(best_length);
+			writer.write_distance(best_distance);
+		} else {
+			best_length = 1;
+			writer.write_literal(bytes[i] as u32);
+		}
+		for position in (i..i + best_length).filter(|position| position + MIN_MATCH <= bytes.len()) {
+			let h = hash(position);
+			previous[position] = head[h];
+			head[h] = position;
+		}
+		i += best_length;
+	}
+	//end of block.
+	writer.write_literal(256);
+	writer.flush();
+
+	writer.bytes.extend_from_slice(&adler32(bytes).to_be_bytes());
+	return writer.bytes;
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+	let mut a: u32 = 1;
+	let mut b: u32 = 0;
+	for chunk in bytes.chunks(5552) {
+		for byte in chunk {
+			a += *byte as u32;
+			b += a;
+		}
+		a %= 65521;
+		b %= 65521;
+	}
+	return (b << 16) | a;
+}
+
+const LENGTH_BASE: [usize; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u32; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DISTANCE_BASE: [usize; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+const DISTANCE_EXTRA: [u32; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+//Writes deflate's bit stream, which fills each byte from the lowest bit up.
+struct BitWriter {
+	bytes: Vec<u8>,
+	buffer: u64,
+	count: u32,
+}
+
+impl BitWriter {
+	fn new() -> BitWriter {
+		BitWriter {
+			bytes: Vec::new(),
+			buffer: 0,
+			count: 0,
+		}
+	}
+
+	fn write_bits(&mut self, value: u32, bits: u32) {
+		self.buffer |= (value as u64) << self.count;
+		self.count += bits;
+		while self.count >= 8 {
+			self.bytes.push(self.buffer as u8);
+			self.buffer >>= 8;
+			self.count -= 8;
+		}
+	}
+
+	//Huffman codes are the one thing stored from the highest bit down.
+	fn write_code(&mut self, code: u32, bits: u32) {
+		self.write_bits(code.reverse_bits() >> (32 - bits), bits);
+	}
+
+	//the fixed literal/length code from the deflate spec.
+	fn write_literal(&mut self, symbol: u32) {
+		match symbol {
+			0..=143 => self.write_code(0x30 + symbol, 8),
+			144..=255 => self.write_code(0x190 + symbol - 144, 9),
+			256..=279 => self.write_code(symbol - 256, 7),
+			_ => self.write_code(0xc0 + symbol - 280, 8),
+		}
+	}
+
+	fn write_length(&mut self, length: usize) {
+		let code = LENGTH_BASE.iter().rposition(|base| *base <= length).unwrap();
+		self.write_literal(257 + code as u32);
+		self.write_bits((length - LENGTH_BASE[code]) as u32, LENGTH_EXTRA[code]);
+	}
+
+	fn write_distance(&mut self, distance: usize) {
+		let code = DISTANCE_BASE.iter().rposition(|base| *base <= distance).unwrap();
+		self.write_code(code as u32, 5);
+		self.write_bits((distance - DISTANCE_BASE[code]) as u32, DISTANCE_EXTRA[code]);
+	}
+
+	fn flush(&mut self) {
+		if self.count > 0 {
+			self.bytes.push(self.buffer as u8);
+			self.buffer = 0;
+			self.count = 0;
+		}
+	}
+}
@@ -0,0 +1,331 @@
+//The C interface, declared in include/raytracer.h.
+//Scenes and cameras are handed out as opaque pointers that C owns until it frees them,
+//every call that can fail returns an RtStatus instead of panicking across the boundary.
+//Pointers passed in have to be valid for the call, and handles can't be used from two threads at once.
+//What each function needs from its pointers is in its Safety section.
+
+use crate::Color;
+use crate::Point;
+use crate::Vector;
+use crate::camera::Camera;
+use crate::hittable::HittableList;
+use crate::material::MaterialEnum;
+
+use std::ffi::c_char;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RtStatus {
+	Ok = 0,
+	NullPointer = 1,
+	InvalidArgument = 2,
+	BufferTooSmall = 3,
+	//something went wrong inside the renderer, the handles involved shouldn't be used again.
+	Internal = 4,
+}
+
+//A scene and the materials that have been made for it, materials are referred to by index.
+pub struct RtScene {
+	world: HittableList,
+	materials: Vec<MaterialEnum>,
+}
+
+pub struct RtCamera {
+	camera: Camera,
+}
+
+#[unsafe(no_mangle)]
+//takes a plain int, C can pass values that aren't in the enum.
+pub extern "C" fn rt_status_string(status: i32) -> *const c_char {
+	let message: &'static [u8] = match status {
+		0 => b"ok\0",
+		1 => b"a required pointer was null\0",
+		2 => b"an argument was out of range\0",
+		3 => b"the output buffer is too small\0",
+		4 => b"internal renderer error\0",
+		_ => b"unknown status\0",
+	};
+	return message.as_ptr() as *const c_char;
+}
+
+//SCENE
+
+#[unsafe(no_mangle)]
+pub extern "C" fn rt_scene_new() -> *mut RtScene {
+	return Box::into_raw(Box::new(RtScene {
+		world: HittableList::new(),
+		materials: Vec::new(),
+	}));
+}
+
+/// # Safety
+/// `scene` has to be null or a scene from `rt_scene_new`, and each scene can only be freed once.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rt_scene_free(scene: *mut RtScene) {
+	if !scene.is_null() {
+		drop(unsafe { Box::from_raw(scene) });
+	}
+}
+
+//writes the new material's index to material.
+unsafe fn add_material(scene: *mut RtScene, new_material: MaterialEnum, material: *mut u32) -> RtStatus {
+	let (Some(scene), Some(material)) = (unsafe { scene.as_mut() }, unsafe { material.as_mut() }) else {
+		return RtStatus::NullPointer;
+	};
+	*material = scene.materials.len() as u32;
+	scene.materials.push(new_material);
+	return RtStatus::Ok;
+}
+
+fn valid_color(r: f64, g: f64, b: f64) -> bool {
+	return [r, g, b].iter().all(|value| value.is_finite() && *value >= 0.0);
+}
+
+/// # Safety
+/// `scene` has to be null or a scene from `rt_scene_new` that hasn't been freed.
+/// `material` has to be null or point to a `uint32_t` that can be written.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rt_scene_add_lambertian(scene: *mut RtScene, r: f64, g: f64, b: f64, material: *mut u32) -> RtStatus {
+	if !valid_color(r, g, b) {
+		return RtStatus::InvalidArgument;
+	}
+	return unsafe { add_material(scene, MaterialEnum::new_lambertian(r, g, b), material) };
+}
+
+/// # Safety
+/// `scene` has to be null or a scene from `rt_scene_new` that hasn't been freed.
+/// `material` has to be null or point to a `uint32_t` that can be written.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rt_scene_add_metal(scene: *mut RtScene, r: f64, g: f64, b: f64, fuzz: f64, material: *mut u32) -> RtStatus {
+	if !valid_color(r, g, b) || !(0.0..=1.0).contains(&fuzz) {
+		return RtStatus::InvalidArgument;
+	}
+	return unsafe { add_material(scene, MaterialEnum::new_metal(r, g, b, fuzz), material) };
+}
+
+/// # Safety
+/// `scene` has to be null or a scene from `rt_scene_new` that hasn't been freed.
+/// `material` has to be null or point to a `uint32_t` that can be written.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rt_scene_add_dielectric(scene: *mut RtScene, refraction_index: f64, material: *mut u32) -> RtStatus {
+	if !(refraction_index.is_finite() && refraction_index > 0.0) {
+		return RtStatus::InvalidArgument;
+	}
+	return unsafe { add_material(scene, MaterialEnum::new_dielectric(refraction_index), material) };
+}
+
+/// # Safety
+/// `scene` has to be null or a scene from `rt_scene_new` that hasn't been freed.
+/// `material` has to be null or point to a `uint32_t` that can be written.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rt_scene_add_diffuse_light(scene: *mut RtScene, r: f64, g: f64, b: f64, material: *mut u32) -> RtStatus {
+	if !valid_color(r, g, b) {
+		return RtStatus::InvalidArgument;
+	}
+	return unsafe { add_material(scene, MaterialEnum::new_diffuse_light(r, g, b), material) };
+}
+
+/// # Safety
+/// `scene` has to be null or a scene from `rt_scene_new` that hasn't been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rt_scene_add_sphere(scene: *mut RtScene, x: f64, y: f64, z: f64, radius: f64, material: u32) -> RtStatus {
+	let Some(scene) = (unsafe { scene.as_mut() }) else {
+		return RtStatus::NullPointer;
+	};
+	let Some(&material) = scene.materials.get(material as usize) else {
+		return RtStatus::InvalidArgument;
+	};
+	if ![x, y, z, radius].iter().all(|value| value.is_finite()) || radius <= 0.0 {
+		return RtStatus::InvalidArgument;
+	}
+	scene.world.add(x, y, z, radius, material);
+	return RtStatus::Ok;
+}
+
+/// positions holds vertex_count xyz triples, indices holds index_count / 3 triangles.
+///
+/// # Safety
+/// `scene` has to be null or a scene from `rt_scene_new` that hasn't been freed.
+/// `positions` has to point to `vertex_count * 3` doubles and `indices` to `index_count` ints,
+/// either can be null when its count is 0.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rt_scene_add_mesh(scene: *mut RtScene, positions: *const f64, vertex_count: usize, indices: *const u32, index_count: usize, material: u32) -> RtStatus {
+	let Some(scene) = (unsafe { scene.as_mut() }) else {
+		return RtStatus::NullPointer;
+	};
+	if (positions.is_null() && vertex_count > 0) || (indices.is_null() && index_count > 0) {
+		return RtStatus::NullPointer;
+	}
+	let Some(&material) = scene.materials.get(material as usize) else {
+		return RtStatus::InvalidArgument;
+	};
+	if index_count == 0 {
+		return RtStatus::Ok;
+	}
+
+	//a count from C can be big enough to wrap, which would read a short slice of positions.
+	let Some(position_count) = vertex_count.checked_mul(3) else {
+		return RtStatus::InvalidArgument;
+	};
+	let positions = unsafe { std::slice::from_raw_parts(positions, position_count) };
+	let indices = unsafe { std::slice::from_raw_parts(indices, index_count) };
+	if !positions.iter().all(|value| value.is_finite()) {
+		return RtStatus::InvalidArgument;
+	}
+	let vertices: Vec<Point> = positions.chunks(3).map(|p| Point::new(p[0], p[1], p[2])).collect();
+	let indices: Vec<usize> = indices.iter().map(|&index| index as usize).collect();
+	if !scene.world.add_mesh(&vertices, &indices, material) {
+		return RtStatus::InvalidArgument;
+	}
+	return RtStatus::Ok;
+}
+
+//CAMERA
+
+//null if width or height aren't positive.
+#[unsafe(no_mangle)]
+pub extern "C" fn rt_camera_new(width: i32, height: i32) -> *mut RtCamera {
+	if width <= 0 || height <= 0 {
+		return std::ptr::null_mut();
+	}
+	//the camera rounds the height down from the aspect ratio, half a pixel extra makes sure it lands on height.
+	let aspect_ratio = width as f64 / (height as f64 + 0.5);
+	let camera = Camera::new(aspect_ratio, width, 16);
+	return Box::into_raw(Box::new(RtCamera { camera }));
+}
+
+/// # Safety
+/// `camera` has to be null or a camera from `rt_camera_new`, and each camera can only be freed once.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rt_camera_free(camera: *mut RtCamera) {
+	if !camera.is_null() {
+		drop(unsafe { Box::from_raw(camera) });
+	}
+}
+
+/// vfov is the vertical field of view in degrees.
+///
+/// # Safety
+/// `camera` has to be null or a camera from `rt_camera_new` that hasn't been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rt_camera_look_at(
+	camera: *mut RtCamera,
+	from_x: f64, from_y: f64, from_z: f64,
+	at_x: f64, at_y: f64, at_z: f64,
+	up_x: f64, up_y: f64, up_z: f64,
+	vfov: f64,
+) -> RtStatus {
+	let Some(camera) = (unsafe { camera.as_mut() }) else {
+		return RtStatus::NullPointer;
+	};
+	let lookfrom = Point::new(from_x, from_y, from_z);
+	let lookat = Point::new(at_x, at_y, at_z);
+	let vup = Vector::new(up_x, up_y, up_z);
+	let view: Vector = lookat - lookfrom;
+	if !(vfov > 0.0 && vfov < 180.0) || view.length_squared() == 0.0 || view.cross(&vup).length_squared() == 0.0 {
+		return RtStatus::InvalidArgument;
+	}
+	camera.camera.lookfrom = lookfrom;
+	camera.camera.lookat = lookat;
+	camera.camera.vup = vup;
+	camera.camera.vfov = vfov;
+	camera.camera.initialize();
+	return RtStatus::Ok;
+}
+
+/// # Safety
+/// `camera` has to be null or a camera from `rt_camera_new` that hasn't been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rt_camera_set_samples(camera: *mut RtCamera, samples_per_pixel: i32, max_depth: i32) -> RtStatus {
+	let Some(camera) = (unsafe { camera.as_mut() }) else {
+		return RtStatus::NullPointer;
+	};
+	if samples_per_pixel <= 0 || max_depth <= 0 {
+		return RtStatus::InvalidArgument;
+	}
+	camera.camera.samples_per_pixel = samples_per_pixel;
+	camera.camera.max_depth = max_depth;
+	return RtStatus::Ok;
+}
+
+/// # Safety
+/// `camera` has to be null or a camera from `rt_camera_new` that hasn't been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rt_camera_set_seed(camera: *mut RtCamera, seed: u64) -> RtStatus {
+	let Some(camera) = (unsafe { camera.as_mut() }) else {
+		return RtStatus::NullPointer;
+	};
+	camera.camera.seed = seed;
+	return RtStatus::Ok;
+}
+
+/// 0 uses one thread per core.
+///
+/// # Safety
+/// `camera` has to be null or a camera from `rt_camera_new` that hasn't been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rt_camera_set_threads(camera: *mut RtCamera, threads: u32) -> RtStatus {
+	let Some(camera) = (unsafe { camera.as_mut() }) else {
+		return RtStatus::NullPointer;
+	};
+	camera.camera.threads = threads as usize;
+	return RtStatus::Ok;
+}
+
+/// rays that miss everything see this color instead of the sky.
+///
+/// # Safety
+/// `camera` has to be null or a camera from `rt_camera_new` that hasn't been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rt_camera_set_background(camera: *mut RtCamera, r: f64, g: f64, b: f64) -> RtStatus {
+	let Some(camera) = (unsafe { camera.as_mut() }) else {
+		return RtStatus::NullPointer;
+	};
+	if !valid_color(r, g, b) {
+		return RtStatus::InvalidArgument;
+	}
+	camera.camera.background = crate::camera::Background::Solid(Color::new(r, g, b));
+	return RtStatus::Ok;
+}
+
+//RENDER
+
+/// Renders scene into buffer as linear RGB floats, row by row from the top left.
+/// buffer_length is the number of floats in buffer and has to be at least width * height * 3.
+///
+/// # Safety
+/// `camera` and `scene` have to be null or live handles from `rt_camera_new` and `rt_scene_new`.
+/// `buffer` has to be null or point to `buffer_length` floats that can be written.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rt_render(camera: *const RtCamera, scene: *const RtScene, buffer: *mut f32, buffer_length: usize) -> RtStatus {
+	let (Some(camera), Some(scene)) = (unsafe { camera.as_ref() }, unsafe { scene.as_ref() }) else {
+		return RtStatus::NullPointer;
+	};
+	if buffer.is_null() {
+		return RtStatus::NullPointer;
+	}
+	let camera = &camera.camera;
+	let needed = camera.image_width as usize * camera.image_height() as usize * 3;
+	if buffer_length < needed {
+		return RtStatus::BufferTooSmall;
+	}
+	let buffer = unsafe { std::slice::from_raw_parts_mut(buffer, needed) };
+
+	let result = catch_unwind(AssertUnwindSafe(|| {
+		let framebuffer = camera.render_framebuffer(&scene.world);
+		for j in 0..framebuffer.height {
+			for i in 0..framebuffer.width {
+				let color = framebuffer.get(i, j);
+				let index = 3 * (j * framebuffer.width + i) as usize;
+				buffer[index] = color.x as f32;
+				buffer[index + 1] = color.y as f32;
+				buffer[index + 2] = color.z as f32;
+			}
+		}
+	}));
+	match result {
+		Ok(()) => return RtStatus::Ok,
+		Err(_) => return RtStatus::Internal,
+	}
+}
@@ -0,0 +1,120 @@
+use crate::PI;
+
+//Reconstruction filters, they decide how much a sample counts towards the pixels around it.
+//Each sample is splatted into every pixel whose center is within radius of it,
+//weighted by the filter at the offset between the two. Offsets are in pixels.
+#[derive(Debug, Clone, Copy)]
+pub enum Filter {
+	//every sample inside the radius counts the same, radius 0.5 is a plain per pixel average.
+	Box { radius: f64 },
+	//falls off linearly to zero at the radius.
+	Tent { radius: f64 },
+	//a gaussian shifted down so it reaches zero at the radius.
+	Gaussian { radius: f64, sigma: f64 },
+	//Mitchell-Netravali cubic, b = c = 1/3 is the recommended balance of blur and ringing.
+	Mitchell { radius: f64, b: f64, c: f64 },
+	//a sinc windowed by a wider sinc, tau is how many lobes fit in the radius.
+	Lanczos { radius: f64, tau: f64 },
+}
+
+impl Default for Filter {
+	fn default() -> Self { Filter::Box { radius: 0.5 } }
+}
+
+impl Filter {
+	pub fn new_box(radius: f64) -> Filter {
+		Filter::Box { radius }
+	}
+	pub fn new_tent(radius: f64) -> Filter {
+		Filter::Tent { radius }
+	}
+	pub fn new_gaussian(radius: f64) -> Filter {
+		Filter::Gaussian { radius, sigma: radius / 3.0 }
+	}
+	pub fn new_mitchell(radius: f64) -> Filter {
+		Filter::Mitchell { radius, b: 1.0 / 3.0, c: 1.0 / 3.0 }
+	}
+	pub fn new_lanczos(radius: f64) -> Filter {
+		Filter::Lanczos { radius, tau: 3.0 }
+	}
+
+	pub fn radius(&self) -> f64 {
+		match *self {
+			Filter::Box { radius } => radius,
+			Filter::Tent { radius } => radius,
+			Filter::Gaussian { radius, .. } => radius,
+			Filter::Mitchell { radius, .. } => radius,
+			Filter::Lanczos { radius, .. } => radius,
+		}
+	}
+
+	//how many pixels past its own a sample can reach.
+	pub fn margin(&self) -> i32 {
+		return (self.radius() + 0.5).floor() as i32;
+	}
+
+	//the filters are separable, so the weight is the 1d filter in x times the one in y.
+	pub fn evaluate(&self, x: f64, y: f64) -> f64 {
+		return self.evaluate_1d(x) * self.evaluate_1d(y);
+	}
+
+	//offset is from the sample to the pixel's center, the other filters only need how far that is.
+	fn evaluate_1d(&self, offset: f64) -> f64 {
+		let x = offset.abs();
+		match *self {
+			Filter::Box { radius } => {
+				//half open, so a sample right on the edge between two pixels counts for exactly one of them,
+				//the one it's the left or top edge of since a pixel covers [i, i+1).
+				if -radius < offset && offset <= radius {
+					return 1.0;
+				}
+				return 0.0;
+			}
+			Filter::Tent { radius } => {
+				return (radius - x).max(0.0);
+			}
+			Filter::Gaussian { radius, sigma } => {
+				if x >= radius {
+					return 0.0;
+				}
+				let gaussian = |v: f64| f64::exp(-(v * v) / (2.0 * sigma * sigma));
+				return (gaussian(x) - gaussian(radius)).max(0.0);
+			}
+			Filter::Mitchell { radius, b, c } => {
+				if x >= radius {
+					return 0.0;
+				}
+				//the cubic is defined over [-2, 2].
+				let x = 2.0 * x / radius;
+				if x < 1.0 {
+					return ((12.0 - 9.0 * b - 6.0 * c) * x * x * x
+						+ (-18.0 + 12.0 * b + 6.0 * c) * x * x
+						+ (6.0 - 2.0 * b)) / 6.0;
+				}
+				return ((-b - 6.0 * c) * x * x * x
+					+ (6.0 * b + 30.0 * c) * x * x
+					+ (-12.0 * b - 48.0 * c) * x
+					+ (8.0 * b + 24.0 * c)) / 6.0;
+			}
+			Filter::Lanczos { radius, tau } => {
+				if x >= radius {
+					return 0.0;
+				}
+				let x = x / radius;
+				return windowed_sinc(x, tau);
+			}
+		}
+	}
+}
+
+fn sinc(x: f64) -> f64 {
+	if x.abs() < 1e-5 {
+		return 1.0;
+	}
+	return f64::sin(PI * x) / (PI * x);
+}
+
+//x is in [0, 1] across the radius.
+fn windowed_sinc(x: f64, tau: f64) -> f64 {
+	return sinc(x * tau) * sinc(x);
+}
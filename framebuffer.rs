@@ -0,0 +1,367 @@
+use crate::Color;
+use crate::Vector;
+use crate::color::{write_color, luminance};
+use crate::filter::Filter;
+use crate::aov::{Aov, AovSample};
+use crate::rng::mix_bits;
+use crate::exr::{ExrImage, ExrCompression, ExrPixelType};
+
+use std::fs::File;
+use std::io::prelude::*;
+
+//Everything accumulated for one pixel so far.
+//weighted_sum and weight_sum collect the filtered samples splatted in from this pixel and its neighbours.
+//count, mean and m2 are Welford's running mean and variance of the brightness of the pixel's own samples.
+//The lighting passes are filtered the same way as the color, the geometry passes
+//are plain averages of the pixel's own samples that hit something.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PixelAccumulator {
+	pub weighted_sum: Color,
+	pub weight_sum: f64,
+	pub count: i32,
+	pub mean: f64,
+	pub m2: f64,
+
+	pub emission_sum: Color,
+	pub direct_sum: Color,
+	pub indirect_sum: Color,
+
+	pub hit_count: i32,
+	pub depth_sum: f64,
+	pub normal_sum: Vector,
+	pub albedo_sum: Color,
+	//ids can't be averaged, so these come from the first sample that hit something.
+	pub object_id: i32,
+	pub material_id: u32,
+}
+
+impl PixelAccumulator {
+	//records one of this pixel's own samples for the sample count, variance and geometry passes.
+	pub fn add_sample(&mut self, sample: Color, aov: &AovSample) {
+		self.count += 1;
+		let x = luminance(&sample);
+		let delta = x - self.mean;
+		self.mean += delta / self.count as f64;
+		self.m2 += delta * (x - self.mean);
+
+		if aov.hit {
+			if self.hit_count == 0 {
+				self.object_id = aov.object_id;
+				self.material_id = aov.material_id;
+			}
+			self.hit_count += 1;
+			self.depth_sum += aov.depth;
+			self.normal_sum += aov.normal;
+			self.albedo_sum += aov.albedo;
+		}
+	}
+	pub fn splat(&mut self, sample: Color, aov: &AovSample, weight: f64) {
+		self.weighted_sum += sample * weight;
+		self.weight_sum += weight;
+		self.emission_sum += aov.emission * weight;
+		self.direct_sum += aov.direct * weight;
+		self.indirect_sum += aov.indirect * weight;
+	}
+
+	//adds the splats of other to this pixel, leaving its own samples alone.
+	pub fn add_splats(&mut self, other: &PixelAccumulator) {
+		self.weighted_sum += other.weighted_sum;
+		self.weight_sum += other.weight_sum;
+		self.emission_sum += other.emission_sum;
+		self.direct_sum += other.direct_sum;
+		self.indirect_sum += other.indirect_sum;
+	}
+
+	pub fn color(&self) -> Color {
+		if self.weight_sum == 0.0 {
+			return Color::null_vector();
+		}
+		return self.weighted_sum / self.weight_sum;
+	}
+
+	pub fn emission(&self) -> Color {
+		return self.filtered(self.emission_sum);
+	}
+	pub fn direct(&self) -> Color {
+		return self.filtered(self.direct_sum);
+	}
+	pub fn indirect(&self) -> Color {
+		return self.filtered(self.indirect_sum);
+	}
+	fn filtered(&self, sum: Color) -> Color {
+		if self.weight_sum == 0.0 {
+			return Color::null_vector();
+		}
+		return sum / self.weight_sum;
+	}
+
+	//infinite when none of the pixel's samples hit anything.
+	pub fn depth(&self) -> f64 {
+		if self.hit_count == 0 {
+			return f64::INFINITY;
+		}
+		return self.depth_sum / self.hit_count as f64;
+	}
+	pub fn normal(&self) -> Vector {
+		return self.normal_sum.normalize();
+	}
+	pub fn albedo(&self) -> Color {
+		if self.hit_count == 0 {
+			return Color::null_vector();
+		}
+		return self.albedo_sum / self.hit_count as f64;
+	}
+
+	//true once the 95% confidence interval is within tolerance of the mean.
+	//very dark pixels are compared against 0.01 so they can still finish.
+	pub fn converged(&self, tolerance: f64) -> bool {
+		if self.count < 2 {
+			return false;
+		}
+		let variance = self.m2 / (self.count - 1) as f64;
+		let half_width = 1.96 * f64::sqrt(variance / self.count as f64);
+		return half_width <= tolerance * self.mean.max(0.01);
+	}
+}
+
+//The accumulated samples of a render, stored row by row from the top left.
+//Renders can keep adding samples to it, the image is always the current average.
+#[derive(Clone)]
+pub struct Framebuffer {
+	pub width: i32,
+	pub height: i32,
+	pixels: Vec<PixelAccumulator>,
+}
+
+impl Framebuffer {
+	pub fn new(width: i32, height: i32) -> Framebuffer {
+		Framebuffer {
+			width,
+			height,
+			pixels: vec![PixelAccumulator::default(); (width * height) as usize],
+		}
+	}
+
+	pub fn get(&self, i: i32, j: i32) -> Color {
+		return self.pixels[(j * self.width + i) as usize].color();
+	}
+	pub fn pixel(&self, i: i32, j: i32) -> PixelAccumulator {
+		return self.pixels[(j * self.width + i) as usize];
+	}
+	pub fn set_pixel(&mut self, i: i32, j: i32, pixel: PixelAccumulator) {
+		self.pixels[(j * self.width + i) as usize] = pixel;
+	}
+
+	//Takes over the pixels the finished tiles own, then adds the splats they spread into their
+	//neighbours'. the owned pixels carried on from this framebuffer's sums, so a pixel adds up its
+	//samples in the same order however many passes the render is split into.
+	pub fn merge_tiles(&mut self, tiles: &[&FilmTile]) {
+		for tile in tiles {
+			for j in tile.y0..tile.y1 {
+				for i in tile.x0..tile.x1 {
+					self.pixels[(j * self.width + i) as usize] = *tile.pixel(i, j);
+				}
+			}
+		}
+		for tile in tiles {
+			for j in tile.region_y0..tile.region_y1 {
+				for i in tile.region_x0..tile.region_x1 {
+					if !tile.owns(i, j) {
+						self.pixels[(j * self.width + i) as usize].add_splats(tile.pixel(i, j));
+					}
+				}
+			}
+		}
+	}
+
+	pub fn sample_count(&self, i: i32, j: i32) -> i32 {
+		return self.pixels[(j * self.width + i) as usize].count;
+	}
+
+	pub fn write_ppm(&self, file: &mut File) {
+		let _ = file.write_all(format!("P3\n{0} {1}\n255\n", self.width, self.height).as_bytes());
+		for pixel in &self.pixels {
+			write_color(&pixel.color(), file);
+		}
+	}
+
+	//Writes how many samples each pixel took, white is the pixel that took the most.
+	pub fn write_sample_heatmap(&self, file: &mut File) {
+		let _ = file.write_all(format!("P3\n{0} {1}\n255\n", self.width, self.height).as_bytes());
+		let max_count = self.pixels.iter().map(|pixel| pixel.count).max().unwrap_or(0).max(1);
+		for pixel in &self.pixels {
+			let level = (255.0 * pixel.count as f64 / max_count as f64) as i32;
+			let _ = file.write_all(format!("{level} {level} {level}\n").as_bytes());
+		}
+	}
+
+	//Writes one of the extra passes as its own image.
+	//depth is scaled so the farthest hit is white, normals are mapped from [-1,1] to [0,1]
+	//and every id gets its own made up color. Pixels that didn't hit anything are black.
+	pub fn write_aov(&self, aov: Aov, file: &mut File) {
+		if aov == Aov::SampleCount {
+			self.write_sample_heatmap(file);
+			return;
+		}
+		let _ = file.write_all(format!("P3\n{0} {1}\n255\n", self.width, self.height).as_bytes());
+		let max_depth = self.pixels.iter()
+			.map(|pixel| pixel.depth())
+			.filter(|depth| depth.is_finite())
+			.fold(0.0, f64::max);
+
+		for pixel in &self.pixels {
+			let color = match aov {
+				Aov::Depth if pixel.hit_count > 0 && max_depth > 0.0 => {
+					let level = pixel.depth() / max_depth;
+					Color::new(level, level, level)
+				}
+				Aov::Normal if pixel.hit_count > 0 => 0.5 * (pixel.normal() + Color::new(1.0, 1.0, 1.0)),
+				Aov::Albedo => pixel.albedo(),
+				Aov::ObjectId if pixel.hit_count > 0 => id_color(pixel.object_id as u64),
+				Aov::MaterialId if pixel.hit_count > 0 => id_color(pixel.material_id as u64),
+				Aov::Direct => pixel.direct(),
+				Aov::Indirect => pixel.indirect(),
+				Aov::Emission => pixel.emission(),
+				_ => Color::null_vector(),
+			};
+			//the lighting passes add up to the beauty image, so they get the same gamma.
+			if matches!(aov, Aov::Direct | Aov::Indirect | Aov::Emission | Aov::Albedo) {
+				write_color(&color, file);
+			} else {
+				write_linear(&color, file);
+			}
+		}
+	}
+
+	//Puts the image and every pass into one EXR image, as named layers.
+	//colors use pixel_type, depth is always float since half runs out of precision quickly
+	//and the ids and sample counts are whole numbers. Ids are shifted up by one so 0 means nothing was hit.
+	pub fn to_exr(&self, pixel_type: ExrPixelType, compression: ExrCompression) -> ExrImage {
+		let mut image = ExrImage::new(self.width, self.height, compression);
+		let layer = |value: fn(&PixelAccumulator) -> Color| -> Vec<Color> {
+			return self.pixels.iter().map(value).collect();
+		};
+		image.add_layer("", ["R", "G", "B"], pixel_type, &layer(PixelAccumulator::color));
+		image.add_layer(Aov::Albedo.name(), ["R", "G", "B"], pixel_type, &layer(PixelAccumulator::albedo));
+		image.add_layer(Aov::Normal.name(), ["X", "Y", "Z"], pixel_type, &layer(PixelAccumulator::normal));
+		image.add_layer(Aov::Emission.name(), ["R", "G", "B"], pixel_type, &layer(PixelAccumulator::emission));
+		image.add_layer(Aov::Direct.name(), ["R", "G", "B"], pixel_type, &layer(PixelAccumulator::direct));
+		image.add_layer(Aov::Indirect.name(), ["R", "G", "B"], pixel_type, &layer(PixelAccumulator::indirect));
+
+		let depth: Vec<f64> = self.pixels.iter().map(|pixel| pixel.depth()).collect();
+		image.add_channel(&format!("{}.Z", Aov::Depth.name()), ExrPixelType::Float, depth);
+		let object_ids: Vec<f64> = self.pixels.iter()
+			.map(|pixel| if pixel.hit_count > 0 { pixel.object_id as f64 + 1.0 } else { 0.0 })
+			.collect();
+		image.add_channel(&format!("{}.id", Aov::ObjectId.name()), ExrPixelType::Uint, object_ids);
+		let material_ids: Vec<f64> = self.pixels.iter()
+			.map(|pixel| if pixel.hit_count > 0 { pixel.material_id.max(1) as f64 } else { 0.0 })
+			.collect();
+		image.add_channel(&format!("{}.id", Aov::MaterialId.name()), ExrPixelType::Uint, material_ids);
+		let counts: Vec<f64> = self.pixels.iter().map(|pixel| pixel.count as f64).collect();
+		image.add_channel(&format!("{}.count", Aov::SampleCount.name()), ExrPixelType::Uint, counts);
+		return image;
+	}
+
+	//Writes every pass next to each other, as prefix_depth.ppm, prefix_normal.ppm and so on.
+	pub fn write_aovs(&self, prefix: &str) -> std::io::Result<()> {
+		for aov in Aov::ALL {
+			self.write_aov(aov, &mut File::create(format!("{prefix}_{}.ppm", aov.name()))?);
+		}
+		return Ok(());
+	}
+}
+
+//for passes that hold data rather than light, written without gamma.
+fn write_linear(color: &Color, file: &mut File) {
+	let r = (255.999 * color.x.clamp(0.0, 1.0)) as i32;
+	let g = (255.999 * color.y.clamp(0.0, 1.0)) as i32;
+	let b = (255.999 * color.z.clamp(0.0, 1.0)) as i32;
+	let _ = file.write_all(format!("{r} {g} {b}\n").as_bytes());
+}
+
+//a bright color picked from the id, so neighbouring ids are easy to tell apart.
+fn id_color(id: u64) -> Color {
+	let bits = mix_bits(id.wrapping_add(1));
+	let channel = |shift: u32| 0.2 + 0.8 * ((bits >> shift) & 0xff) as f64 / 255.0;
+	return Color::new(channel(0), channel(8), channel(16));
+}
+
+//The pixels one tile of a render owns, plus the margin around them its filter reaches into.
+//Tiles are rendered on their own and merged into the framebuffer afterwards,
+//so threads never write to the same pixel at once.
+pub struct FilmTile {
+	x0: i32,
+	y0: i32,
+	x1: i32,
+	y1: i32,
+	region_x0: i32,
+	region_y0: i32,
+	region_x1: i32,
+	region_y1: i32,
+	pixels: Vec<PixelAccumulator>,
+}
+
+impl FilmTile {
+	//x1 and y1 are exclusive. The owned pixels start as they are in the framebuffer,
+	//the margin around them starts at zero.
+	pub fn new(framebuffer: &Framebuffer, x0: i32, y0: i32, x1: i32, y1: i32, margin: i32) -> FilmTile {
+		let region_x0 = (x0 - margin).max(0);
+		let region_y0 = (y0 - margin).max(0);
+		let region_x1 = (x1 + margin).min(framebuffer.width);
+		let region_y1 = (y1 + margin).min(framebuffer.height);
+
+		let mut pixels: Vec<PixelAccumulator> = Vec::new();
+		for j in region_y0..region_y1 {
+			for i in region_x0..region_x1 {
+				let mut pixel = PixelAccumulator::default();
+				if i >= x0 && i < x1 && j >= y0 && j < y1 {
+					pixel = framebuffer.pixel(i, j);
+				}
+				pixels.push(pixel);
+			}
+		}
+
+		FilmTile {
+			x0,
+			y0,
+			x1,
+			y1,
+			region_x0,
+			region_y0,
+			region_x1,
+			region_y1,
+			pixels,
+		}
+	}
+
+	pub fn owns(&self, i: i32, j: i32) -> bool {
+		return i >= self.x0 && i < self.x1 && j >= self.y0 && j < self.y1;
+	}
+	pub fn pixel(&self, i: i32, j: i32) -> &PixelAccumulator {
+		return &self.pixels[((j - self.region_y0) * (self.region_x1 - self.region_x0) + (i - self.region_x0)) as usize];
+	}
+	pub fn pixel_mut(&mut self, i: i32, j: i32) -> &mut PixelAccumulator {
+		let width = self.region_x1 - self.region_x0;
+		return &mut self.pixels[((j - self.region_y0) * width + (i - self.region_x0)) as usize];
+	}
+
+	//Spreads a sample taken at film position (x, y) over the pixels around it.
+	//pixel (i, j) covers [i, i+1) x [j, j+1), so its center is at (i + 0.5, j + 0.5).
+	pub fn splat(&mut self, x: f64, y: f64, sample: Color, aov: &AovSample, filter: &Filter) {
+		let radius = filter.radius();
+		let min_i = ((x - 0.5 - radius).ceil() as i32).max(self.region_x0);
+		let max_i = ((x - 0.5 + radius).floor() as i32).min(self.region_x1 - 1);
+		let min_j = ((y - 0.5 - radius).ceil() as i32).max(self.region_y0);
+		let max_j = ((y - 0.5 + radius).floor() as i32).min(self.region_y1 - 1);
+
+		for j in min_j..=max_j {
+			for i in min_i..=max_i {
+				let weight = filter.evaluate(i as f64 + 0.5 - x, j as f64 + 0.5 - y);
+				if weight != 0.0 {
+					self.pixel_mut(i, j).splat(sample, aov, weight);
+				}
+			}
+		}
+	}
+}
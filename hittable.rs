@@ -4,6 +4,14 @@ use crate::Point;
 use crate::Ray;
 use crate::Interval;
 use crate::material::*;
+use crate::light::LightEnum;
+use crate::PI;
+use crate::sampler::Sampler;
+use crate::onb::Onb;
+use crate::sampling;
+use crate::aabb::Aabb;
+use crate::bvh::{Bvh, BvhTree};
+use crate::stats;
 
 #[derive(Clone, Copy, Default)]
 pub struct HitRecord {
@@ -11,13 +19,18 @@ pub struct HitRecord {
 	pub normal: Vector,
 	pub material: MaterialEnum,
 	pub front_face: bool,
-	t: f64,
+	pub t: f64,
+	//where on the surface the hit is, for textures.
+	pub u: f64,
+	pub v: f64,
+	//index of the object in the HittableList that was hit.
+	pub object_id: i32,
 }
 
 impl HitRecord {
 	//ensures that the normal is facing outwards and not inwards.
-	fn set_face_normal (&mut self, r: &Ray, outward_normal: &Vector) {
-		if Point::dot(&r.dir, &outward_normal) < 0.0 {
+	pub(crate) fn set_face_normal (&mut self, r: &Ray, outward_normal: &Vector) {
+		if Point::dot(&r.dir, outward_normal) < 0.0 {
 			self.front_face = true;
 			self.normal = *outward_normal;
 		}
@@ -26,10 +39,91 @@ impl HitRecord {
 			self.normal = *outward_normal * -1.0;
 		}
 	}
+
+	//the normal pointing out of the object, whichever side the ray came from.
+	pub fn outward_normal(&self) -> Vector {
+		if self.front_face {
+			return self.normal;
+		}
+		return self.normal * -1.0;
+	}
+}
+
+//A stretch of a ray that's inside a solid, from where it goes in to where it comes out.
+//Both records are filled in like hit would, with the normal facing the ray.
+#[derive(Clone, Copy)]
+pub struct Span {
+	pub enter: HitRecord,
+	pub exit: HitRecord,
 }
 
-pub trait Hittable {
+//how many surfaces the default spans looks for before giving up.
+const MAX_CROSSINGS: usize = 64;
+
+//A point picked on the surface of a hittable, used for sampling lights.
+//pdf is with respect to solid angle as seen from the point the sample was taken from.
+#[derive(Debug, Clone, Copy)]
+pub struct SurfaceSample {
+	pub point: Point,
+	pub normal: Vector,
+	pub pdf: f64,
+}
+
+pub trait Hittable: Send + Sync {
 	fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool;
+	//a box the whole object fits in, for the bvh.
+	fn bounding_box(&self) -> Aabb;
+
+	//Only shapes that can act as area lights need to override these.
+	fn is_emissive(&self) -> bool {
+		return false;
+	}
+	fn sample_surface(&self, _origin: &Point, _sampler: &mut Sampler) -> Option<SurfaceSample> {
+		return None;
+	}
+	//the solid angle pdf that sample_surface would pick the first point hit along direction.
+	fn surface_pdf(&self, _origin: &Point, _direction: &Vector) -> f64 {
+		return 0.0;
+	}
+
+	//Adds every span of r inside the object to spans, in order along the ray and over the whole line,
+	//behind the origin as well, which is what csg needs. It only makes sense for closed shapes.
+	//This walks the surfaces one hit at a time, shapes that can do it directly override it.
+	fn spans(&self, r: &Ray, spans: &mut Vec<Span>) {
+		let mut rec = HitRecord::default();
+		let mut enter = HitRecord::default();
+		let mut depth = 0;
+		let mut t_min = f64::NEG_INFINITY;
+		for _ in 0..MAX_CROSSINGS {
+			if !self.hit(r, Interval::new(t_min, f64::INFINITY), &mut rec) {
+				return;
+			}
+			//step past the surface, or the same one would be found again.
+			t_min = rec.t + 1e-9 * rec.t.abs().max(1.0);
+			if rec.front_face {
+				if depth == 0 {
+					enter = rec;
+				}
+				depth += 1;
+			} else if depth > 0 {
+				depth -= 1;
+				if depth == 0 {
+					spans.push(Span { enter, exit: rec });
+				}
+			}
+		}
+	}
+}
+
+//turns an area pdf for a point on a surface into a solid angle pdf seen from origin.
+pub(crate) fn area_to_solid_angle(origin: &Point, point: &Point, normal: &Vector, area_pdf: f64) -> f64 {
+	let to_point: Vector = *point - *origin;
+	let distance_squared = to_point.length_squared();
+	let cosine = (Vector::dot(&to_point, normal) / distance_squared.sqrt()).abs();
+	if cosine < 1e-8 {
+		return 0.0;
+	}
+	return area_pdf * distance_squared / cosine;
 }
 
 pub struct Sphere {
@@ -51,6 +145,16 @@ impl Sphere {
 			material,
 		}
 	}
+
+	fn fill_record(&self, r: &Ray, t: f64, rec: &mut HitRecord) {
+		rec.t = t;
+		rec.hit_point = r.at(rec.t);
+		//the normal is a unit vector.
+		let outward_normal: Vector = (rec.hit_point - self.center) / self.radius;
+		rec.set_face_normal(r, &outward_normal);
+		(rec.u, rec.v) = sphere_uv(&outward_normal);
+		rec.material = self.material;
+	}
 }
 
 
@@ -79,33 +183,379 @@ impl Hittable for Sphere {
 			}
 		}
 
-		rec.t = root;
-		rec.hit_point = r.at(rec.t);
-		//the normal is a unit vector.
-		rec.set_face_normal(&r, &((rec.hit_point - self.center) / self.radius));
+		self.fill_record(r, root, rec);
+		return true;
+	}
+
+	//a ray along a line through the sphere goes in and out once.
+	fn spans(&self, r: &Ray, spans: &mut Vec<Span>) {
+		let oc: Vector = self.center - r.origin;
+		let a: f64 = r.dir.length_squared();
+		let h: f64 = Point::dot(&r.dir, &oc);
+		let c: f64 = oc.length_squared() - (self.radius * self.radius);
+		let discriminant: f64 = h * h - a * c;
+		if discriminant <= 0.0 {
+			return;
+		}
+		let sqrtd: f64 = f64::sqrt(discriminant);
+		let mut span = Span {
+			enter: HitRecord::default(),
+			exit: HitRecord::default(),
+		};
+		self.fill_record(r, (h - sqrtd) / a, &mut span.enter);
+		self.fill_record(r, (h + sqrtd) / a, &mut span.exit);
+		spans.push(span);
+	}
+
+	fn bounding_box(&self) -> Aabb {
+		let extent = Vector::new(self.radius, self.radius, self.radius);
+		return Aabb::from_points(&(self.center - extent), &(self.center + extent));
+	}
+
+	fn is_emissive(&self) -> bool {
+		return self.material.is_emissive();
+	}
+	//from outside, pick a direction uniformly in the cone the sphere covers
+	//so no samples get wasted on the back. from inside, pick uniformly over the area.
+	fn sample_surface(&self, origin: &Point, sampler: &mut Sampler) -> Option<SurfaceSample> {
+		let oc: Vector = self.center - *origin;
+		let distance_squared = oc.length_squared();
+		let radius_squared = self.radius * self.radius;
+
+		if distance_squared <= radius_squared {
+			let normal: Vector = sampling::uniform_sphere(sampler.get_2d());
+			let point: Point = self.center + normal * self.radius;
+			let area = 4.0 * PI * radius_squared;
+			return Some(SurfaceSample {
+				point,
+				normal,
+				pdf: area_to_solid_angle(origin, &point, &normal, 1.0 / area),
+			});
+		}
+
+		let cos_max = f64::sqrt(1.0 - radius_squared / distance_squared);
+		let uvw = Onb::new(&oc);
+		let local: Vector = sampling::uniform_cone(sampler.get_2d(), cos_max);
+		let direction: Vector = uvw.local(&local).normalize();
+
+		//distance along direction to the near side of the sphere.
+		let distance = distance_squared.sqrt();
+		let cos_theta = local.z;
+		let sin_theta_squared = (1.0 - cos_theta * cos_theta).max(0.0);
+		let t = distance * cos_theta - f64::sqrt((radius_squared - distance_squared * sin_theta_squared).max(0.0));
+		let point: Point = *origin + direction * t;
+
+		return Some(SurfaceSample {
+			point,
+			normal: (point - self.center) / self.radius,
+			pdf: sampling::uniform_cone_pdf(cos_max),
+		});
+	}
+	fn surface_pdf(&self, origin: &Point, direction: &Vector) -> f64 {
+		let mut rec = HitRecord::default();
+		if !self.hit(&Ray::new(*origin, *direction), Interval::new(0.001, f64::INFINITY), &mut rec) {
+			return 0.0;
+		}
+
+		let distance_squared = (self.center - *origin).length_squared();
+		let radius_squared = self.radius * self.radius;
+		if distance_squared <= radius_squared {
+			let area = 4.0 * PI * radius_squared;
+			return area_to_solid_angle(origin, &rec.hit_point, &rec.normal, 1.0 / area);
+		}
+		let cos_max = f64::sqrt(1.0 - radius_squared / distance_squared);
+		return sampling::uniform_cone_pdf(cos_max);
+	}
+}
+
+//u goes once around the y axis starting at -x, v goes from the bottom (-y) to the top.
+pub(crate) fn sphere_uv(p: &Vector) -> (f64, f64) {
+	let theta = f64::acos(-p.y.clamp(-1.0, 1.0));
+	let phi = f64::atan2(-p.z, p.x) + PI;
+	return (phi / (2.0 * PI), theta / PI);
+}
+
+//A parallelogram with one corner at q and sides u and v.
+pub struct Quad {
+	q: Point,
+	u: Vector,
+	v: Vector,
+	//w is used to find the plane coordinates of a hit point.
+	w: Vector,
+	normal: Vector,
+	d: f64,
+	area: f64,
+	material: MaterialEnum,
+}
+
+impl Quad {
+	pub fn new(q: Point, u: Vector, v: Vector, material: MaterialEnum) -> Quad {
+		let n: Vector = u.cross(&v);
+		let normal: Vector = n.normalize();
+		Quad {
+			q,
+			u,
+			v,
+			w: n / Vector::dot(&n, &n),
+			normal,
+			d: Vector::dot(&normal, &q),
+			area: n.length(),
+			material,
+		}
+	}
+}
+
+impl Hittable for Quad {
+	fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+		let denominator = Vector::dot(&self.normal, &r.dir);
+
+		//the ray is parallel to the plane.
+		if denominator.abs() < 1e-8 {
+			return false;
+		}
+
+		let t = (self.d - Vector::dot(&self.normal, &r.origin)) / denominator;
+		if !ray_t.contains(t) {
+			return false;
+		}
+
+		//check the hit point is inside the quad using its plane coordinates.
+		let intersection: Point = r.at(t);
+		let planar_hit: Vector = intersection - self.q;
+		let alpha = Vector::dot(&self.w, &planar_hit.cross(&self.v));
+		let beta = Vector::dot(&self.w, &self.u.cross(&planar_hit));
+		if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+			return false;
+		}
+
+		rec.t = t;
+		rec.hit_point = intersection;
+		rec.u = alpha;
+		rec.v = beta;
+		rec.set_face_normal(r, &self.normal);
 		rec.material = self.material;
 
 		return true;
 	}
+
+	fn bounding_box(&self) -> Aabb {
+		let diagonal1 = Aabb::from_points(&self.q, &(self.q + self.u + self.v));
+		let diagonal2 = Aabb::from_points(&(self.q + self.u), &(self.q + self.v));
+		return Aabb::enclosing(&diagonal1, &diagonal2);
+	}
+
+	fn is_emissive(&self) -> bool {
+		return self.material.is_emissive();
+	}
+	fn sample_surface(&self, origin: &Point, sampler: &mut Sampler) -> Option<SurfaceSample> {
+		let (a, b) = sampler.get_2d();
+		let point: Point = self.q + (a * self.u) + (b * self.v);
+		return Some(SurfaceSample {
+			point,
+			normal: self.normal,
+			pdf: area_to_solid_angle(origin, &point, &self.normal, 1.0 / self.area),
+		});
+	}
+	fn surface_pdf(&self, origin: &Point, direction: &Vector) -> f64 {
+		let mut rec = HitRecord::default();
+		if !self.hit(&Ray::new(*origin, *direction), Interval::new(0.001, f64::INFINITY), &mut rec) {
+			return 0.0;
+		}
+		return area_to_solid_angle(origin, &rec.hit_point, &self.normal, 1.0 / self.area);
+	}
+}
+
+//TRIANGLE
+
+#[derive(Clone, Copy)]
+pub struct Triangle {
+	v0: Point,
+	edge1: Vector,
+	edge2: Vector,
+	normal: Vector,
+	area: f64,
+	material: MaterialEnum,
+}
+
+impl Triangle {
+	//the normal follows the right hand rule, counter clockwise vertices face the viewer.
+	pub fn new(v0: Point, v1: Point, v2: Point, material: MaterialEnum) -> Triangle {
+		let edge1: Vector = v1 - v0;
+		let edge2: Vector = v2 - v0;
+		let n: Vector = edge1.cross(&edge2);
+		Triangle {
+			v0,
+			edge1,
+			edge2,
+			normal: n.normalize(),
+			area: 0.5 * n.length(),
+			material,
+		}
+	}
+}
+
+impl Hittable for Triangle {
+	//Moller-Trumbore, solves for t and the barycentric coordinates in one go.
+	fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+		let p: Vector = r.dir.cross(&self.edge2);
+		let determinant = Vector::dot(&self.edge1, &p);
+		//the ray is parallel to the triangle.
+		if determinant.abs() < 1e-12 {
+			return false;
+		}
+		let inverse = 1.0 / determinant;
+
+		let s: Vector = r.origin - self.v0;
+		let b1 = Vector::dot(&s, &p) * inverse;
+		if !(0.0..=1.0).contains(&b1) {
+			return false;
+		}
+		let q: Vector = s.cross(&self.edge1);
+		let b2 = Vector::dot(&r.dir, &q) * inverse;
+		if b2 < 0.0 || b1 + b2 > 1.0 {
+			return false;
+		}
+
+		let t = Vector::dot(&self.edge2, &q) * inverse;
+		if !ray_t.contains(t) {
+			return false;
+		}
+
+		rec.t = t;
+		rec.hit_point = r.at(t);
+		rec.u = b1;
+		rec.v = b2;
+		rec.set_face_normal(r, &self.normal);
+		rec.material = self.material;
+		return true;
+	}
+
+	fn bounding_box(&self) -> Aabb {
+		let v1: Point = self.v0 + self.edge1;
+		let v2: Point = self.v0 + self.edge2;
+		return Aabb::enclosing(&Aabb::from_points(&self.v0, &v1), &Aabb::from_points(&self.v0, &v2));
+	}
+
+	fn is_emissive(&self) -> bool {
+		return self.material.is_emissive();
+	}
+	fn sample_surface(&self, origin: &Point, sampler: &mut Sampler) -> Option<SurfaceSample> {
+		let (_, b1, b2) = sampling::uniform_triangle(sampler.get_2d());
+		let point: Point = self.v0 + (b1 * self.edge1) + (b2 * self.edge2);
+		return Some(SurfaceSample {
+			point,
+			normal: self.normal,
+			pdf: area_to_solid_angle(origin, &point, &self.normal, sampling::uniform_triangle_pdf(self.area)),
+		});
+	}
+	fn surface_pdf(&self, origin: &Point, direction: &Vector) -> f64 {
+		let mut rec = HitRecord::default();
+		if !self.hit(&Ray::new(*origin, *direction), Interval::new(0.001, f64::INFINITY), &mut rec) {
+			return 0.0;
+		}
+		return area_to_solid_angle(origin, &rec.hit_point, &self.normal, sampling::uniform_triangle_pdf(self.area));
+	}
 }
 
 //#[derive(Debug, Copy, Clone)]
+#[derive(Default)]
 pub struct HittableList {
 	objects: Vec<Box<dyn Hittable>>,
+	//indices into objects of everything that emits light.
+	lights: Vec<usize>,
+	//point, spot and sun lights, these have no surface to hit.
+	punctual_lights: Vec<LightEnum>,
+	//built by build_bvh, adding an object throws it away again.
+	bvh: Option<BvhTree>,
 }
 
 impl HittableList {
 	pub fn new() -> HittableList {
 		HittableList {
 			objects: Vec::new(),
+			lights: Vec::new(),
+			punctual_lights: Vec::new(),
+			bvh: None,
 		}
 	}
 	pub fn raw_add(&mut self, object: Box<dyn Hittable>) {
+		if object.is_emissive() {
+			self.lights.push(self.objects.len());
+		}
 		self.objects.push(object);
+		self.bvh = None;
 	}
 	pub fn add(&mut self, x:f64, y:f64, z:f64, radius: f64, material: MaterialEnum) {
 		self.raw_add(Box::new(Sphere::new(Point::new(x,y,z), radius, material)));
 	}
+	pub fn add_quad(&mut self, q: Point, u: Vector, v: Vector, material: MaterialEnum) {
+		self.raw_add(Box::new(Quad::new(q, u, v, material)));
+	}
+	pub fn add_triangle(&mut self, v0: Point, v1: Point, v2: Point, material: MaterialEnum) {
+		self.raw_add(Box::new(Triangle::new(v0, v1, v2, material)));
+	}
+	//Adds a triangle for every three indices into vertices.
+	//returns false and adds nothing if an index is out of range or there are leftover indices.
+	pub fn add_mesh(&mut self, vertices: &[Point], indices: &[usize], material: MaterialEnum) -> bool {
+		if !indices.len().is_multiple_of(3) || indices.iter().any(|&index| index >= vertices.len()) {
+			return false;
+		}
+		for face in indices.chunks(3) {
+			self.add_triangle(vertices[face[0]], vertices[face[1]], vertices[face[2]], material);
+		}
+		return true;
+	}
+
+	//Groups the objects into a bvh so a ray only tests the ones whose boxes it passes through.
+	//Without it every ray tests every object, which is fine for a handful of them.
+	//call it again after adding more objects.
+	pub fn build_bvh(&mut self) {
+		let boxes: Vec<Aabb> = self.objects.iter().map(|object| object.bounding_box()).collect();
+		self.bvh = Some(BvhTree::new(&boxes));
+	}
+	pub fn has_bvh(&self) -> bool {
+		return self.bvh.is_some();
+	}
+	pub fn len(&self) -> usize {
+		return self.objects.len();
+	}
+	pub fn is_empty(&self) -> bool {
+		return self.objects.is_empty();
+	}
+
+	pub fn add_light(&mut self, light: LightEnum) {
+		self.punctual_lights.push(light);
+	}
+	pub fn punctual_lights(&self) -> &[LightEnum] {
+		return &self.punctual_lights;
+	}
+
+	pub fn light_count(&self) -> usize {
+		return self.lights.len();
+	}
+
+	//Picks one of the lights uniformly and samples a point on it.
+	//the returned pdf includes the chance of picking that light.
+	pub fn sample_light(&self, origin: &Point, sampler: &mut Sampler) -> Option<SurfaceSample> {
+		if self.lights.is_empty() {
+			return None;
+		}
+		let count = self.lights.len();
+		let index = ((sampler.get_1d() * count as f64) as usize).min(count - 1);
+		let mut sample = self.objects[self.lights[index]].sample_surface(origin, sampler)?;
+		sample.pdf /= count as f64;
+		return Some(sample);
+	}
+
+	//the pdf sample_light has of choosing direction from origin and landing on the object hit, which is
+	//the one at index in objects. Other lights further along the same direction don't count, the sample
+	//would have been a point on them instead.
+	pub fn light_pdf(&self, hit: usize, origin: &Point, direction: &Vector) -> f64 {
+		if !self.lights.contains(&hit) {
+			return 0.0;
+		}
+		return self.objects[hit].surface_pdf(origin, direction) / self.lights.len() as f64;
+	}
 
 	// This goes through all the existing objects for every ray, and figures out where the closest point hit was.
 	//and since t_max is closest_so_far, objects dont get considered unless theyre closer than the closest_so_far.
@@ -113,17 +563,51 @@ impl HittableList {
 	//This can check multiple surfaces/spheres
 	pub fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
 		let mut temp_rec: HitRecord = HitRecord::default();
+
+		if let Some(bvh) = &self.bvh {
+			return bvh.closest_hit(r, ray_t, |index, ray_t| {
+				if !self.objects[index].hit(r, ray_t, &mut temp_rec) {
+					return None;
+				}
+				temp_rec.object_id = index as i32;
+				*rec = temp_rec;
+				return Some(temp_rec.t);
+			});
+		}
+
+		stats::count(|counters| counters.intersection_tests += self.objects.len() as u64);
 		let mut hit_anything: bool = false;
 		let mut closest_so_far: f64 = ray_t.max;
 
-		for object in &self.objects {
+		for (index, object) in self.objects.iter().enumerate() {
 			if object.hit(r, Interval::new(ray_t.min, closest_so_far), &mut temp_rec) {
 				hit_anything = true;
 				closest_so_far = temp_rec.t;
-				*rec = temp_rec.clone();
+				temp_rec.object_id = index as i32;
+				*rec = temp_rec;
 			}
 		}
 
 		return hit_anything;
 	}
-}
\ No newline at end of file
+}
+
+//An axis aligned box made of six quads, with a and b as opposite corners.
+pub fn new_box(a: Point, b: Point, material: MaterialEnum) -> Bvh {
+	let min = Point::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z));
+	let max = Point::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z));
+	let dx = Vector::new(max.x - min.x, 0.0, 0.0);
+	let dy = Vector::new(0.0, max.y - min.y, 0.0);
+	let dz = Vector::new(0.0, 0.0, max.z - min.z);
+
+	//every side's normal points out of the box.
+	let sides: Vec<Box<dyn Hittable>> = vec![
+		Box::new(Quad::new(Point::new(min.x, min.y, max.z), dx, dy, material)),
+		Box::new(Quad::new(Point::new(max.x, min.y, max.z), dz * -1.0, dy, material)),
+		Box::new(Quad::new(Point::new(max.x, min.y, min.z), dx * -1.0, dy, material)),
+		Box::new(Quad::new(Point::new(min.x, min.y, min.z), dz, dy, material)),
+		Box::new(Quad::new(Point::new(min.x, max.y, max.z), dx, dz * -1.0, material)),
+		Box::new(Quad::new(Point::new(min.x, min.y, min.z), dx, dz, material)),
+	];
+	return Bvh::new(sides);
+}
@@ -0,0 +1,89 @@
+/* C interface to the raytracer, implemented in ffi.rs.
+ *
+ * Build the library with `cargo build --release`, which produces
+ * target/release/libraytracer.so (or .dylib/.dll) and libraytracer.a.
+ *
+ * Scenes and cameras are opaque handles owned by the caller, free them with
+ * rt_scene_free and rt_camera_free. Calls that can fail return an RtStatus.
+ * A handle must not be used from two threads at the same time, rendering
+ * itself uses as many threads as the camera is set up for.
+ */
+#ifndef RAYTRACER_H
+#define RAYTRACER_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef enum RtStatus {
+	RT_OK = 0,
+	RT_NULL_POINTER = 1,
+	RT_INVALID_ARGUMENT = 2,
+	RT_BUFFER_TOO_SMALL = 3,
+	/* the renderer failed internally, the handles involved should not be used again */
+	RT_INTERNAL = 4
+} RtStatus;
+
+typedef struct RtScene RtScene;
+typedef struct RtCamera RtCamera;
+
+/* a static, human readable description of status */
+const char *rt_status_string(RtStatus status);
+
+/* SCENE */
+
+RtScene *rt_scene_new(void);
+/* accepts NULL */
+void rt_scene_free(RtScene *scene);
+
+/* Materials are created on a scene and referred to by the index written to *material.
+ * colors are linear RGB and can't be negative, fuzz is between 0 and 1. */
+RtStatus rt_scene_add_lambertian(RtScene *scene, double r, double g, double b, uint32_t *material);
+RtStatus rt_scene_add_metal(RtScene *scene, double r, double g, double b, double fuzz, uint32_t *material);
+RtStatus rt_scene_add_dielectric(RtScene *scene, double refraction_index, uint32_t *material);
+/* emits light from the front face, the scene is lit by it with light sampling */
+RtStatus rt_scene_add_diffuse_light(RtScene *scene, double r, double g, double b, uint32_t *material);
+
+RtStatus rt_scene_add_sphere(RtScene *scene, double x, double y, double z, double radius, uint32_t material);
+/* positions holds vertex_count xyz triples, indices holds index_count / 3 triangles.
+ * counter clockwise triangles face the viewer. Nothing is added if an index is out of range
+ * or vertex_count is too big to address. */
+RtStatus rt_scene_add_mesh(RtScene *scene, const double *positions, size_t vertex_count,
+                           const uint32_t *indices, size_t index_count, uint32_t material);
+
+/* CAMERA */
+
+/* NULL if width or height is not positive. Starts at the origin looking down -z
+ * with a 90 degree field of view, 16 samples per pixel and a sky background. */
+RtCamera *rt_camera_new(int32_t width, int32_t height);
+/* accepts NULL */
+void rt_camera_free(RtCamera *camera);
+
+/* vfov is the vertical field of view in degrees */
+RtStatus rt_camera_look_at(RtCamera *camera,
+                           double from_x, double from_y, double from_z,
+                           double at_x, double at_y, double at_z,
+                           double up_x, double up_y, double up_z,
+                           double vfov);
+RtStatus rt_camera_set_samples(RtCamera *camera, int32_t samples_per_pixel, int32_t max_depth);
+/* the same seed always renders the same image */
+RtStatus rt_camera_set_seed(RtCamera *camera, uint64_t seed);
+/* 0 uses one thread per core */
+RtStatus rt_camera_set_threads(RtCamera *camera, uint32_t threads);
+/* rays that miss everything see this color instead of the sky */
+RtStatus rt_camera_set_background(RtCamera *camera, double r, double g, double b);
+
+/* RENDER */
+
+/* Renders scene into buffer as linear RGB floats, row by row from the top left.
+ * buffer_length is the number of floats in buffer, at least width * height * 3. */
+RtStatus rt_render(const RtCamera *camera, const RtScene *scene, float *buffer, size_t buffer_length);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
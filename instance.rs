@@ -0,0 +1,159 @@
+//Wrappers that place a shared object somewhere else in the scene.
+//The ray gets moved into the object's own space instead of moving the object,
+//so any number of instances can point at one copy of the geometry.
+use crate::Point;
+use crate::Ray;
+use crate::Vector;
+use crate::Interval;
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable, Span, SurfaceSample};
+use crate::sampler::Sampler;
+use crate::degrees_to_radians;
+
+use std::sync::Arc;
+
+pub struct Translate {
+	object: Arc<dyn Hittable>,
+	offset: Vector,
+	bbox: Aabb,
+}
+
+impl Translate {
+	pub fn new(object: Arc<dyn Hittable>, offset: Vector) -> Translate {
+		let inner = object.bounding_box();
+		let bbox = Aabb::new(
+			Interval::new(inner.x.min + offset.x, inner.x.max + offset.x),
+			Interval::new(inner.y.min + offset.y, inner.y.max + offset.y),
+			Interval::new(inner.z.min + offset.z, inner.z.max + offset.z),
+		);
+		Translate {
+			object,
+			offset,
+			bbox,
+		}
+	}
+}
+
+impl Hittable for Translate {
+	fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+		let offset_r = Ray::new(r.origin - self.offset, r.dir);
+		if !self.object.hit(&offset_r, ray_t, rec) {
+			return false;
+		}
+		rec.hit_point += self.offset;
+		return true;
+	}
+
+	fn spans(&self, r: &Ray, spans: &mut Vec<Span>) {
+		let offset_r = Ray::new(r.origin - self.offset, r.dir);
+		let first = spans.len();
+		self.object.spans(&offset_r, spans);
+		for span in &mut spans[first..] {
+			span.enter.hit_point += self.offset;
+			span.exit.hit_point += self.offset;
+		}
+	}
+
+	fn bounding_box(&self) -> Aabb {
+		return self.bbox;
+	}
+
+	//moving the object doesn't change the solid angle it covers, so the pdfs stay as they are.
+	fn is_emissive(&self) -> bool {
+		return self.object.is_emissive();
+	}
+	fn sample_surface(&self, origin: &Point, sampler: &mut Sampler) -> Option<SurfaceSample> {
+		let mut sample = self.object.sample_surface(&(*origin - self.offset), sampler)?;
+		sample.point += self.offset;
+		return Some(sample);
+	}
+	fn surface_pdf(&self, origin: &Point, direction: &Vector) -> f64 {
+		return self.object.surface_pdf(&(*origin - self.offset), direction);
+	}
+}
+
+//Turns the object around the y axis, positive angles go counter clockwise seen from above.
+pub struct RotateY {
+	object: Arc<dyn Hittable>,
+	sin_theta: f64,
+	cos_theta: f64,
+	bbox: Aabb,
+}
+
+impl RotateY {
+	//angle is in degrees.
+	pub fn new(object: Arc<dyn Hittable>, angle: f64) -> RotateY {
+		let radians = degrees_to_radians(angle);
+		let mut rotate = RotateY {
+			object,
+			sin_theta: radians.sin(),
+			cos_theta: radians.cos(),
+			bbox: Aabb::empty(),
+		};
+
+		//the box around the rotated corners of the object's own box.
+		let mut bbox = Aabb::empty();
+		for corner in rotate.object.bounding_box().corners() {
+			let rotated: Point = rotate.to_world(&corner);
+			bbox = Aabb::enclosing(&bbox, &Aabb::from_points(&rotated, &rotated));
+		}
+		rotate.bbox = bbox;
+		return rotate;
+	}
+
+	fn to_world(&self, v: &Vector) -> Vector {
+		return Vector::new(
+			self.cos_theta * v.x + self.sin_theta * v.z,
+			v.y,
+			-self.sin_theta * v.x + self.cos_theta * v.z,
+		);
+	}
+	fn to_object(&self, v: &Vector) -> Vector {
+		return Vector::new(
+			self.cos_theta * v.x - self.sin_theta * v.z,
+			v.y,
+			self.sin_theta * v.x + self.cos_theta * v.z,
+		);
+	}
+}
+
+impl Hittable for RotateY {
+	fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+		let rotated_r = Ray::new(self.to_object(&r.origin), self.to_object(&r.dir));
+		if !self.object.hit(&rotated_r, ray_t, rec) {
+			return false;
+		}
+		rec.hit_point = self.to_world(&rec.hit_point);
+		rec.normal = self.to_world(&rec.normal);
+		return true;
+	}
+
+	fn spans(&self, r: &Ray, spans: &mut Vec<Span>) {
+		let rotated_r = Ray::new(self.to_object(&r.origin), self.to_object(&r.dir));
+		let first = spans.len();
+		self.object.spans(&rotated_r, spans);
+		for span in &mut spans[first..] {
+			for rec in [&mut span.enter, &mut span.exit] {
+				rec.hit_point = self.to_world(&rec.hit_point);
+				rec.normal = self.to_world(&rec.normal);
+			}
+		}
+	}
+
+	fn bounding_box(&self) -> Aabb {
+		return self.bbox;
+	}
+
+	fn is_emissive(&self) -> bool {
+		return self.object.is_emissive();
+	}
+	fn sample_surface(&self, origin: &Point, sampler: &mut Sampler) -> Option<SurfaceSample> {
+		let mut sample = self.object.sample_surface(&self.to_object(origin), sampler)?;
+		sample.point = self.to_world(&sample.point);
+		sample.normal = self.to_world(&sample.normal);
+		return Some(sample);
+	}
+	fn surface_pdf(&self, origin: &Point, direction: &Vector) -> f64 {
+		return self.object.surface_pdf(&self.to_object(origin), &self.to_object(direction));
+	}
+}
@@ -1,3 +1,4 @@
+#[derive(Debug, Clone, Copy)]
 pub struct Interval {
 	pub min: f64,
 	pub max: f64,
@@ -28,4 +29,22 @@ impl Interval {
 		}
 		return x;
 	}
-}
\ No newline at end of file
+	//the smallest interval holding both a and b.
+	pub fn enclosing(a: &Interval, b: &Interval) -> Interval {
+		return Interval::new(a.min.min(b.min), a.max.max(b.max));
+	}
+	//grows the interval by delta in total, half on each side.
+	pub fn expand(&self, delta: f64) -> Interval {
+		let padding = delta / 2.0;
+		return Interval::new(self.min - padding, self.max + padding);
+	}
+}
+//contains nothing, min is above max.
+pub static EMPTY: Interval = Interval{
+	min: f64::INFINITY,
+	max: f64::NEG_INFINITY,
+};
+pub static UNIVERSE: Interval = Interval{
+	min: f64::NEG_INFINITY,
+	max: f64::INFINITY,
+};
@@ -0,0 +1,73 @@
+//#![allow(unused_imports)]
+#![allow(clippy::needless_return)]
+//file sec/lib.rs
+//The renderer as a library. The types most programs need are re-exported here,
+//the modules hold the rest.
+pub mod vector;
+pub use vector::Vector;
+
+pub mod ray;
+pub use ray::Ray;
+
+pub mod color;
+
+pub mod hittable;
+pub use hittable::{HitRecord, Hittable, HittableList, Quad, Sphere};
+pub mod shapes;
+pub use shapes::{Capsule, Cone, Cylinder, Torus};
+
+pub mod interval;
+pub use interval::Interval;
+
+pub mod aabb;
+pub mod bvh;
+pub use bvh::Bvh;
+pub mod instance;
+pub mod medium;
+pub mod csg;
+pub use csg::Csg;
+pub mod sdf;
+pub use sdf::{Sdf, SdfShape};
+
+pub mod camera;
+pub use camera::{AdaptiveSampling, Background, Camera, DepthLimits, PathRegularization, RussianRoulette};
+
+pub mod material;
+pub use material::{Material, MaterialEnum};
+
+pub mod texture;
+pub use texture::Texture;
+pub mod noise;
+
+pub mod light;
+pub use light::LightEnum;
+
+pub mod onb;
+pub mod sampling;
+
+pub mod rng;
+pub mod sampler;
+pub use sampler::SamplerKind;
+
+pub mod framebuffer;
+pub use framebuffer::Framebuffer;
+pub mod filter;
+pub use filter::Filter;
+mod checkpoint;
+pub mod aov;
+pub use aov::Aov;
+pub mod stats;
+pub use stats::RenderStats;
+pub mod denoiser;
+pub use denoiser::Denoiser;
+pub mod exr;
+pub mod ffi;
+
+pub mod scene;
+pub use scene::Scene;
+
+pub mod utility;
+pub use utility::*;
+
+pub type Color = Vector;
+pub type Point = Vector;
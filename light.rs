@@ -0,0 +1,185 @@
+use crate::Color;
+use crate::Point;
+use crate::Vector;
+
+use crate::degrees_to_radians;
+use crate::sampler::Sampler;
+use crate::onb::Onb;
+use crate::sampling;
+
+//What a light delivers to a point.
+//direction is normalized and points from the shaded point towards the light,
+//distance is how far a shadow ray has to go, infinite for the sun.
+//li is the incoming radiance already divided by the sampling pdf.
+#[derive(Debug, Clone, Copy)]
+pub struct LightSample {
+	pub direction: Vector,
+	pub distance: f64,
+	pub li: Color,
+}
+
+//Lights that have no geometry, so rays can never hit them and they are only reached by shadow rays.
+trait Light {
+	fn sample_li(&self, point: &Point, sampler: &mut Sampler) -> Option<LightSample>;
+}
+
+//This redirects the sample_li call to the right light, same as MaterialEnum.
+#[derive(Debug, Clone, Copy)]
+pub enum LightEnum {
+	Point(PointLight),
+	Spot(SpotLight),
+	Directional(DirectionalLight),
+}
+
+impl LightEnum {
+	pub fn sample_li(&self, point: &Point, sampler: &mut Sampler) -> Option<LightSample> {
+		match self {
+			LightEnum::Point(light) => light.sample_li(point, sampler),
+			LightEnum::Spot(light) => light.sample_li(point, sampler),
+			LightEnum::Directional(light) => light.sample_li(point, sampler),
+		}
+	}
+
+	pub fn new_point(position: Point, intensity: Color) -> LightEnum {
+		LightEnum::Point(PointLight::new(position, intensity))
+	}
+	//angles are in degrees and measured from the axis of the cone.
+	pub fn new_spot(position: Point, target: Point, intensity: Color, cone_angle: f64, falloff_start: f64) -> LightEnum {
+		LightEnum::Spot(SpotLight::new(position, target, intensity, cone_angle, falloff_start))
+	}
+	//direction is the way the light travels, angular_diameter is in degrees and 0 gives hard shadows.
+	pub fn new_directional(direction: Vector, irradiance: Color, angular_diameter: f64) -> LightEnum {
+		LightEnum::Directional(DirectionalLight::new(direction, irradiance, angular_diameter))
+	}
+}
+
+//POINT LIGHT
+
+#[derive(Debug, Clone, Copy)]
+pub struct PointLight {
+	position: Point,
+	intensity: Color,
+}
+
+impl PointLight {
+	pub fn new(position: Point, intensity: Color) -> PointLight {
+		PointLight {
+			position,
+			intensity,
+		}
+	}
+}
+
+impl Light for PointLight {
+	//falls off with the inverse square of the distance.
+	fn sample_li(&self, point: &Point, _sampler: &mut Sampler) -> Option<LightSample> {
+		let to_light: Vector = self.position - *point;
+		let distance_squared = to_light.length_squared();
+		if distance_squared == 0.0 {
+			return None;
+		}
+		let distance = distance_squared.sqrt();
+		return Some(LightSample {
+			direction: to_light / distance,
+			distance,
+			li: self.intensity / distance_squared,
+		});
+	}
+}
+
+//SPOT LIGHT
+
+#[derive(Debug, Clone, Copy)]
+pub struct SpotLight {
+	position: Point,
+	axis: Vector,
+	intensity: Color,
+	cos_total_width: f64,
+	cos_falloff_start: f64,
+}
+
+impl SpotLight {
+	pub fn new(position: Point, target: Point, intensity: Color, cone_angle: f64, falloff_start: f64) -> SpotLight {
+		//the soft edge can't start outside of the cone.
+		let falloff_start = falloff_start.min(cone_angle);
+		SpotLight {
+			position,
+			axis: (target - position).normalize(),
+			intensity,
+			cos_total_width: f64::cos(degrees_to_radians(cone_angle)),
+			cos_falloff_start: f64::cos(degrees_to_radians(falloff_start)),
+		}
+	}
+
+	//1 inside the inner cone, 0 outside the outer one and a smoothstep in between.
+	fn falloff(&self, cos_theta: f64) -> f64 {
+		if cos_theta >= self.cos_falloff_start {
+			return 1.0;
+		}
+		if cos_theta <= self.cos_total_width {
+			return 0.0;
+		}
+		let t = (cos_theta - self.cos_total_width) / (self.cos_falloff_start - self.cos_total_width);
+		return t * t * (3.0 - 2.0 * t);
+	}
+}
+
+impl Light for SpotLight {
+	fn sample_li(&self, point: &Point, _sampler: &mut Sampler) -> Option<LightSample> {
+		let to_light: Vector = self.position - *point;
+		let distance_squared = to_light.length_squared();
+		if distance_squared == 0.0 {
+			return None;
+		}
+		let distance = distance_squared.sqrt();
+		let direction: Vector = to_light / distance;
+
+		let falloff = self.falloff(Vector::dot(&(direction * -1.0), &self.axis));
+		if falloff == 0.0 {
+			return None;
+		}
+		return Some(LightSample {
+			direction,
+			distance,
+			li: self.intensity * (falloff / distance_squared),
+		});
+	}
+}
+
+//DIRECTIONAL LIGHT
+
+#[derive(Debug, Clone, Copy)]
+pub struct DirectionalLight {
+	//points from the scene towards the sun.
+	to_light: Vector,
+	irradiance: Color,
+	cos_max: f64,
+}
+
+impl DirectionalLight {
+	pub fn new(direction: Vector, irradiance: Color, angular_diameter: f64) -> DirectionalLight {
+		let half_angle = degrees_to_radians(angular_diameter.clamp(0.0, 180.0)) / 2.0;
+		DirectionalLight {
+			to_light: direction.normalize() * -1.0,
+			irradiance,
+			cos_max: f64::cos(half_angle),
+		}
+	}
+}
+
+impl Light for DirectionalLight {
+	//a sun with a size picks a direction uniformly inside its disc,
+	//the radiance and the pdf are both constant so only the irradiance is left.
+	fn sample_li(&self, _point: &Point, sampler: &mut Sampler) -> Option<LightSample> {
+		let mut direction: Vector = self.to_light;
+		if self.cos_max < 1.0 {
+			let uvw = Onb::new(&self.to_light);
+			direction = uvw.local(&sampling::uniform_cone(sampler.get_2d(), self.cos_max)).normalize();
+		}
+		return Some(LightSample {
+			direction,
+			distance: f64::INFINITY,
+			li: self.irradiance,
+		});
+	}
+}
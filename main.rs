@@ -1,103 +1,36 @@
-//#![allow(unused_imports)]
-//#![allow(dead_code)]
+#![allow(clippy::needless_return)]
 //file sec/main.rs
-mod vector;
-use vector::Vector;
-
-mod ray;
-use ray::Ray;
-
-mod color;
-use color::*;
-
-mod hittable;
-use crate::hittable::*;
-
-mod interval;
-use interval::Interval;
-
-mod camera;
-use camera::*;
+use raytracer::RussianRoulette;
+use raytracer::scene::{load_scene, SCENE_NAMES};
 
 use std::fs::File;
-use std::io::prelude::*;
-
-mod material;
-use material::*;
-
-use rand::Rng;
-pub type Color = Vector;
-pub type Point = Vector;
-
-
-//UTILITY
-
-static INFINITY: f64 = f64::INFINITY;
-static PI: f64 = 3.1415926535897932385;
-
-
-static EMPTY: Interval = Interval{
-	min: f64::INFINITY,
-	max: f64::INFINITY,
-};
-static UNIVERSE: Interval = Interval{
-	min: f64::INFINITY,
-	max: f64::INFINITY,
-};
-
-
-pub fn degrees_to_radians(degrees: f64) -> f64 {
-	return (degrees * PI) / 180.0;
-}
+use std::process::ExitCode;
 
-pub fn random_f64() -> f64 {
-	return rand::random_range(0.0..1.0);
-}
-pub fn random_f64_in_range(min: f64, max: f64) -> f64 {
-	return rand::random_range(min..max);
-}
 
 //MAIN
-fn main() -> std::io::Result<()> {
-
-	//Image
-	let aspect_ratio: f64 = 16.0 / 9.0;
-
-	let image_width: i32 = 300;
-
-	let samples_per_pixel: i32 = 50;
-
+//raytracer [scene] [stats file], renders one of the built in scenes to img.ppm, three_spheres if none is given.
+//the render statistics also get written to the stats file as JSON when there is one.
+fn main() -> ExitCode {
+	let name = std::env::args().nth(1).unwrap_or(String::from("three_spheres"));
+	let Some(mut scene) = load_scene(&name) else {
+		eprintln!("unknown scene {name}, the scenes are: {}", SCENE_NAMES.join(", "));
+		return ExitCode::FAILURE;
+	};
+	scene.camera.stats_path = std::env::args().nth(2);
+	scene.camera.progress = true;
+	//lets deep paths end early for a little extra noise.
+	scene.camera.russian_roulette = Some(RussianRoulette { min_depth: 3 });
 
 	// Initialization
-	let mut file = File::create("img.ppm")?;
-	//Render
-	//Camera
-	let camera = Camera::new(aspect_ratio, image_width, samples_per_pixel);
-
-	
+	let mut file = match File::create("img.ppm") {
+		Ok(file) => file,
+		Err(error) => {
+			eprintln!("can't create img.ppm: {error}");
+			return ExitCode::FAILURE;
+		}
+	};
 
-	
-	//let _ = file.write_all(b"P3\n256 256\n255\n");
-	//let _ = file.write_all(b"P3\n711 400\n255\n");
-	//let _ = file.write_all(format!("P3\n{0} {1}\n255\n", image_width, image_height).as_bytes());
-	//pixel_delta_u.print();
-	//pixel_delta_v.print();
-
-
-	let mut world: HittableList = HittableList::new();
-
-	world.add(0.0, 0.0, -1.2, 0.5, MaterialEnum::new_lambertian(0.1, 0.2, 0.5) );
-	//right
-	world.add(1.0, 0.0, -1.0, 0.5, MaterialEnum::new_metal(0.6, 0.2, 0.8, 1.0) );
-	//left
-	world.add(-1.0, 0.0, -1.0, 0.5, MaterialEnum::new_dielectric(1.50) );
-	world.add(-1.0, 0.0, -1.0, 0.4, MaterialEnum::new_dielectric(1.00/1.50) );
-	world.add(0.0, -100.5, -1.0, 100.0, MaterialEnum::new_lambertian(0.8, 0.8, 0.0));
-
-	camera.render(&world, &mut file);
-
-	//let test_vec = Vector::new(1.0, 1.0, 0.0);
-	//test_vec.print();
-	//println!("{}", test_vec.length_squared());
-	Ok(())
-}
\ No newline at end of file
+	//Render
+	scene.camera.render(&scene.world, &mut file);
+	return ExitCode::SUCCESS;
+}
@@ -2,80 +2,238 @@ use crate::Color;
 use crate::Vector;
 use crate::Ray;
 use crate::HitRecord;
+use crate::PI;
 
-use crate::random_f64_in_range;
+use crate::sampler::Sampler;
+use crate::rng::hash;
+use crate::onb::Onb;
+use crate::sampling;
+use crate::texture::Texture;
 
-//This redirects the scatter call to wherever its supposed to go.
+use std::ops::BitOr;
+
+//Describes what kind of lobe a scatter sample was drawn from.
+//Flags can be combined, e.g. LobeFlags::SPECULAR | LobeFlags::TRANSMISSION.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct LobeFlags(u8);
+
+impl LobeFlags {
+	pub const NONE: LobeFlags = LobeFlags(0);
+	pub const DIFFUSE: LobeFlags = LobeFlags(1);
+	pub const GLOSSY: LobeFlags = LobeFlags(1 << 1);
+	//a delta lobe, it can only be sampled and never evaluated.
+	pub const SPECULAR: LobeFlags = LobeFlags(1 << 2);
+	pub const REFLECTION: LobeFlags = LobeFlags(1 << 3);
+	pub const TRANSMISSION: LobeFlags = LobeFlags(1 << 4);
+	//scattering inside a medium, there's no surface and so no cosine term.
+	pub const VOLUME: LobeFlags = LobeFlags(1 << 5);
+
+	pub fn contains(&self, other: LobeFlags) -> bool {
+		return (self.0 & other.0) == other.0;
+	}
+	pub fn is_delta(&self) -> bool {
+		return self.contains(LobeFlags::SPECULAR);
+	}
+}
+
+impl BitOr for LobeFlags {
+	type Output = Self;
+	fn bitor(self, rhs: Self) -> Self::Output {
+		LobeFlags(self.0 | rhs.0)
+	}
+}
+
+//The result of sampling a material.
+//direction is normalized and points away from the hit point.
+//weight is eval * cos / pdf, so the caller only has to multiply by it.
+//for delta lobes pdf is the probability of picking that lobe instead of a density.
+#[derive(Debug, Clone, Copy)]
+pub struct ScatterSample {
+	pub direction: Vector,
+	pub weight: Color,
+	pub pdf: f64,
+	pub lobe: LobeFlags,
+}
+
+//wo points back towards where the ray came from, wi is the scattered direction.
+//both are normalized. eval returns the bsdf value without the cosine term,
+//and pdf is the solid angle density that sample would have produced wi with.
+pub trait Material {
+	fn sample(&self, ray_in: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<ScatterSample>;
+	fn eval(&self, wo: &Vector, wi: &Vector, rec: &HitRecord) -> Color;
+	fn pdf(&self, wo: &Vector, wi: &Vector, rec: &HitRecord) -> f64;
+	//radiance given off by the surface itself, most materials don't emit anything.
+	fn emitted(&self, _rec: &HitRecord) -> Color {
+		return Color::null_vector();
+	}
+}
+
+//This redirects the material calls to wherever they're supposed to go.
 #[derive(Clone, Copy)]
 pub enum MaterialEnum {
 	Lambertian(LambertianMaterial),
 	Metal(MetalMaterial),
 	Dielectric(DielectricMaterial),
+	DiffuseLight(DiffuseLightMaterial),
+	Isotropic(IsotropicMaterial),
 }
 
-impl MaterialEnum {
-	pub fn scatter(&self, ray_in: &Ray, rec: &HitRecord, attenuation: &mut Color, scattered: &mut Ray) -> bool {
+impl Material for MaterialEnum {
+	fn sample(&self, ray_in: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<ScatterSample> {
 		match self {
-			MaterialEnum::Lambertian(material) => {
-				//println!("noo");
-				material.scatter(ray_in, rec, attenuation, scattered)
-			}
-			MaterialEnum::Metal(material) => {
-				//println!("woo");
-				material.scatter(ray_in, rec, attenuation, scattered)
-			}
-			MaterialEnum::Dielectric(material) => {
-				material.scatter(ray_in, rec, attenuation, scattered)
-			}
+			MaterialEnum::Lambertian(material) => material.sample(ray_in, rec, sampler),
+			MaterialEnum::Metal(material) => material.sample(ray_in, rec, sampler),
+			MaterialEnum::Dielectric(material) => material.sample(ray_in, rec, sampler),
+			MaterialEnum::DiffuseLight(material) => material.sample(ray_in, rec, sampler),
+			MaterialEnum::Isotropic(material) => material.sample(ray_in, rec, sampler),
+		}
+	}
+	fn eval(&self, wo: &Vector, wi: &Vector, rec: &HitRecord) -> Color {
+		match self {
+			MaterialEnum::Lambertian(material) => material.eval(wo, wi, rec),
+			MaterialEnum::Metal(material) => material.eval(wo, wi, rec),
+			MaterialEnum::Dielectric(material) => material.eval(wo, wi, rec),
+			MaterialEnum::DiffuseLight(material) => material.eval(wo, wi, rec),
+			MaterialEnum::Isotropic(material) => material.eval(wo, wi, rec),
 		}
 	}
+	fn pdf(&self, wo: &Vector, wi: &Vector, rec: &HitRecord) -> f64 {
+		match self {
+			MaterialEnum::Lambertian(material) => material.pdf(wo, wi, rec),
+			MaterialEnum::Metal(material) => material.pdf(wo, wi, rec),
+			MaterialEnum::Dielectric(material) => material.pdf(wo, wi, rec),
+			MaterialEnum::DiffuseLight(material) => material.pdf(wo, wi, rec),
+			MaterialEnum::Isotropic(material) => material.pdf(wo, wi, rec),
+		}
+	}
+	fn emitted(&self, rec: &HitRecord) -> Color {
+		match self {
+			MaterialEnum::DiffuseLight(material) => material.emitted(rec),
+			_ => Color::null_vector(),
+		}
+	}
+}
 
+impl MaterialEnum {
 	pub fn new_metal(i: f64, j: f64, k: f64, fuzz: f64) -> MaterialEnum {
 		MaterialEnum::Metal(MetalMaterial::new(Vector::new(i, j, k), fuzz))
 	}
 	pub fn new_lambertian(i: f64, j: f64, k: f64) -> MaterialEnum {
-		MaterialEnum::Lambertian(LambertianMaterial::new(Vector::new(i, j, k)))
+		MaterialEnum::Lambertian(LambertianMaterial::new(Texture::Solid(Vector::new(i, j, k))))
+	}
+	pub fn new_textured(texture: Texture) -> MaterialEnum {
+		MaterialEnum::Lambertian(LambertianMaterial::new(texture))
 	}
 	pub fn new_dielectric(index: f64) -> MaterialEnum {
 		MaterialEnum::Dielectric(DielectricMaterial::new(index))
 	}
-}
+	pub fn new_diffuse_light(i: f64, j: f64, k: f64) -> MaterialEnum {
+		MaterialEnum::DiffuseLight(DiffuseLightMaterial::new(Vector::new(i, j, k)))
+	}
 
-impl Default for MaterialEnum {
-	fn default() -> Self { MaterialEnum::Lambertian(LambertianMaterial::new(Color::unit_vector() * 0.5)) }
+	//the phase function of a medium, light scatters equally in every direction.
+	pub fn new_isotropic(i: f64, j: f64, k: f64) -> MaterialEnum {
+		MaterialEnum::Isotropic(IsotropicMaterial::new(Vector::new(i, j, k)))
+	}
+
+	pub fn is_emissive(&self) -> bool {
+		return matches!(self, MaterialEnum::DiffuseLight(_));
+	}
+	//volumes have no surface, light reaches them from every side without a cosine.
+	pub fn is_volumetric(&self) -> bool {
+		return matches!(self, MaterialEnum::Isotropic(_));
+	}
+
+	//glass lets light through, so it's lit from both sides.
+	pub fn is_transmissive(&self) -> bool {
+		return matches!(self, MaterialEnum::Dielectric(_));
+	}
+
+	//The material with its mirror and glass directions spread over a cone whose half angle has
+	//cosine cos_max, for path regularization. None for materials without a delta lobe.
+	pub fn regularized(&self, cos_max: f64) -> Option<MaterialEnum> {
+		match self {
+			MaterialEnum::Metal(material) => {
+				//fuzz blurs the reflection already, the cone shouldn't make it sharper.
+				let fuzz = material.fuzz.min(1.0);
+				let spread_cos = cos_max.min(f64::sqrt(1.0 - fuzz * fuzz));
+				return Some(MaterialEnum::Metal(MetalMaterial { spread_cos, ..*material }));
+			}
+			MaterialEnum::Dielectric(material) => {
+				return Some(MaterialEnum::Dielectric(DielectricMaterial { spread_cos: cos_max, ..*material }));
+			}
+			_ => return None,
+		}
+	}
+
+	//the base color of the surface where rec hit it, used for the albedo pass.
+	pub fn albedo(&self, rec: &HitRecord) -> Color {
+		match self {
+			MaterialEnum::Lambertian(material) => material.albedo(rec),
+			MaterialEnum::Metal(material) => material.albedo,
+			MaterialEnum::Dielectric(_) => Color::new(1.0, 1.0, 1.0),
+			MaterialEnum::DiffuseLight(_) => Color::null_vector(),
+			MaterialEnum::Isotropic(material) => material.albedo,
+		}
+	}
+
+	//Materials are plain values, so two with the same type and parameters get the same id.
+	pub fn id(&self) -> u32 {
+		let values: Vec<u64> = match self {
+			MaterialEnum::Lambertian(material) => [vec![0], material.texture.hash_values()].concat(),
+			MaterialEnum::Metal(material) => vec![1, material.albedo.x.to_bits(), material.albedo.y.to_bits(), material.albedo.z.to_bits(), material.fuzz.to_bits()],
+			MaterialEnum::Dielectric(material) => vec![2, material.refraction_index.to_bits()],
+			MaterialEnum::DiffuseLight(material) => vec![3, material.emit.x.to_bits(), material.emit.y.to_bits(), material.emit.z.to_bits()],
+			MaterialEnum::Isotropic(material) => vec![4, material.albedo.x.to_bits(), material.albedo.y.to_bits(), material.albedo.z.to_bits()],
+		};
+		return hash(&values) as u32;
+	}
 }
 
-//This only exists to make sure that the function's parameters are adhered to.
-trait Material {
-	fn scatter(&self, ray_in: &Ray, rec: &HitRecord, attenuation: &mut Color, scattered: &mut Ray) -> bool;
+impl Default for MaterialEnum {
+	fn default() -> Self { MaterialEnum::Lambertian(LambertianMaterial::new(Texture::Solid(Color::unit_vector() * 0.5))) }
 }
 
 //LAMBERTIAN MATERIAL
 
 #[derive(Clone, Copy)]
 pub struct LambertianMaterial {
-	albedo: Color,
+	texture: Texture,
 }
 
 impl LambertianMaterial {
-	pub fn new(albedo: Color) -> LambertianMaterial {
+	pub fn new(texture: Texture) -> LambertianMaterial {
 		LambertianMaterial {
-			albedo,
+			texture,
 		}
 	}
+	fn albedo(&self, rec: &HitRecord) -> Color {
+		return self.texture.value(rec.u, rec.v, &rec.hit_point);
+	}
 }
 
 impl Material for LambertianMaterial {
-	fn scatter(&self, ray_in: &Ray, rec: &HitRecord, attenuation: &mut Color, scattered: &mut Ray) -> bool {
-		let mut scatter_direction:Vector = rec.normal + Color::random_normal_vector();
-		if scatter_direction.near_zero() {
-			scatter_direction = rec.normal;
-		}
+	fn sample(&self, _ray_in: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<ScatterSample> {
+		let uvw = Onb::new(&rec.normal);
+		let local: Vector = sampling::cosine_hemisphere(sampler.get_2d());
+		let direction: Vector = uvw.local(&local).normalize();
 
-		*scattered = Ray::new(rec.hit_point, scatter_direction);
-		*attenuation = self.albedo;
-		//println!("boop");
-		return true;
+		//the cosine and the 1/pi cancel against the pdf, which leaves just the albedo.
+		return Some(ScatterSample {
+			direction,
+			weight: self.albedo(rec),
+			pdf: sampling::cosine_hemisphere_pdf(local.z),
+			lobe: LobeFlags::DIFFUSE | LobeFlags::REFLECTION,
+		});
+	}
+	fn eval(&self, _wo: &Vector, wi: &Vector, rec: &HitRecord) -> Color {
+		if Vector::dot(wi, &rec.normal) <= 0.0 {
+			return Color::null_vector();
+		}
+		return self.albedo(rec) / PI;
+	}
+	fn pdf(&self, _wo: &Vector, wi: &Vector, rec: &HitRecord) -> f64 {
+		return sampling::cosine_hemisphere_pdf(Vector::dot(wi, &rec.normal));
 	}
 }
 
@@ -85,6 +243,8 @@ impl Material for LambertianMaterial {
 pub struct MetalMaterial {
 	albedo: Color,
 	fuzz: f64,
+	//below 1 the reflection is spread evenly over a cone with this cosine, see MaterialEnum::regularized.
+	spread_cos: f64,
 }
 
 impl MetalMaterial {
@@ -92,62 +252,231 @@ impl MetalMaterial {
 		MetalMaterial {
 			albedo,
 			fuzz,
+			spread_cos: 1.0,
 		}
 	}
 }
 
+//the fuzzed reflection has no closed form pdf, so the whole lobe is treated as a delta.
+//a regularized one is a cone around the mirror direction instead, which can be evaluated.
 impl Material for MetalMaterial {
-	fn scatter(&self, ray_in: &Ray, rec: &HitRecord, attenuation: &mut Color, scattered: &mut Ray) -> bool {
-		let reflected: Vector = Vector::reflect(&ray_in.dir, &rec.normal) + (self.fuzz * Vector::random_normal_vector());
-		*scattered = Ray::new(rec.hit_point, reflected);
-		*attenuation = self.albedo;
-		// println!("boop");
-		return true;
+	fn sample(&self, ray_in: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<ScatterSample> {
+		if self.spread_cos < 1.0 {
+			let mirror = Vector::reflect(&ray_in.dir.normalize(), &rec.normal);
+			let direction = sample_cone(&mirror, self.spread_cos, sampler);
+			if Vector::dot(&direction, &rec.normal) <= 0.0 {
+				return None;
+			}
+			return Some(ScatterSample {
+				direction,
+				weight: self.albedo,
+				pdf: sampling::uniform_cone_pdf(self.spread_cos),
+				lobe: LobeFlags::GLOSSY | LobeFlags::REFLECTION,
+			});
+		}
+
+		let reflected: Vector = Vector::reflect(&ray_in.dir.normalize(), &rec.normal) + (self.fuzz * Vector::random_normal_vector(sampler));
+		//fuzz can push the reflection under the surface, the light would have to go through the metal
+		//so it's absorbed instead. This is where rough metal loses energy at grazing angles.
+		if Vector::dot(&reflected, &rec.normal) <= 0.0 {
+			return None;
+		}
+		return Some(ScatterSample {
+			direction: reflected.normalize(),
+			weight: self.albedo,
+			pdf: 1.0,
+			lobe: LobeFlags::SPECULAR | LobeFlags::REFLECTION,
+		});
+	}
+	fn eval(&self, wo: &Vector, wi: &Vector, rec: &HitRecord) -> Color {
+		let cos_theta = Vector::dot(wi, &rec.normal);
+		if cos_theta <= 0.0 {
+			return Color::null_vector();
+		}
+		return self.albedo * (self.pdf(wo, wi, rec) / cos_theta);
+	}
+	fn pdf(&self, wo: &Vector, wi: &Vector, rec: &HitRecord) -> f64 {
+		if self.spread_cos >= 1.0 {
+			return 0.0;
+		}
+		let mirror = Vector::reflect(&(*wo * -1.0), &rec.normal);
+		return cone_pdf(wi, &mirror, self.spread_cos);
 	}
 }
 
+//DIELECTRIC MATERIAL
+
 #[derive(Clone, Copy)]
 pub struct DielectricMaterial {
 	refraction_index: f64,
+	//below 1 both directions are spread evenly over a cone with this cosine, see MaterialEnum::regularized.
+	spread_cos: f64,
 }
 
 impl DielectricMaterial {
 	pub fn new(index: f64) -> DielectricMaterial {
 		DielectricMaterial {
 			refraction_index: index,
+			spread_cos: 1.0,
 		}
 	}
 
+	//The mirror and refracted directions for light leaving towards -unit_direction,
+	//and how likely the mirror one is. There's no refracted direction under total internal reflection.
+	fn directions(&self, unit_direction: &Vector, rec: &HitRecord) -> (Vector, Option<Vector>, f64) {
+		let mut ri: f64 = self.refraction_index;
+		if rec.front_face {
+			ri = 1.0 / self.refraction_index;
+		}
+
+		let cos_theta = Vector::dot(&(*unit_direction * (-1.0)), &rec.normal).min(1.0);
+		let sin_theta = f64::sqrt(1.0 - cos_theta * cos_theta);
+		let reflected = Vector::reflect(unit_direction, &rec.normal);
+
+		//total internal reflection always reflects, otherwise fresnel picks the lobe.
+		if ri * sin_theta > 1.0 {
+			return (reflected, None, 1.0);
+		}
+		let refracted = Vector::refract(unit_direction, &rec.normal, ri).normalize();
+		return (reflected, Some(refracted), Self::reflectance(cos_theta, ri));
+	}
+
 	fn reflectance(cosine: f64, refraction_index: f64) -> f64 {
 		let mut r0 = (1.0 - refraction_index) / (1.0 + refraction_index);
 		r0 = r0 * r0;
-		return r0 + (1.0 - r0) * f64::powi((1.0 - cosine), 5);
+		return r0 + (1.0 - r0) * f64::powi(1.0 - cosine, 5);
 	}
 }
 
 impl Material for DielectricMaterial {
-	fn scatter(&self, ray_in: &Ray, rec: &HitRecord, attenuation: &mut Color, scattered: &mut Ray) -> bool {
-		*attenuation = Color::new(1.0, 1.0, 1.0);
-		let mut ri: f64 = self.refraction_index;
-		if rec.front_face {
-			ri = 1.0 / self.refraction_index;
+	fn sample(&self, ray_in: &Ray, rec: &HitRecord, sampler: &mut Sampler) -> Option<ScatterSample> {
+		let (reflected, refracted, reflect_probability) = self.directions(&ray_in.dir.normalize(), rec);
+
+		let mut direction = reflected;
+		let mut pdf = reflect_probability;
+		let mut lobe = LobeFlags::REFLECTION;
+		let u = sampler.get_1d();
+		if let Some(refracted) = refracted
+			&& reflect_probability <= u {
+			direction = refracted;
+			pdf = 1.0 - reflect_probability;
+			lobe = LobeFlags::TRANSMISSION;
 		}
 
-		let unit_direction = ray_in.dir.normalize();
-		let cos_theta = Vector::dot(&(unit_direction * (-1.0)), &rec.normal).min(1.0);
-		let sin_theta = f64::sqrt(1.0 - cos_theta * cos_theta);
+		if self.spread_cos < 1.0 {
+			//eval and pdf both add up the two cones, so the weight stays 1.
+			let direction = sample_cone(&direction, self.spread_cos, sampler);
+			return Some(ScatterSample {
+				direction,
+				weight: Color::new(1.0, 1.0, 1.0),
+				pdf: self.pdf(&(ray_in.dir.normalize() * -1.0), &direction, rec),
+				lobe: LobeFlags::GLOSSY | lobe,
+			});
+		}
+		return Some(ScatterSample {
+			direction,
+			weight: Color::new(1.0, 1.0, 1.0),
+			pdf,
+			lobe: LobeFlags::SPECULAR | lobe,
+		});
+	}
+	fn eval(&self, wo: &Vector, wi: &Vector, rec: &HitRecord) -> Color {
+		let cos_theta = Vector::dot(wi, &rec.normal).abs();
+		if cos_theta <= 0.0 {
+			return Color::null_vector();
+		}
+		return Color::new(1.0, 1.0, 1.0) * (self.pdf(wo, wi, rec) / cos_theta);
+	}
+	fn pdf(&self, wo: &Vector, wi: &Vector, rec: &HitRecord) -> f64 {
+		if self.spread_cos >= 1.0 {
+			return 0.0;
+		}
+		let (reflected, refracted, reflect_probability) = self.directions(&(*wo * -1.0), rec);
+		let mut pdf = reflect_probability * cone_pdf(wi, &reflected, self.spread_cos);
+		if let Some(refracted) = refracted {
+			pdf += (1.0 - reflect_probability) * cone_pdf(wi, &refracted, self.spread_cos);
+		}
+		return pdf;
+	}
+}
+
+//A direction spread evenly over the cone around axis whose half angle has cosine cos_max.
+fn sample_cone(axis: &Vector, cos_max: f64, sampler: &mut Sampler) -> Vector {
+	let uvw = Onb::new(axis);
+	return uvw.local(&sampling::uniform_cone(sampler.get_2d(), cos_max)).normalize();
+}
+
+//the density sample_cone gives wi.
+fn cone_pdf(wi: &Vector, axis: &Vector, cos_max: f64) -> f64 {
+	if Vector::dot(wi, &axis.normalize()) < cos_max {
+		return 0.0;
+	}
+	return sampling::uniform_cone_pdf(cos_max);
+}
+
+//DIFFUSE LIGHT MATERIAL
 
-		//let cannot_refract: bool = ri * sin_theta > 1.0;
-		let mut direction: Vector = Vector::unit_vector();
+#[derive(Clone, Copy)]
+pub struct DiffuseLightMaterial {
+	emit: Color,
+}
 
-		if ri * sin_theta > 1.0 || Self::reflectance(cos_theta, ri) > random_f64_in_range(0.0, 1.0) {
-			direction = Vector::reflect(&unit_direction, &rec.normal);
-		} else {
-			direction = Vector::refract(&unit_direction, &rec.normal, ri);
+impl DiffuseLightMaterial {
+	pub fn new(emit: Color) -> DiffuseLightMaterial {
+		DiffuseLightMaterial {
+			emit,
 		}
+	}
+}
 
+//lights only emit from their front face and absorb everything that hits them.
+impl Material for DiffuseLightMaterial {
+	fn sample(&self, _ray_in: &Ray, _rec: &HitRecord, _sampler: &mut Sampler) -> Option<ScatterSample> {
+		return None;
+	}
+	fn eval(&self, _wo: &Vector, _wi: &Vector, _rec: &HitRecord) -> Color {
+		return Color::null_vector();
+	}
+	fn pdf(&self, _wo: &Vector, _wi: &Vector, _rec: &HitRecord) -> f64 {
+		return 0.0;
+	}
+	fn emitted(&self, rec: &HitRecord) -> Color {
+		if !rec.front_face {
+			return Color::null_vector();
+		}
+		return self.emit;
+	}
+}
+
+//ISOTROPIC MATERIAL
+
+#[derive(Clone, Copy)]
+pub struct IsotropicMaterial {
+	albedo: Color,
+}
 
-		*scattered = Ray::new(rec.hit_point, direction);
-		return true;
+impl IsotropicMaterial {
+	pub fn new(albedo: Color) -> IsotropicMaterial {
+		IsotropicMaterial {
+			albedo,
+		}
+	}
+}
+
+//the normal means nothing here, every direction on the sphere is as likely.
+impl Material for IsotropicMaterial {
+	fn sample(&self, _ray_in: &Ray, _rec: &HitRecord, sampler: &mut Sampler) -> Option<ScatterSample> {
+		return Some(ScatterSample {
+			direction: sampling::uniform_sphere(sampler.get_2d()),
+			weight: self.albedo,
+			pdf: sampling::uniform_sphere_pdf(),
+			lobe: LobeFlags::VOLUME,
+		});
 	}
-}
\ No newline at end of file
+	fn eval(&self, _wo: &Vector, _wi: &Vector, _rec: &HitRecord) -> Color {
+		return self.albedo * sampling::uniform_sphere_pdf();
+	}
+	fn pdf(&self, _wo: &Vector, _wi: &Vector, _rec: &HitRecord) -> f64 {
+		return sampling::uniform_sphere_pdf();
+	}
+}
@@ -0,0 +1,84 @@
+use crate::Color;
+use crate::Ray;
+use crate::Vector;
+use crate::Interval;
+use crate::interval;
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::MaterialEnum;
+use crate::rng::hash;
+
+use std::sync::Arc;
+
+//Fog, smoke or haze of the same density everywhere inside a closed boundary.
+//A ray going through it scatters somewhere inside with a chance that grows with the distance,
+//or passes straight through. The scattering direction comes from an isotropic material.
+pub struct ConstantMedium {
+	boundary: Arc<dyn Hittable>,
+	neg_inv_density: f64,
+	phase_function: MaterialEnum,
+}
+
+impl ConstantMedium {
+	//density is the chance of scattering per unit of distance.
+	pub fn new(boundary: Arc<dyn Hittable>, density: f64, albedo: Color) -> ConstantMedium {
+		ConstantMedium {
+			boundary,
+			neg_inv_density: -1.0 / density,
+			phase_function: MaterialEnum::new_isotropic(albedo.x, albedo.y, albedo.z),
+		}
+	}
+
+	//hit has no sampler to draw from, so the scattering distance comes from a hash of the ray.
+	//every camera sample traces different rays, and the same ray always scatters in the same place,
+	//which keeps renders deterministic and makes shadow rays agree with themselves.
+	fn random(r: &Ray) -> f64 {
+		let bits = hash(&[
+			r.origin.x.to_bits(), r.origin.y.to_bits(), r.origin.z.to_bits(),
+			r.dir.x.to_bits(), r.dir.y.to_bits(), r.dir.z.to_bits(),
+		]);
+		//in (0, 1], the log below can't be given 0.
+		return ((bits >> 11) + 1) as f64 * (1.0 / (1u64 << 53) as f64);
+	}
+}
+
+impl Hittable for ConstantMedium {
+	fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+		//where the ray enters and leaves the boundary, even if it starts inside.
+		let mut rec1 = HitRecord::default();
+		let mut rec2 = HitRecord::default();
+		if !self.boundary.hit(r, interval::UNIVERSE, &mut rec1) {
+			return false;
+		}
+		if !self.boundary.hit(r, Interval::new(rec1.t + 0.0001, f64::INFINITY), &mut rec2) {
+			return false;
+		}
+
+		let t_enter = rec1.t.max(ray_t.min).max(0.0);
+		let t_exit = rec2.t.min(ray_t.max);
+		if t_enter >= t_exit {
+			return false;
+		}
+
+		let ray_length = r.dir.length();
+		let distance_inside_boundary = (t_exit - t_enter) * ray_length;
+		let hit_distance = self.neg_inv_density * Self::random(r).ln();
+		if hit_distance > distance_inside_boundary {
+			return false;
+		}
+
+		rec.t = t_enter + hit_distance / ray_length;
+		rec.hit_point = r.at(rec.t);
+		//there's no surface, the isotropic material ignores these.
+		rec.normal = Vector::new(1.0, 0.0, 0.0);
+		rec.front_face = true;
+		rec.u = 0.0;
+		rec.v = 0.0;
+		rec.material = self.phase_function;
+		return true;
+	}
+
+	fn bounding_box(&self) -> Aabb {
+		return self.boundary.bounding_box();
+	}
+}
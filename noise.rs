@@ -0,0 +1,70 @@
+//Gradient noise for procedural textures.
+//The gradients come from hashing the lattice points instead of from permutation tables,
+//so there is nothing to allocate and the textures using it can stay Copy.
+use crate::Point;
+use crate::Vector;
+use crate::rng::hash;
+
+//smooth noise in about [-1, 1], 0 at every integer point.
+pub fn noise(p: &Point) -> f64 {
+	let cell = Point::new(p.x.floor(), p.y.floor(), p.z.floor());
+	let local: Vector = *p - cell;
+	let fade = Vector::new(fade(local.x), fade(local.y), fade(local.z));
+
+	let mut sum = 0.0;
+	for corner in 0..8 {
+		let dx = (corner & 1) as f64;
+		let dy = ((corner >> 1) & 1) as f64;
+		let dz = ((corner >> 2) & 1) as f64;
+		let gradient = gradient(cell.x + dx, cell.y + dy, cell.z + dz);
+		let offset = Vector::new(local.x - dx, local.y - dy, local.z - dz);
+
+		let weight = (dx * fade.x + (1.0 - dx) * (1.0 - fade.x))
+			* (dy * fade.y + (1.0 - dy) * (1.0 - fade.y))
+			* (dz * fade.z + (1.0 - dz) * (1.0 - fade.z));
+		sum += weight * Vector::dot(&gradient, &offset);
+	}
+	return sum;
+}
+
+//Several octaves of noise, each twice as fine and half as strong as the last.
+//fractal_sum keeps the sign, turbulence adds up the absolute values.
+pub fn fractal_sum(p: &Point, octaves: i32) -> f64 {
+	let mut sum = 0.0;
+	let mut point = *p;
+	let mut weight = 1.0;
+	for _ in 0..octaves {
+		sum += weight * noise(&point);
+		weight *= 0.5;
+		point *= 2.0;
+	}
+	return sum;
+}
+pub fn turbulence(p: &Point, octaves: i32) -> f64 {
+	let mut sum = 0.0;
+	let mut point = *p;
+	let mut weight = 1.0;
+	for _ in 0..octaves {
+		sum += weight * noise(&point).abs();
+		weight *= 0.5;
+		point *= 2.0;
+	}
+	return sum;
+}
+
+//the quintic curve from improved Perlin noise, flat at 0 and 1.
+fn fade(t: f64) -> f64 {
+	return t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+}
+
+//one of the 12 cube edge directions, picked by hashing the lattice point.
+fn gradient(x: f64, y: f64, z: f64) -> Vector {
+	const GRADIENTS: [(f64, f64, f64); 12] = [
+		(1.0, 1.0, 0.0), (-1.0, 1.0, 0.0), (1.0, -1.0, 0.0), (-1.0, -1.0, 0.0),
+		(1.0, 0.0, 1.0), (-1.0, 0.0, 1.0), (1.0, 0.0, -1.0), (-1.0, 0.0, -1.0),
+		(0.0, 1.0, 1.0), (0.0, -1.0, 1.0), (0.0, 1.0, -1.0), (0.0, -1.0, -1.0),
+	];
+	let index = hash(&[x as i64 as u64, y as i64 as u64, z as i64 as u64]) % 12;
+	let (gx, gy, gz) = GRADIENTS[index as usize];
+	return Vector::new(gx, gy, gz);
+}
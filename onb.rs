@@ -0,0 +1,34 @@
+use crate::Vector;
+
+//An orthonormal basis, w is the axis everything is built around (usually a normal).
+#[derive(Debug, Clone, Copy)]
+pub struct Onb {
+	pub u: Vector,
+	pub v: Vector,
+	pub w: Vector,
+}
+
+impl Onb {
+	//builds the other two axes without branching on which component of n is largest.
+	//from "Building an Orthonormal Basis, Revisited" (Duff et al. 2017).
+	pub fn new(n: &Vector) -> Onb {
+		let w: Vector = n.normalize();
+		let sign = 1.0_f64.copysign(w.z);
+		let a = -1.0 / (sign + w.z);
+		let b = w.x * w.y * a;
+		Onb {
+			u: Vector::new(1.0 + sign * w.x * w.x * a, sign * b, -sign * w.x),
+			v: Vector::new(b, sign + w.y * w.y * a, -w.y),
+			w,
+		}
+	}
+
+	//goes from coordinates in this basis to world space.
+	pub fn local(&self, a: &Vector) -> Vector {
+		return (a.x * self.u) + (a.y * self.v) + (a.z * self.w);
+	}
+	//goes from world space to coordinates in this basis.
+	pub fn world_to_local(&self, a: &Vector) -> Vector {
+		return Vector::new(Vector::dot(a, &self.u), Vector::dot(a, &self.v), Vector::dot(a, &self.w));
+	}
+}
@@ -0,0 +1,63 @@
+//PCG32 random number generator (pcg-random.org).
+//It's tiny, fast and the same seed always gives the same sequence on every machine,
+//which the global rand functions can't promise.
+
+const PCG_MULTIPLIER: u64 = 0x5851_f42d_4c95_7f2d;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Rng {
+	state: u64,
+	increment: u64,
+}
+
+impl Rng {
+	//different streams with the same seed give unrelated sequences.
+	pub fn new(seed: u64, stream: u64) -> Rng {
+		let mut rng = Rng {
+			state: 0,
+			increment: (stream << 1) | 1,
+		};
+		rng.next_u32();
+		rng.state = rng.state.wrapping_add(seed);
+		rng.next_u32();
+		return rng;
+	}
+
+	pub fn next_u32(&mut self) -> u32 {
+		let old_state = self.state;
+		self.state = old_state.wrapping_mul(PCG_MULTIPLIER).wrapping_add(self.increment);
+		let xorshifted = (((old_state >> 18) ^ old_state) >> 27) as u32;
+		let rotation = (old_state >> 59) as u32;
+		return xorshifted.rotate_right(rotation);
+	}
+	pub fn next_u64(&mut self) -> u64 {
+		return ((self.next_u32() as u64) << 32) | self.next_u32() as u64;
+	}
+
+	//uniform in [0,1), uses 53 bits so every representable step can come up.
+	pub fn random_f64(&mut self) -> f64 {
+		return (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64);
+	}
+	pub fn random_f64_in_range(&mut self, min: f64, max: f64) -> f64 {
+		return min + (max - min) * self.random_f64();
+	}
+}
+
+//The splitmix64 finalizer, turns nearby inputs into unrelated outputs.
+pub fn mix_bits(mut v: u64) -> u64 {
+	v ^= v >> 31;
+	v = v.wrapping_mul(0x7fb5_d329_728e_a185);
+	v ^= v >> 27;
+	v = v.wrapping_mul(0x81da_def4_bc2d_d44d);
+	v ^= v >> 33;
+	return v;
+}
+
+//hashes a list of values into one, used to derive seeds from pixel and sample indices.
+pub fn hash(values: &[u64]) -> u64 {
+	let mut h: u64 = 0x9e37_79b9_7f4a_7c15;
+	for &value in values {
+		h = mix_bits(h ^ value.wrapping_add(0x9e37_79b9_7f4a_7c15));
+	}
+	return h;
+}
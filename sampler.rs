@@ -0,0 +1,272 @@
+use crate::rng::{Rng, hash, mix_bits};
+
+//the largest f64 below 1, samples have to stay inside [0,1).
+const ONE_MINUS_EPSILON: f64 = 1.0 - f64::EPSILON / 2.0;
+
+//Where each use of random numbers lives in the sample vector.
+//Keeping these fixed means a bounce always starts on the same dimension even when an
+//earlier bounce used fewer numbers, so the low discrepancy samplers stay well distributed.
+const PIXEL_DIMENSION: u32 = 0;
+const LENS_DIMENSION: u32 = 2;
+const TIME_DIMENSION: u32 = 4;
+const BOUNCE_DIMENSION: u32 = 5;
+//enough for the bsdf sample plus picking a light and a point on it, with room to spare.
+const DIMENSIONS_PER_BOUNCE: u32 = 8;
+
+//Which pattern the samples of a pixel follow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SamplerKind {
+	//plain uniform random numbers.
+	Independent,
+	//jittered strata, each dimension shuffled separately.
+	Stratified,
+	//the Halton sequence with Owen scrambling, one prime base per dimension.
+	Halton,
+	//pairs of Sobol dimensions padded together, each scrambled with its own seed.
+	Sobol,
+}
+
+//Hands out the random numbers used while tracing one camera sample.
+//Everything is derived from (seed, pixel, sample index, dimension), so a sample's value never
+//depends on which thread rendered it or what ran before it.
+#[derive(Debug, Clone)]
+pub struct Sampler {
+	kind: SamplerKind,
+	seed: u64,
+	samples_per_pixel: i32,
+	pixel: u64,
+	sample_index: i32,
+	dimension: u32,
+	//past this the pattern is done for the current bounce and numbers come from rng.
+	dimension_end: u32,
+	rng: Rng,
+}
+
+impl Sampler {
+	pub fn new(kind: SamplerKind, seed: u64, samples_per_pixel: i32) -> Sampler {
+		Sampler {
+			kind,
+			seed,
+			samples_per_pixel: samples_per_pixel.max(1),
+			pixel: 0,
+			sample_index: 0,
+			dimension: 0,
+			dimension_end: BOUNCE_DIMENSION,
+			rng: Rng::new(seed, 0),
+		}
+	}
+
+	pub fn start_pixel_sample(&mut self, i: i32, j: i32, sample_index: i32) {
+		self.pixel = ((j as u32 as u64) << 32) | (i as u32 as u64);
+		self.sample_index = sample_index;
+		self.dimension = 0;
+		self.dimension_end = BOUNCE_DIMENSION;
+		self.rng = Rng::new(hash(&[self.seed, self.pixel, sample_index as u64]), self.pixel);
+	}
+
+	//Moves to the block of dimensions reserved for the given bounce, 0 is the first hit.
+	pub fn start_bounce(&mut self, bounce: i32) {
+		self.dimension = BOUNCE_DIMENSION + bounce.max(0) as u32 * DIMENSIONS_PER_BOUNCE;
+		self.dimension_end = self.dimension + DIMENSIONS_PER_BOUNCE;
+	}
+
+	pub fn get_pixel_2d(&mut self) -> (f64, f64) {
+		return self.sample_2d(PIXEL_DIMENSION);
+	}
+	pub fn get_lens_2d(&mut self) -> (f64, f64) {
+		return self.sample_2d(LENS_DIMENSION);
+	}
+	pub fn get_time_1d(&mut self) -> f64 {
+		return self.sample_1d(TIME_DIMENSION);
+	}
+
+	pub fn get_1d(&mut self) -> f64 {
+		if self.dimension >= self.dimension_end {
+			return self.rng.random_f64();
+		}
+		let value = self.sample_1d(self.dimension);
+		self.dimension += 1;
+		return value;
+	}
+	pub fn get_2d(&mut self) -> (f64, f64) {
+		if self.dimension + 1 >= self.dimension_end {
+			return (self.rng.random_f64(), self.rng.random_f64());
+		}
+		let value = self.sample_2d(self.dimension);
+		self.dimension += 2;
+		return value;
+	}
+	pub fn random_f64_in_range(&mut self, min: f64, max: f64) -> f64 {
+		return min + (max - min) * self.get_1d();
+	}
+
+	fn dimension_hash(&self, dimension: u32) -> u64 {
+		return hash(&[self.seed, self.pixel, dimension as u64]);
+	}
+
+	fn sample_1d(&mut self, dimension: u32) -> f64 {
+		match self.kind {
+			SamplerKind::Independent => {
+				return self.rng.random_f64();
+			}
+			SamplerKind::Stratified => {
+				let Some(stratum) = self.permuted_index(dimension) else {
+					return self.rng.random_f64();
+				};
+				let jitter = self.rng.random_f64();
+				return ((stratum as f64 + jitter) / self.samples_per_pixel as f64).min(ONE_MINUS_EPSILON);
+			}
+			SamplerKind::Halton => {
+				if dimension as usize >= PRIMES.len() {
+					return self.rng.random_f64();
+				}
+				let scramble = self.dimension_hash(dimension);
+				return owen_scrambled_radical_inverse(PRIMES[dimension as usize], self.sample_index as u64, scramble);
+			}
+			SamplerKind::Sobol => {
+				let scramble = self.dimension_hash(dimension);
+				let index = self.permuted_index(dimension).unwrap_or(self.sample_index as u32);
+				return sobol_sample(van_der_corput(index), scramble as u32);
+			}
+		}
+	}
+
+	fn sample_2d(&mut self, dimension: u32) -> (f64, f64) {
+		match self.kind {
+			SamplerKind::Independent => {
+				return (self.rng.random_f64(), self.rng.random_f64());
+			}
+			SamplerKind::Stratified => {
+				//a grid that's as square as possible and has at least one cell per sample.
+				let count = self.samples_per_pixel as u32;
+				let x_strata = (count as f64).sqrt() as u32;
+				let y_strata = count.div_ceil(x_strata);
+				let Some(stratum) = self.permuted_index(dimension) else {
+					return (self.rng.random_f64(), self.rng.random_f64());
+				};
+				let x = (stratum % x_strata) as f64 + self.rng.random_f64();
+				let y = (stratum / x_strata) as f64 + self.rng.random_f64();
+				return ((x / x_strata as f64).min(ONE_MINUS_EPSILON), (y / y_strata as f64).min(ONE_MINUS_EPSILON));
+			}
+			SamplerKind::Halton => {
+				return (self.sample_1d(dimension), self.sample_1d(dimension + 1));
+			}
+			SamplerKind::Sobol => {
+				let scramble = self.dimension_hash(dimension);
+				let index = self.permuted_index(dimension).unwrap_or(self.sample_index as u32);
+				return (
+					sobol_sample(van_der_corput(index), scramble as u32),
+					sobol_sample(sobol_second_dimension(index), (scramble >> 32) as u32),
+				);
+			}
+		}
+	}
+
+	//the sample index shuffled differently for every pixel and dimension,
+	//None once a pixel takes more samples than it was set up for.
+	fn permuted_index(&self, dimension: u32) -> Option<u32> {
+		if self.sample_index >= self.samples_per_pixel {
+			return None;
+		}
+		let index = permutation_element(self.sample_index as u32, self.samples_per_pixel as u32, self.dimension_hash(dimension) as u32);
+		return Some(index);
+	}
+}
+
+//Returns where i ends up in a random permutation of 0..n picked by seed,
+//without building the permutation (Kensler, "Correlated Multi-Jittered Sampling").
+fn permutation_element(mut i: u32, n: u32, seed: u32) -> u32 {
+	let mut w = n - 1;
+	w |= w >> 1;
+	w |= w >> 2;
+	w |= w >> 4;
+	w |= w >> 8;
+	w |= w >> 16;
+	loop {
+		i ^= seed;
+		i = i.wrapping_mul(0xe170893d);
+		i ^= seed >> 16;
+		i ^= (i & w) >> 4;
+		i ^= seed >> 8;
+		i = i.wrapping_mul(0x0929eb3f);
+		i ^= seed >> 23;
+		i ^= (i & w) >> 1;
+		i = i.wrapping_mul(1 | seed >> 27);
+		i = i.wrapping_mul(0x6935fa69);
+		i ^= (i & w) >> 11;
+		i = i.wrapping_mul(0x74dcb303);
+		i ^= (i & w) >> 2;
+		i = i.wrapping_mul(0x9e501cc3);
+		i ^= (i & w) >> 2;
+		i = i.wrapping_mul(0xc860a3df);
+		i &= w;
+		i ^= i >> 5;
+		if i < n {
+			break;
+		}
+	}
+	return (i.wrapping_add(seed)) % n;
+}
+
+//Mirrors the digits of a in the given base around the decimal point,
+//shuffling each digit with a permutation that depends on the digits before it.
+fn owen_scrambled_radical_inverse(base: u64, mut a: u64, scramble: u64) -> f64 {
+	let inverse_base = 1.0 / base as f64;
+	let mut inverse_base_m = 1.0;
+	let mut value = 0.0;
+	//the digits so far, only used to pick the next permutation. for large bases they don't fit
+	//in 64 bits before the value runs out of precision, so they wrap.
+	let mut reversed: u64 = 0;
+	//keep going after a runs out of digits, the trailing zeros get scrambled too.
+	while 1.0 - (base - 1) as f64 * inverse_base_m < 1.0 {
+		let next = a / base;
+		let digit = a - next * base;
+		let digit_hash = mix_bits(scramble ^ reversed) as u32;
+		let digit = permutation_element(digit as u32, base as u32, digit_hash) as u64;
+		reversed = reversed.wrapping_mul(base).wrapping_add(digit);
+		inverse_base_m *= inverse_base;
+		value += digit as f64 * inverse_base_m;
+		a = next;
+	}
+	return value.min(ONE_MINUS_EPSILON);
+}
+
+//The first Sobol dimension is the base 2 radical inverse, kept bit reversed here.
+fn van_der_corput(index: u32) -> u32 {
+	return index.reverse_bits();
+}
+
+//The second Sobol dimension, its direction numbers are v(k) = v(k-1) ^ (v(k-1) >> 1).
+fn sobol_second_dimension(mut index: u32) -> u32 {
+	let mut v: u32 = 1 << 31;
+	let mut result: u32 = 0;
+	while index != 0 {
+		if index & 1 == 1 {
+			result ^= v;
+		}
+		index >>= 1;
+		v ^= v >> 1;
+	}
+	return result;
+}
+
+//Owen scrambles the bits of a Sobol value with a hash based scrambler
+//(Laine and Karras, refined by Burley) and turns it into a float.
+fn sobol_sample(mut v: u32, seed: u32) -> f64 {
+	v = v.reverse_bits();
+	v ^= v.wrapping_mul(0x3d20adea);
+	v = v.wrapping_add(seed);
+	v = v.wrapping_mul((seed >> 16) | 1);
+	v ^= v.wrapping_mul(0x05526c56);
+	v ^= v.wrapping_mul(0x53a22864);
+	v = v.reverse_bits();
+	return (v as f64 / 4294967296.0).min(ONE_MINUS_EPSILON);
+}
+
+//one base for each Halton dimension, after these the sampler falls back to random numbers.
+const PRIMES: [u64; 64] = [
+	2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53,
+	59, 61, 67, 71, 73, 79, 83, 89, 97, 101, 103, 107, 109, 113, 127, 131,
+	137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191, 193, 197, 199, 211, 223,
+	227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281, 283, 293, 307, 311,
+];
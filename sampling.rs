@@ -0,0 +1,87 @@
+//Warps uniform random numbers in [0,1) onto shapes.
+//Directions are in local space with +z as the axis, use an Onb to move them into world space.
+//Every sampler has a matching pdf function.
+use crate::Vector;
+use crate::PI;
+
+pub fn uniform_sphere(u: (f64, f64)) -> Vector {
+	let z = 1.0 - 2.0 * u.0;
+	let r = f64::sqrt((1.0 - z * z).max(0.0));
+	let phi = 2.0 * PI * u.1;
+	return Vector::new(r * phi.cos(), r * phi.sin(), z);
+}
+pub fn uniform_sphere_pdf() -> f64 {
+	return 1.0 / (4.0 * PI);
+}
+
+pub fn uniform_hemisphere(u: (f64, f64)) -> Vector {
+	let z = u.0;
+	let r = f64::sqrt((1.0 - z * z).max(0.0));
+	let phi = 2.0 * PI * u.1;
+	return Vector::new(r * phi.cos(), r * phi.sin(), z);
+}
+pub fn uniform_hemisphere_pdf() -> f64 {
+	return 1.0 / (2.0 * PI);
+}
+
+//Shirley's concentric mapping, it keeps neighbouring samples close together
+//which matters for stratified and low discrepancy points.
+pub fn uniform_disk(u: (f64, f64)) -> (f64, f64) {
+	let offset_x = 2.0 * u.0 - 1.0;
+	let offset_y = 2.0 * u.1 - 1.0;
+	if offset_x == 0.0 && offset_y == 0.0 {
+		return (0.0, 0.0);
+	}
+
+	let r;
+	let theta;
+	if offset_x.abs() > offset_y.abs() {
+		r = offset_x;
+		theta = (PI / 4.0) * (offset_y / offset_x);
+	} else {
+		r = offset_y;
+		theta = (PI / 2.0) - (PI / 4.0) * (offset_x / offset_y);
+	}
+	return (r * theta.cos(), r * theta.sin());
+}
+pub fn uniform_disk_pdf() -> f64 {
+	return 1.0 / PI;
+}
+
+//Malley's method, project points on the disk up onto the hemisphere.
+pub fn cosine_hemisphere(u: (f64, f64)) -> Vector {
+	let (x, y) = uniform_disk(u);
+	let z = f64::sqrt((1.0 - x * x - y * y).max(0.0));
+	return Vector::new(x, y, z);
+}
+pub fn cosine_hemisphere_pdf(cos_theta: f64) -> f64 {
+	return cos_theta.max(0.0) / PI;
+}
+
+//returns barycentric coordinates, the pdf is one over the area of the triangle.
+pub fn uniform_triangle(u: (f64, f64)) -> (f64, f64, f64) {
+	let b0;
+	let b1;
+	if u.0 < u.1 {
+		b0 = u.0 / 2.0;
+		b1 = u.1 - b0;
+	} else {
+		b1 = u.1 / 2.0;
+		b0 = u.0 - b1;
+	}
+	return (b0, b1, 1.0 - b0 - b1);
+}
+pub fn uniform_triangle_pdf(area: f64) -> f64 {
+	return 1.0 / area;
+}
+
+//directions within the angle whose cosine is cos_max of the +z axis.
+pub fn uniform_cone(u: (f64, f64), cos_max: f64) -> Vector {
+	let cos_theta = (1.0 - u.0) + u.0 * cos_max;
+	let sin_theta = f64::sqrt((1.0 - cos_theta * cos_theta).max(0.0));
+	let phi = 2.0 * PI * u.1;
+	return Vector::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta);
+}
+pub fn uniform_cone_pdf(cos_max: f64) -> f64 {
+	return 1.0 / (2.0 * PI * (1.0 - cos_max));
+}
@@ -0,0 +1,382 @@
+//Built in scenes for trying out and comparing changes to the renderer.
+//Each one comes with the camera it's meant to be seen from, and sample counts that give a
+//reasonably clean image in a reasonable time. Change the camera before rendering for quicker previews.
+use crate::Color;
+use crate::Point;
+use crate::Vector;
+use crate::camera::{Background, Camera};
+use crate::hittable::{new_box, Hittable, HittableList, Sphere, Triangle};
+use crate::bvh::Bvh;
+use crate::csg::Csg;
+use crate::instance::{RotateY, Translate};
+use crate::light::LightEnum;
+use crate::material::MaterialEnum;
+use crate::medium::ConstantMedium;
+use crate::rng::Rng;
+use crate::sdf::{Sdf, SdfShape};
+use crate::shapes::{Capsule, Cone, Cylinder, Torus};
+use crate::texture::Texture;
+
+use std::sync::Arc;
+
+pub struct Scene {
+	pub world: HittableList,
+	pub camera: Camera,
+}
+
+//every name load_scene knows, in the order they're listed to users.
+pub const SCENE_NAMES: [&str; 10] = [
+	"three_spheres",
+	"cornell_box",
+	"random_spheres",
+	"glass_caustics",
+	"earth",
+	"fog_room",
+	"many_instances",
+	"csg_parts",
+	"machined_parts",
+	"sdf_shapes",
+];
+
+//None if there's no scene called name.
+pub fn load_scene(name: &str) -> Option<Scene> {
+	match name {
+		"three_spheres" => return Some(three_spheres()),
+		"cornell_box" => return Some(cornell_box()),
+		"random_spheres" => return Some(random_spheres()),
+		"glass_caustics" => return Some(glass_caustics()),
+		"earth" => return Some(earth()),
+		"fog_room" => return Some(fog_room()),
+		"many_instances" => return Some(many_instances()),
+		"csg_parts" => return Some(csg_parts()),
+		"machined_parts" => return Some(machined_parts()),
+		"sdf_shapes" => return Some(sdf_shapes()),
+		_ => return None,
+	}
+}
+
+//The matte, rough metal and hollow glass spheres on a yellow ground, the original test scene.
+pub fn three_spheres() -> Scene {
+	let camera = Camera::new(16.0 / 9.0, 300, 50);
+
+	let mut world = HittableList::new();
+	world.add(0.0, 0.0, -1.2, 0.5, MaterialEnum::new_lambertian(0.1, 0.2, 0.5));
+	world.add(1.0, 0.0, -1.0, 0.5, MaterialEnum::new_metal(0.6, 0.2, 0.8, 1.0));
+	world.add(-1.0, 0.0, -1.0, 0.5, MaterialEnum::new_dielectric(1.50));
+	world.add(-1.0, 0.0, -1.0, 0.4, MaterialEnum::new_dielectric(1.00 / 1.50));
+	world.add(0.0, -100.5, -1.0, 100.0, MaterialEnum::new_lambertian(0.8, 0.8, 0.0));
+	return Scene { world, camera };
+}
+
+//the empty room of the cornell box, 555 units on a side and open towards -z.
+fn cornell_room(world: &mut HittableList) {
+	let red = MaterialEnum::new_lambertian(0.65, 0.05, 0.05);
+	let white = MaterialEnum::new_lambertian(0.73, 0.73, 0.73);
+	let green = MaterialEnum::new_lambertian(0.12, 0.45, 0.15);
+	let light = MaterialEnum::new_diffuse_light(15.0, 15.0, 15.0);
+
+	world.add_quad(Point::new(555.0, 0.0, 0.0), Vector::new(0.0, 555.0, 0.0), Vector::new(0.0, 0.0, 555.0), green);
+	world.add_quad(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 555.0, 0.0), Vector::new(0.0, 0.0, 555.0), red);
+	//facing down into the room.
+	world.add_quad(Point::new(343.0, 554.0, 332.0), Vector::new(-130.0, 0.0, 0.0), Vector::new(0.0, 0.0, -105.0), light);
+	world.add_quad(Point::new(0.0, 0.0, 0.0), Vector::new(555.0, 0.0, 0.0), Vector::new(0.0, 0.0, 555.0), white);
+	world.add_quad(Point::new(555.0, 555.0, 555.0), Vector::new(-555.0, 0.0, 0.0), Vector::new(0.0, 0.0, -555.0), white);
+	world.add_quad(Point::new(0.0, 0.0, 555.0), Vector::new(555.0, 0.0, 0.0), Vector::new(0.0, 555.0, 0.0), white);
+}
+
+fn cornell_camera() -> Camera {
+	let mut camera = Camera::new(1.0, 400, 200);
+	camera.background = Background::Solid(Color::null_vector());
+	camera.max_depth = 50;
+	camera.vfov = 40.0;
+	camera.lookfrom = Point::new(278.0, 278.0, -800.0);
+	camera.lookat = Point::new(278.0, 278.0, 0.0);
+	camera.initialize();
+	return camera;
+}
+
+//a box standing on the floor, turned by angle degrees and moved to offset.
+fn placed_box(size: Vector, angle: f64, offset: Vector, material: MaterialEnum) -> Box<dyn Hittable> {
+	let shape: Arc<dyn Hittable> = Arc::new(new_box(Point::null_vector(), size, material));
+	let rotated: Arc<dyn Hittable> = Arc::new(RotateY::new(shape, angle));
+	return Box::new(Translate::new(rotated, offset));
+}
+
+//The classic Cornell box with its tall and short block, lit by the light in the ceiling.
+pub fn cornell_box() -> Scene {
+	let mut world = HittableList::new();
+	cornell_room(&mut world);
+	let white = MaterialEnum::new_lambertian(0.73, 0.73, 0.73);
+	world.raw_add(placed_box(Vector::new(165.0, 330.0, 165.0), 15.0, Vector::new(265.0, 0.0, 295.0), white));
+	world.raw_add(placed_box(Vector::new(165.0, 165.0, 165.0), -18.0, Vector::new(130.0, 0.0, 65.0), white));
+	return Scene { world, camera: cornell_camera() };
+}
+
+//The cover of Ray Tracing in One Weekend, a field of small random spheres around three big ones,
+//seen with a shallow depth of field. the spheres come from a fixed seed, so it's the same every time.
+pub fn random_spheres() -> Scene {
+	let mut world = HittableList::new();
+	world.add(0.0, -1000.0, 0.0, 1000.0, MaterialEnum::new_lambertian(0.5, 0.5, 0.5));
+
+	let mut rng = Rng::new(42, 0);
+	for a in -11..11 {
+		for b in -11..11 {
+			let choose_material = rng.random_f64();
+			let center = Point::new(a as f64 + 0.9 * rng.random_f64(), 0.2, b as f64 + 0.9 * rng.random_f64());
+			if (center - Point::new(4.0, 0.2, 0.0)).length() <= 0.9 {
+				continue;
+			}
+
+			let material;
+			if choose_material < 0.8 {
+				let albedo = Color::new(rng.random_f64(), rng.random_f64(), rng.random_f64())
+					* Color::new(rng.random_f64(), rng.random_f64(), rng.random_f64());
+				material = MaterialEnum::new_lambertian(albedo.x, albedo.y, albedo.z);
+			} else if choose_material < 0.95 {
+				let albedo = Color::new(rng.random_f64_in_range(0.5, 1.0), rng.random_f64_in_range(0.5, 1.0), rng.random_f64_in_range(0.5, 1.0));
+				material = MaterialEnum::new_metal(albedo.x, albedo.y, albedo.z, rng.random_f64_in_range(0.0, 0.5));
+			} else {
+				material = MaterialEnum::new_dielectric(1.5);
+			}
+			world.add(center.x, center.y, center.z, 0.2, material);
+		}
+	}
+
+	world.add(0.0, 1.0, 0.0, 1.0, MaterialEnum::new_dielectric(1.5));
+	world.add(-4.0, 1.0, 0.0, 1.0, MaterialEnum::new_lambertian(0.4, 0.2, 0.1));
+	world.add(4.0, 1.0, 0.0, 1.0, MaterialEnum::new_metal(0.7, 0.6, 0.5, 0.0));
+	world.build_bvh();
+
+	let mut camera = Camera::new(16.0 / 9.0, 400, 100);
+	camera.max_depth = 50;
+	camera.vfov = 20.0;
+	camera.lookfrom = Point::new(13.0, 2.0, 3.0);
+	camera.lookat = Point::new(0.0, 0.0, 0.0);
+	camera.defocus_angle = 0.6;
+	camera.focus_dist = 10.0;
+	camera.initialize();
+	return Scene { world, camera };
+}
+
+//A solid and a hollow glass sphere under a small bright light, throwing caustics on the floor.
+//Caustics can only be found by paths that happen to hit the light, so this one needs a lot of samples.
+pub fn glass_caustics() -> Scene {
+	let mut world = HittableList::new();
+	let floor = MaterialEnum::new_lambertian(0.8, 0.8, 0.8);
+	let wall = MaterialEnum::new_lambertian(0.6, 0.55, 0.5);
+	world.add_quad(Point::new(-10.0, 0.0, 10.0), Vector::new(20.0, 0.0, 0.0), Vector::new(0.0, 0.0, -20.0), floor);
+	world.add_quad(Point::new(-10.0, 0.0, -4.0), Vector::new(20.0, 0.0, 0.0), Vector::new(0.0, 10.0, 0.0), wall);
+
+	world.add(-1.2, 1.0, 0.0, 1.0, MaterialEnum::new_dielectric(1.5));
+	world.add(1.3, 0.8, 0.5, 0.8, MaterialEnum::new_dielectric(1.5));
+	world.add(1.3, 0.8, 0.5, 0.7, MaterialEnum::new_dielectric(1.0 / 1.5));
+	world.add(1.5, 6.0, 3.0, 0.3, MaterialEnum::new_diffuse_light(150.0, 140.0, 120.0));
+
+	let mut camera = Camera::new(16.0 / 9.0, 400, 500);
+	camera.background = Background::Solid(Color::null_vector());
+	camera.max_depth = 20;
+	camera.vfov = 35.0;
+	camera.lookfrom = Point::new(0.0, 3.5, 8.0);
+	camera.lookat = Point::new(0.0, 0.8, 0.0);
+	camera.initialize();
+	return Scene { world, camera };
+}
+
+//A procedural planet lit by a sun from the side.
+pub fn earth() -> Scene {
+	let mut world = HittableList::new();
+	world.add(0.0, 0.0, 0.0, 2.0, MaterialEnum::new_textured(Texture::Earth));
+	world.add_light(LightEnum::new_directional(Vector::new(-1.0, -0.2, -0.6), Color::new(2.0, 2.0, 1.9), 0.5));
+
+	let mut camera = Camera::new(16.0 / 9.0, 400, 64);
+	camera.background = Background::Solid(Color::new(0.01, 0.01, 0.02));
+	camera.max_depth = 8;
+	camera.vfov = 20.0;
+	camera.lookfrom = Point::new(0.0, 3.0, 12.0);
+	camera.lookat = Point::new(0.0, 0.0, 0.0);
+	camera.initialize();
+	return Scene { world, camera };
+}
+
+//The Cornell room filled with thin fog, so the light from the ceiling shows up as it scatters.
+//a block and a sphere cast shadows through it.
+pub fn fog_room() -> Scene {
+	let mut world = HittableList::new();
+	cornell_room(&mut world);
+	let white = MaterialEnum::new_lambertian(0.73, 0.73, 0.73);
+	world.raw_add(placed_box(Vector::new(165.0, 330.0, 165.0), 15.0, Vector::new(265.0, 0.0, 295.0), white));
+	world.add(190.0, 90.0, 190.0, 90.0, white);
+
+	//the fog stays clear of the walls so it doesn't fight with them over hits.
+	let boundary: Arc<dyn Hittable> = Arc::new(new_box(Point::new(1.0, 1.0, 1.0), Point::new(554.0, 553.0, 554.0), white));
+	world.raw_add(Box::new(ConstantMedium::new(boundary, 0.0015, Color::new(0.9, 0.9, 0.9))));
+
+	//light that scatters many times in the fog adds little, the depth limit keeps the paths short.
+	let mut camera = cornell_camera();
+	camera.samples_per_pixel = 500;
+	camera.max_depth = 16;
+	return Scene { world, camera };
+}
+
+//Ten thousand copies of a few small meshes spread over a plane, each turned a different way.
+//The meshes are stored once and the whole scene sits in a bvh, it's there to stress the acceleration.
+pub fn many_instances() -> Scene {
+	let mut world = HittableList::new();
+	world.add_quad(Point::new(-100.0, 0.0, 100.0), Vector::new(200.0, 0.0, 0.0), Vector::new(0.0, 0.0, -200.0), MaterialEnum::new_lambertian(0.5, 0.5, 0.5));
+
+	let materials = [
+		MaterialEnum::new_lambertian(0.8, 0.3, 0.2),
+		MaterialEnum::new_lambertian(0.2, 0.5, 0.8),
+		MaterialEnum::new_metal(0.9, 0.8, 0.5, 0.2),
+		MaterialEnum::new_lambertian(0.3, 0.7, 0.3),
+	];
+	let meshes: Vec<Arc<dyn Hittable>> = materials.iter().map(|material| Arc::new(icosahedron(0.4, *material)) as Arc<dyn Hittable>).collect();
+
+	let mut rng = Rng::new(7, 0);
+	let count = 100;
+	let spacing = 1.2;
+	for i in 0..count {
+		for j in 0..count {
+			let mesh = meshes[(rng.next_u32() as usize) % meshes.len()].clone();
+			let rotated: Arc<dyn Hittable> = Arc::new(RotateY::new(mesh, rng.random_f64_in_range(0.0, 360.0)));
+			let offset = Vector::new(
+				(i - count / 2) as f64 * spacing + rng.random_f64_in_range(-0.2, 0.2),
+				0.4,
+				(j - count / 2) as f64 * spacing + rng.random_f64_in_range(-0.2, 0.2),
+			);
+			world.raw_add(Box::new(Translate::new(rotated, offset)));
+		}
+	}
+	world.build_bvh();
+
+	let mut camera = Camera::new(16.0 / 9.0, 400, 32);
+	camera.max_depth = 8;
+	camera.vfov = 40.0;
+	camera.lookfrom = Point::new(0.0, 12.0, 40.0);
+	camera.lookat = Point::new(0.0, 0.0, 0.0);
+	camera.initialize();
+	return Scene { world, camera };
+}
+
+//Parts made from boolean combinations of spheres and boxes: a plate with three holes drilled through it,
+//a glass lens where two spheres overlap and a cube with rounded corners and a dimple in the top.
+pub fn csg_parts() -> Scene {
+	let mut world = HittableList::new();
+	world.add(0.0, -1000.0, 0.0, 1000.0, MaterialEnum::new_lambertian(0.5, 0.5, 0.5));
+
+	let steel = MaterialEnum::new_metal(0.7, 0.7, 0.75, 0.2);
+	let plate: Box<dyn Hittable> = Box::new(new_box(Point::new(-3.2, 0.0, -1.0), Point::new(-0.8, 0.3, 1.0), steel));
+	//the drills are wider than the plate is thick, so they go all the way through.
+	let mut drills: Box<dyn Hittable> = Box::new(Sphere::new(Point::new(-2.6, 0.15, 0.0), 0.3, steel));
+	for x in [-2.0, -1.4] {
+		let drill: Box<dyn Hittable> = Box::new(Sphere::new(Point::new(x, 0.15, 0.0), 0.3, steel));
+		drills = Box::new(Csg::union(drills, drill));
+	}
+	world.raw_add(Box::new(Csg::difference(plate, drills)));
+
+	let glass = MaterialEnum::new_dielectric(1.5);
+	let lens_center = Point::new(0.6, 1.0, 0.0);
+	let axis: Vector = Vector::new(1.0, 0.0, 1.0).normalize() * 1.75;
+	let front: Box<dyn Hittable> = Box::new(Sphere::new(lens_center + axis, 2.0, glass));
+	let back: Box<dyn Hittable> = Box::new(Sphere::new(lens_center - axis, 2.0, glass));
+	world.raw_add(Box::new(Csg::intersection(front, back)));
+
+	//the faces are the box's red and the rounded corners the sphere's blue.
+	let cube: Box<dyn Hittable> = Box::new(new_box(Point::new(1.8, 0.0, -0.6), Point::new(3.0, 1.2, 0.6), MaterialEnum::new_lambertian(0.7, 0.1, 0.1)));
+	let rounding: Box<dyn Hittable> = Box::new(Sphere::new(Point::new(2.4, 0.6, 0.0), 0.8, MaterialEnum::new_lambertian(0.1, 0.2, 0.7)));
+	let dimple: Box<dyn Hittable> = Box::new(Sphere::new(Point::new(2.4, 1.3, 0.0), 0.4, MaterialEnum::new_lambertian(0.9, 0.8, 0.2)));
+	world.raw_add(Box::new(Csg::difference(Box::new(Csg::intersection(cube, rounding)), dimple)));
+
+	let mut camera = Camera::new(16.0 / 9.0, 400, 100);
+	camera.max_depth = 20;
+	camera.vfov = 40.0;
+	camera.lookfrom = Point::new(0.0, 3.5, 6.0);
+	camera.lookat = Point::new(0.0, 0.4, 0.0);
+	camera.initialize();
+	return Scene { world, camera };
+}
+
+//The analytic shapes lit by a glowing ring overhead: a capped pipe, a cone with its tip cut off,
+//an open tube lying on its side, a capsule and a glass torus.
+pub fn machined_parts() -> Scene {
+	let mut world = HittableList::new();
+	world.add(0.0, -1000.0, 0.0, 1000.0, MaterialEnum::new_lambertian(0.5, 0.5, 0.5));
+
+	let steel = MaterialEnum::new_metal(0.7, 0.7, 0.75, 0.15);
+	let brass = MaterialEnum::new_metal(0.8, 0.6, 0.3, 0.3);
+	world.raw_add(Box::new(Cylinder::new(Point::new(-2.4, 0.0, 0.0), Point::new(-2.4, 1.4, 0.0), 0.5, steel)));
+	world.raw_add(Box::new(Cone::new(Point::new(-0.9, 0.0, 0.3), Point::new(-0.9, 1.2, 0.3), 0.6, 0.2, brass)));
+	world.raw_add(Box::new(Cylinder::new_open(Point::new(0.3, 0.35, 1.2), Point::new(1.5, 0.35, 0.6), 0.35, MaterialEnum::new_lambertian(0.2, 0.4, 0.7))));
+	world.raw_add(Box::new(Capsule::new(Point::new(0.4, 0.3, -0.6), Point::new(1.6, 0.9, -0.9), 0.3, MaterialEnum::new_lambertian(0.7, 0.2, 0.2))));
+	world.raw_add(Box::new(Torus::new(Point::new(2.7, 0.25, 0.2), Vector::new(0.0, 1.0, 0.0), 0.6, 0.25, MaterialEnum::new_dielectric(1.5))));
+
+	world.raw_add(Box::new(Torus::new(Point::new(0.0, 4.0, 0.0), Vector::new(0.0, 1.0, 0.0), 1.5, 0.1, MaterialEnum::new_diffuse_light(15.0, 14.0, 12.0))));
+
+	let mut camera = Camera::new(16.0 / 9.0, 400, 100);
+	camera.max_depth = 20;
+	camera.vfov = 40.0;
+	camera.background = Background::Solid(Color::new(0.05, 0.05, 0.07));
+	camera.lookfrom = Point::new(0.0, 3.0, 6.5);
+	camera.lookat = Point::new(0.2, 0.5, 0.0);
+	camera.initialize();
+	return Scene { world, camera };
+}
+
+//Shapes that only exist as distance functions: two balls melted together, a twisted bar,
+//a torus with a bumpy skin and a grid of small glass beads.
+pub fn sdf_shapes() -> Scene {
+	let mut world = HittableList::new();
+	world.add(0.0, -1000.0, 0.0, 1000.0, MaterialEnum::new_lambertian(0.5, 0.5, 0.5));
+
+	let blob = Sdf::new_smooth_union(
+		Sdf::new_sphere(Point::new(-2.6, 0.6, 0.0), 0.6),
+		Sdf::new_sphere(Point::new(-1.9, 0.9, 0.2), 0.4),
+		0.5,
+	);
+	world.raw_add(Box::new(SdfShape::new(blob, MaterialEnum::new_lambertian(0.8, 0.3, 0.2))));
+
+	//twisting turns around the y axis through the origin, so the bar is moved into place afterwards.
+	let bar = Sdf::new_twist(Sdf::new_round_box(Point::new(0.0, 0.9, 0.0), Vector::new(0.35, 0.9, 0.15), 0.05), 1.8);
+	world.raw_add(Box::new(Translate::new(Arc::new(SdfShape::new(bar, MaterialEnum::new_metal(0.8, 0.7, 0.4, 0.2))), Vector::new(-0.7, 0.0, 0.0))));
+
+	let bumpy = Sdf::new_displace(Sdf::new_torus(Point::new(0.9, 0.3, 0.0), 0.6, 0.25), 0.04, 12.0);
+	world.raw_add(Box::new(SdfShape::new(bumpy, MaterialEnum::new_lambertian(0.2, 0.5, 0.3))));
+
+	let beads = Sdf::new_repeat(Sdf::new_sphere(Point::new(0.0, 0.15, 0.0), 0.15), Vector::new(0.4, 0.0, 0.4), [3, 1, 3]);
+	world.raw_add(Box::new(Translate::new(Arc::new(SdfShape::new(beads, MaterialEnum::new_dielectric(1.5))), Vector::new(2.6, 0.0, 0.3))));
+
+	world.add_quad(Point::new(-1.5, 4.0, -1.0), Vector::new(3.0, 0.0, 0.0), Vector::new(0.0, 0.0, 2.0), MaterialEnum::new_diffuse_light(6.0, 6.0, 6.0));
+
+	let mut camera = Camera::new(16.0 / 9.0, 400, 100);
+	camera.max_depth = 20;
+	camera.vfov = 40.0;
+	camera.background = Background::Solid(Color::new(0.1, 0.1, 0.12));
+	camera.lookfrom = Point::new(0.0, 3.0, 6.5);
+	camera.lookat = Point::new(0.0, 0.5, 0.0);
+	camera.initialize();
+	return Scene { world, camera };
+}
+
+//twenty triangles around the origin, with its corners radius away.
+fn icosahedron(radius: f64, material: MaterialEnum) -> Bvh {
+	let t = (1.0 + f64::sqrt(5.0)) / 2.0;
+	let vertices: Vec<Point> = [
+		(-1.0, t, 0.0), (1.0, t, 0.0), (-1.0, -t, 0.0), (1.0, -t, 0.0),
+		(0.0, -1.0, t), (0.0, 1.0, t), (0.0, -1.0, -t), (0.0, 1.0, -t),
+		(t, 0.0, -1.0), (t, 0.0, 1.0), (-t, 0.0, -1.0), (-t, 0.0, 1.0),
+	]
+	.iter()
+	.map(|&(x, y, z)| Point::new(x, y, z).normalize() * radius)
+	.collect();
+	let faces: [[usize; 3]; 20] = [
+		[0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+		[1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+		[3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+		[4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+	];
+	let triangles: Vec<Box<dyn Hittable>> = faces
+		.iter()
+		.map(|face| Box::new(Triangle::new(vertices[face[0]], vertices[face[1]], vertices[face[2]], material)) as Box<dyn Hittable>)
+		.collect();
+	return Bvh::new(triangles);
+}
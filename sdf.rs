@@ -0,0 +1,308 @@
+//Shapes given by a signed distance function, negative inside and positive outside, for things
+//like fractals and organic forms that have no mesh. They're rendered by sphere tracing: at any
+//point the surface is at least the distance away, so the ray can safely move on that far.
+//Normals are the gradient of the distance, found by central differences.
+use crate::Point;
+use crate::Vector;
+use crate::Ray;
+use crate::Interval;
+use crate::aabb::Aabb;
+use crate::hittable::{sphere_uv, HitRecord, Hittable};
+use crate::material::MaterialEnum;
+
+pub enum Sdf {
+	Sphere {
+		center: Point,
+		radius: f64,
+	},
+	//a box with its edges rounded off, half_size is to the outside of the rounding.
+	RoundBox {
+		center: Point,
+		half_size: Vector,
+		rounding: f64,
+	},
+	//lies flat, around the y axis through center.
+	Torus {
+		center: Point,
+		major: f64,
+		minor: f64,
+	},
+	Capsule {
+		a: Point,
+		b: Point,
+		radius: f64,
+	},
+	//blends the two together where they are within about k of each other, a plain union when k is 0.
+	SmoothUnion {
+		a: Box<Sdf>,
+		b: Box<Sdf>,
+		k: f64,
+	},
+	//copies of inner spacing apart, copies[axis] of them along each axis, laid out around the origin.
+	//inner should be at the origin too, and each copy has to fit in its own cell or the distances get too long.
+	Repeat {
+		inner: Box<Sdf>,
+		spacing: Vector,
+		copies: [u32; 3],
+	},
+	//turns inner around the y axis by rate radians per unit of height.
+	Twist {
+		inner: Box<Sdf>,
+		rate: f64,
+	},
+	//moves the surface in and out by up to amplitude, in a pattern that repeats every 2 pi / frequency.
+	Displace {
+		inner: Box<Sdf>,
+		amplitude: f64,
+		frequency: f64,
+	},
+}
+
+impl Sdf {
+	pub fn new_sphere(center: Point, radius: f64) -> Sdf {
+		return Sdf::Sphere { center, radius };
+	}
+	pub fn new_round_box(center: Point, half_size: Vector, rounding: f64) -> Sdf {
+		return Sdf::RoundBox { center, half_size, rounding };
+	}
+	pub fn new_torus(center: Point, major: f64, minor: f64) -> Sdf {
+		return Sdf::Torus { center, major, minor };
+	}
+	pub fn new_capsule(a: Point, b: Point, radius: f64) -> Sdf {
+		return Sdf::Capsule { a, b, radius };
+	}
+	pub fn new_smooth_union(a: Sdf, b: Sdf, k: f64) -> Sdf {
+		return Sdf::SmoothUnion { a: Box::new(a), b: Box::new(b), k };
+	}
+	pub fn new_repeat(inner: Sdf, spacing: Vector, copies: [u32; 3]) -> Sdf {
+		return Sdf::Repeat { inner: Box::new(inner), spacing, copies };
+	}
+	pub fn new_twist(inner: Sdf, rate: f64) -> Sdf {
+		return Sdf::Twist { inner: Box::new(inner), rate };
+	}
+	pub fn new_displace(inner: Sdf, amplitude: f64, frequency: f64) -> Sdf {
+		return Sdf::Displace { inner: Box::new(inner), amplitude, frequency };
+	}
+
+	pub fn distance(&self, p: &Point) -> f64 {
+		match self {
+			Sdf::Sphere { center, radius } => return (*p - *center).length() - radius,
+			Sdf::RoundBox { center, half_size, rounding } => {
+				let q = *p - *center;
+				let corner = *half_size - Vector::new(*rounding, *rounding, *rounding);
+				let d = Vector::new(q.x.abs() - corner.x, q.y.abs() - corner.y, q.z.abs() - corner.z);
+				let outside = Vector::new(d.x.max(0.0), d.y.max(0.0), d.z.max(0.0)).length();
+				let inside = d.x.max(d.y).max(d.z).min(0.0);
+				return outside + inside - rounding;
+			}
+			Sdf::Torus { center, major, minor } => {
+				let q = *p - *center;
+				let ring = (q.x * q.x + q.z * q.z).sqrt() - major;
+				return (ring * ring + q.y * q.y).sqrt() - minor;
+			}
+			Sdf::Capsule { a, b, radius } => {
+				let ab = *b - *a;
+				let h = (Vector::dot(&(*p - *a), &ab) / ab.length_squared()).clamp(0.0, 1.0);
+				return (*p - *a - ab * h).length() - radius;
+			}
+			Sdf::SmoothUnion { a, b, k } => {
+				let (da, db) = (a.distance(p), b.distance(p));
+				if *k <= 0.0 {
+					return da.min(db);
+				}
+				//the polynomial smooth minimum, it digs in by at most k / 4 where the two are equal.
+				let h = (0.5 + 0.5 * (db - da) / k).clamp(0.0, 1.0);
+				return db + (da - db) * h - k * h * (1.0 - h);
+			}
+			Sdf::Repeat { inner, spacing, copies } => {
+				let mut q = *p;
+				for axis in 0..3 {
+					let count = copies[axis as usize];
+					if count <= 1 || spacing[axis] == 0.0 {
+						continue;
+					}
+					//the copy whose cell p is in, clamped to the ones there are.
+					let offset = 0.5 * (count - 1) as f64;
+					let cell = (p[axis] / spacing[axis] + offset).round().clamp(0.0, (count - 1) as f64);
+					q[axis] = p[axis] - spacing[axis] * (cell - offset);
+				}
+				return inner.distance(&q);
+			}
+			Sdf::Twist { inner, rate } => {
+				let (sin, cos) = (rate * p.y).sin_cos();
+				let q = Point::new(cos * p.x - sin * p.z, p.y, sin * p.x + cos * p.z);
+				return inner.distance(&q);
+			}
+			Sdf::Displace { inner, amplitude, frequency } => {
+				let wave = (frequency * p.x).sin() * (frequency * p.y).sin() * (frequency * p.z).sin();
+				return inner.distance(p) + amplitude * wave;
+			}
+		}
+	}
+
+	pub fn bounding_box(&self) -> Aabb {
+		match self {
+			Sdf::Sphere { center, radius } => {
+				let extent = Vector::new(*radius, *radius, *radius);
+				return Aabb::from_points(&(*center - extent), &(*center + extent));
+			}
+			Sdf::RoundBox { center, half_size, .. } => return Aabb::from_points(&(*center - *half_size), &(*center + *half_size)),
+			Sdf::Torus { center, major, minor } => {
+				let extent = Vector::new(major + minor, *minor, major + minor);
+				return Aabb::from_points(&(*center - extent), &(*center + extent));
+			}
+			Sdf::Capsule { a, b, radius } => {
+				let extent = Vector::new(*radius, *radius, *radius);
+				return Aabb::enclosing(&Aabb::from_points(&(*a - extent), &(*a + extent)), &Aabb::from_points(&(*b - extent), &(*b + extent)));
+			}
+			Sdf::SmoothUnion { a, b, k } => {
+				let bbox = Aabb::enclosing(&a.bounding_box(), &b.bounding_box());
+				let grow = 0.5 * k.max(0.0);
+				return Aabb::new(bbox.x.expand(grow), bbox.y.expand(grow), bbox.z.expand(grow));
+			}
+			Sdf::Repeat { inner, spacing, copies } => {
+				let mut bbox = inner.bounding_box();
+				for axis in 0..3 {
+					let count = copies[axis as usize];
+					if count <= 1 || spacing[axis] == 0.0 {
+						continue;
+					}
+					//the outermost copies sit half of the row's length either side of the origin.
+					let grow = 0.5 * spacing[axis].abs() * (count - 1) as f64;
+					match axis {
+						0 => bbox.x = Interval::new(bbox.x.min - grow, bbox.x.max + grow),
+						1 => bbox.y = Interval::new(bbox.y.min - grow, bbox.y.max + grow),
+						_ => bbox.z = Interval::new(bbox.z.min - grow, bbox.z.max + grow),
+					}
+				}
+				return bbox;
+			}
+			Sdf::Twist { inner, .. } => {
+				let bbox = inner.bounding_box();
+				let radius = max_radius(&bbox);
+				return Aabb::new(Interval::new(-radius, radius), bbox.y, Interval::new(-radius, radius));
+			}
+			Sdf::Displace { inner, amplitude, .. } => {
+				let bbox = inner.bounding_box();
+				let grow = 2.0 * amplitude.abs();
+				return Aabb::new(bbox.x.expand(grow), bbox.y.expand(grow), bbox.z.expand(grow));
+			}
+		}
+	}
+
+	//How much faster than the distance to the surface the function can change. An exact distance
+	//has 1, the twist and displacement stretch space and have more, so steps are divided by this.
+	fn lipschitz(&self) -> f64 {
+		match self {
+			Sdf::Sphere { .. } | Sdf::RoundBox { .. } | Sdf::Torus { .. } | Sdf::Capsule { .. } => return 1.0,
+			Sdf::SmoothUnion { a, b, .. } => return a.lipschitz().max(b.lipschitz()),
+			Sdf::Repeat { inner, .. } => return inner.lipschitz(),
+			Sdf::Twist { inner, rate } => {
+				//the twist shears space by rate times the distance from the axis, and a shear by s
+				//stretches by at most (s + sqrt(s^2 + 4)) / 2.
+				let shear = rate.abs() * max_radius(&inner.bounding_box());
+				return inner.lipschitz() * 0.5 * (shear + (shear * shear + 4.0).sqrt());
+			}
+			Sdf::Displace { inner, amplitude, frequency } => return inner.lipschitz() + amplitude.abs() * frequency.abs() * 3.0_f64.sqrt(),
+		}
+	}
+}
+
+//how far the box reaches from the y axis.
+fn max_radius(bbox: &Aabb) -> f64 {
+	let x = bbox.x.min.abs().max(bbox.x.max.abs());
+	let z = bbox.z.min.abs().max(bbox.z.max.abs());
+	return (x * x + z * z).sqrt();
+}
+
+//A distance function with a material, that can go in a HittableList next to everything else.
+//The ray is marched until it's within epsilon of the surface, and gives up after max_steps,
+//which can miss the edges of shapes the ray only grazes.
+//An emissive material glows where rays hit it, but it's never sampled as a light: a distance
+//function gives no way to pick points evenly on its surface. Scenes lit by one come out noisier.
+pub struct SdfShape {
+	sdf: Sdf,
+	material: MaterialEnum,
+	bbox: Aabb,
+	lipschitz: f64,
+	pub epsilon: f64,
+	pub max_steps: u32,
+}
+
+impl SdfShape {
+	pub fn new(sdf: Sdf, material: MaterialEnum) -> SdfShape {
+		let bbox = sdf.bounding_box();
+		let lipschitz = sdf.lipschitz();
+		SdfShape {
+			sdf,
+			material,
+			bbox,
+			lipschitz,
+			epsilon: 1e-4,
+			max_steps: 256,
+		}
+	}
+
+	//the gradient of the distance, which points out of the shape.
+	fn normal(&self, p: &Point) -> Vector {
+		let h = self.epsilon;
+		let dx = self.sdf.distance(&(*p + Vector::new(h, 0.0, 0.0))) - self.sdf.distance(&(*p - Vector::new(h, 0.0, 0.0)));
+		let dy = self.sdf.distance(&(*p + Vector::new(0.0, h, 0.0))) - self.sdf.distance(&(*p - Vector::new(0.0, h, 0.0)));
+		let dz = self.sdf.distance(&(*p + Vector::new(0.0, 0.0, h))) - self.sdf.distance(&(*p - Vector::new(0.0, 0.0, h)));
+		return Vector::new(dx, dy, dz).normalize();
+	}
+}
+
+impl Hittable for SdfShape {
+	fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+		let Some(inside_box) = self.bbox.clip(r, ray_t) else {
+			return false;
+		};
+		//t counts in multiples of the direction, distances are in world units.
+		let scale = 1.0 / (r.dir.length() * self.lipschitz);
+		let mut t = inside_box.min;
+		let mut distance = self.sdf.distance(&r.at(t));
+		let mut steps = 0;
+
+		//a ray that starts on the surface, like one that just bounced off it, steps off it first
+		//so it doesn't find the surface it's leaving. One coming in from outside the box that lands
+		//on the surface, where the shape fills the box right to the side, has hit it there.
+		if inside_box.min <= ray_t.min {
+			while distance.abs() < self.epsilon && steps < self.max_steps {
+				t += self.epsilon * scale;
+				distance = self.sdf.distance(&r.at(t));
+				steps += 1;
+			}
+		}
+		//rays that are inside where they enter the box march towards the way out.
+		let sign = distance.signum();
+
+		while steps < self.max_steps {
+			if t > inside_box.max {
+				return false;
+			}
+			if sign * distance < self.epsilon {
+				rec.t = t;
+				rec.hit_point = r.at(t);
+				let outward_normal = self.normal(&rec.hit_point);
+				rec.set_face_normal(r, &outward_normal);
+				(rec.u, rec.v) = sphere_uv(&outward_normal);
+				rec.material = self.material;
+				return true;
+			}
+			t += sign * distance * scale;
+			distance = self.sdf.distance(&r.at(t));
+			steps += 1;
+		}
+		return false;
+	}
+
+	fn bounding_box(&self) -> Aabb {
+		return self.bbox;
+	}
+
+	//keeps it out of the scene's lights whatever its material, see above.
+	fn is_emissive(&self) -> bool {
+		return false;
+	}
+}
@@ -0,0 +1,827 @@
+//The rounded shapes CAD models are made of: cylinders, cones, tori and capsules.
+//Each one is built around an axis and does its work in a local space where that axis is +z,
+//starting at the shape's base. Distances along the ray are the same in both spaces.
+//All of them can be area lights, their surfaces are sampled uniformly by area.
+use crate::Point;
+use crate::Vector;
+use crate::Ray;
+use crate::Interval;
+use crate::PI;
+use crate::aabb::Aabb;
+use crate::hittable::{area_to_solid_angle, HitRecord, Hittable, SurfaceSample};
+use crate::material::MaterialEnum;
+use crate::onb::Onb;
+use crate::sampler::Sampler;
+use crate::sampling;
+
+//Where a shape sits, the local z axis points along axis from origin.
+#[derive(Clone, Copy)]
+struct Frame {
+	origin: Point,
+	basis: Onb,
+}
+
+//A hit in local space, normal points out of the shape.
+struct LocalHit {
+	t: f64,
+	normal: Vector,
+	u: f64,
+	v: f64,
+}
+
+impl Frame {
+	fn new(origin: Point, axis: &Vector) -> Frame {
+		Frame {
+			origin,
+			basis: Onb::new(axis),
+		}
+	}
+
+	fn ray_to_local(&self, r: &Ray) -> Ray {
+		return Ray::new(self.basis.world_to_local(&(r.origin - self.origin)), self.basis.world_to_local(&r.dir));
+	}
+	fn point_to_world(&self, p: &Point) -> Point {
+		return self.origin + self.basis.local(p);
+	}
+
+	fn fill_record(&self, r: &Ray, hit: &LocalHit, material: MaterialEnum, rec: &mut HitRecord) {
+		rec.t = hit.t;
+		rec.hit_point = r.at(hit.t);
+		rec.set_face_normal(r, &self.basis.local(&hit.normal).normalize());
+		rec.u = hit.u;
+		rec.v = hit.v;
+		rec.material = material;
+	}
+
+	//the box around a disk of the given radius centered at height z on the axis.
+	fn disk_box(&self, z: f64, radius: f64) -> Aabb {
+		let center = self.point_to_world(&Point::new(0.0, 0.0, z));
+		let axis = self.basis.w;
+		let extent = Vector::new(
+			radius * f64::sqrt((1.0 - axis.x * axis.x).max(0.0)),
+			radius * f64::sqrt((1.0 - axis.y * axis.y).max(0.0)),
+			radius * f64::sqrt((1.0 - axis.z * axis.z).max(0.0)),
+		);
+		return Aabb::from_points(&(center - extent), &(center + extent));
+	}
+}
+
+//keeps hit if it's in ray_t and closer than best.
+fn keep_closest(best: &mut Option<LocalHit>, hit: LocalHit, ray_t: &Interval) {
+	if !ray_t.surrounds(hit.t) {
+		return;
+	}
+	if let Some(current) = best
+		&& current.t <= hit.t {
+		return;
+	}
+	*best = Some(hit);
+}
+
+//the roots of a t^2 + 2 half_b t + c, smallest first.
+fn solve_quadratic(a: f64, half_b: f64, c: f64) -> Option<(f64, f64)> {
+	if a.abs() < 1e-12 {
+		if half_b.abs() < 1e-12 {
+			return None;
+		}
+		let t = -c / (2.0 * half_b);
+		return Some((t, t));
+	}
+	let discriminant = half_b * half_b - a * c;
+	if discriminant < 0.0 {
+		return None;
+	}
+	//this form doesn't lose precision when half_b is much bigger than the root.
+	let q = -(half_b + half_b.signum() * discriminant.sqrt());
+	if q == 0.0 {
+		return Some((0.0, 0.0));
+	}
+	let (t0, t1) = (q / a, c / q);
+	if t0 < t1 {
+		return Some((t0, t1));
+	}
+	return Some((t1, t0));
+}
+
+//where p is around the z axis, from 0 to 1 starting at +x.
+fn azimuth(p: &Point) -> f64 {
+	let phi = f64::atan2(p.y, p.x);
+	if phi < 0.0 {
+		return (phi + 2.0 * PI) / (2.0 * PI);
+	}
+	return phi / (2.0 * PI);
+}
+
+//a flat end at height z facing normal_z, mapped onto the unit square.
+fn cap_hit(r: &Ray, z: f64, radius: f64, normal_z: f64) -> Option<LocalHit> {
+	if r.dir.z.abs() < 1e-12 {
+		return None;
+	}
+	let t = (z - r.origin.z) / r.dir.z;
+	let p = r.at(t);
+	if p.x * p.x + p.y * p.y > radius * radius {
+		return None;
+	}
+	return Some(LocalHit {
+		t,
+		normal: Vector::new(0.0, 0.0, normal_z),
+		u: 0.5 + 0.5 * p.x / radius,
+		v: 0.5 + 0.5 * p.y / radius,
+	});
+}
+
+//a uniformly picked point on the cap at height z, and its normal.
+fn cap_sample(u: (f64, f64), z: f64, radius: f64, normal_z: f64) -> (Point, Vector) {
+	let (x, y) = sampling::uniform_disk(u);
+	return (Point::new(x * radius, y * radius, z), Vector::new(0.0, 0.0, normal_z));
+}
+
+//Picks one of the pieces a surface is made of in proportion to their areas, using up u.
+//returns the piece and u stretched back over [0, 1).
+fn pick_piece(u: f64, areas: &[f64]) -> (usize, f64) {
+	let total: f64 = areas.iter().sum();
+	let mut start = 0.0;
+	for (index, area) in areas.iter().enumerate() {
+		let end = start + area / total;
+		if u < end || index == areas.len() - 1 {
+			return (index, ((u - start) / (end - start)).clamp(0.0, 1.0 - f64::EPSILON));
+		}
+		start = end;
+	}
+	return (0, u);
+}
+
+fn area_light_sample(origin: &Point, point: Point, normal: Vector, area: f64) -> Option<SurfaceSample> {
+	return Some(SurfaceSample {
+		point,
+		normal,
+		pdf: area_to_solid_angle(origin, &point, &normal, 1.0 / area),
+	});
+}
+
+fn area_light_pdf(shape: &dyn Hittable, area: f64, origin: &Point, direction: &Vector) -> f64 {
+	let mut rec = HitRecord::default();
+	if !shape.hit(&Ray::new(*origin, *direction), Interval::new(0.001, f64::INFINITY), &mut rec) {
+		return 0.0;
+	}
+	return area_to_solid_angle(origin, &rec.hit_point, &rec.normal, 1.0 / area);
+}
+
+//CYLINDER
+
+//A cylinder from base to top. u goes around the axis and v up it,
+//the caps are mapped onto the unit square seen from above.
+pub struct Cylinder {
+	frame: Frame,
+	height: f64,
+	radius: f64,
+	capped: bool,
+	area: f64,
+	bbox: Aabb,
+	material: MaterialEnum,
+}
+
+impl Cylinder {
+	//closed at both ends.
+	pub fn new(base: Point, top: Point, radius: f64, material: MaterialEnum) -> Cylinder {
+		return Cylinder::build(base, top, radius, true, material);
+	}
+	//just the tube.
+	pub fn new_open(base: Point, top: Point, radius: f64, material: MaterialEnum) -> Cylinder {
+		return Cylinder::build(base, top, radius, false, material);
+	}
+
+	fn build(base: Point, top: Point, radius: f64, capped: bool, material: MaterialEnum) -> Cylinder {
+		let radius = radius.max(0.0);
+		let height = (top - base).length();
+		if height <= 0.0 || radius <= 0.0 {
+			//base and top in the same place have no axis, and no radius leaves just a line,
+			//so there's nothing to hit. any axis keeps the frame finite.
+			return Cylinder {
+				frame: Frame::new(base, &Vector::new(0.0, 0.0, 1.0)),
+				height,
+				radius,
+				capped,
+				area: 0.0,
+				bbox: Aabb::empty(),
+				material,
+			};
+		}
+		let frame = Frame::new(base, &(top - base));
+		let mut area = 2.0 * PI * radius * height;
+		if capped {
+			area += 2.0 * PI * radius * radius;
+		}
+		Cylinder {
+			frame,
+			height,
+			radius,
+			capped,
+			area,
+			bbox: Aabb::enclosing(&frame.disk_box(0.0, radius), &frame.disk_box(height, radius)),
+			material,
+		}
+	}
+
+	fn sample_point(&self, u: (f64, f64)) -> (Point, Vector) {
+		let side = 2.0 * PI * self.radius * self.height;
+		let cap = PI * self.radius * self.radius;
+		let mut areas = vec![side];
+		if self.capped {
+			areas.extend([cap, cap]);
+		}
+		let (piece, u0) = pick_piece(u.0, &areas);
+		match piece {
+			1 => return cap_sample((u0, u.1), 0.0, self.radius, -1.0),
+			2 => return cap_sample((u0, u.1), self.height, self.radius, 1.0),
+			_ => {
+				let phi = 2.0 * PI * u.1;
+				let normal = Vector::new(phi.cos(), phi.sin(), 0.0);
+				return (normal * self.radius + Vector::new(0.0, 0.0, u0 * self.height), normal);
+			}
+		}
+	}
+}
+
+impl Hittable for Cylinder {
+	fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+		if self.area <= 0.0 {
+			return false;
+		}
+		let local = self.frame.ray_to_local(r);
+		let (o, d) = (local.origin, local.dir);
+		let mut best: Option<LocalHit> = None;
+
+		let a = d.x * d.x + d.y * d.y;
+		let half_b = o.x * d.x + o.y * d.y;
+		let c = o.x * o.x + o.y * o.y - self.radius * self.radius;
+		if let Some((t0, t1)) = solve_quadratic(a, half_b, c) {
+			for t in [t0, t1] {
+				let p = local.at(t);
+				if p.z < 0.0 || p.z > self.height {
+					continue;
+				}
+				keep_closest(&mut best, LocalHit {
+					t,
+					normal: Vector::new(p.x, p.y, 0.0) / self.radius,
+					u: azimuth(&p),
+					v: p.z / self.height,
+				}, &ray_t);
+			}
+		}
+		if self.capped {
+			for (z, normal_z) in [(0.0, -1.0), (self.height, 1.0)] {
+				if let Some(hit) = cap_hit(&local, z, self.radius, normal_z) {
+					keep_closest(&mut best, hit, &ray_t);
+				}
+			}
+		}
+
+		let Some(hit) = best else {
+			return false;
+		};
+		self.frame.fill_record(r, &hit, self.material, rec);
+		return true;
+	}
+
+	fn bounding_box(&self) -> Aabb {
+		return self.bbox;
+	}
+
+	fn is_emissive(&self) -> bool {
+		return self.material.is_emissive();
+	}
+	fn sample_surface(&self, origin: &Point, sampler: &mut Sampler) -> Option<SurfaceSample> {
+		if self.area <= 0.0 {
+			return None;
+		}
+		let (point, normal) = self.sample_point(sampler.get_2d());
+		return area_light_sample(origin, self.frame.point_to_world(&point), self.frame.basis.local(&normal), self.area);
+	}
+	fn surface_pdf(&self, origin: &Point, direction: &Vector) -> f64 {
+		return area_light_pdf(self, self.area, origin, direction);
+	}
+}
+
+//CONE
+
+//A cone or a cut off cone, base_radius at base narrowing or widening to top_radius at top.
+//a top_radius of 0 makes a pointed cone. uv works like the cylinder's.
+pub struct Cone {
+	frame: Frame,
+	height: f64,
+	base_radius: f64,
+	top_radius: f64,
+	capped: bool,
+	area: f64,
+	bbox: Aabb,
+	material: MaterialEnum,
+}
+
+impl Cone {
+	//closed at the ends that have a radius.
+	pub fn new(base: Point, top: Point, base_radius: f64, top_radius: f64, material: MaterialEnum) -> Cone {
+		return Cone::build(base, top, base_radius, top_radius, true, material);
+	}
+	pub fn new_open(base: Point, top: Point, base_radius: f64, top_radius: f64, material: MaterialEnum) -> Cone {
+		return Cone::build(base, top, base_radius, top_radius, false, material);
+	}
+
+	fn build(base: Point, top: Point, base_radius: f64, top_radius: f64, capped: bool, material: MaterialEnum) -> Cone {
+		let (base_radius, top_radius) = (base_radius.max(0.0), top_radius.max(0.0));
+		let height = (top - base).length();
+		if height <= 0.0 {
+			//base and top in the same place have no slope to go by, so there's nothing to hit.
+			//any axis keeps the frame finite.
+			return Cone {
+				frame: Frame::new(base, &Vector::new(0.0, 0.0, 1.0)),
+				height: 0.0,
+				base_radius,
+				top_radius,
+				capped,
+				area: 0.0,
+				bbox: Aabb::empty(),
+				material,
+			};
+		}
+		let frame = Frame::new(base, &(top - base));
+		let slant = f64::sqrt((top_radius - base_radius).powi(2) + height * height);
+		let mut area = PI * (base_radius + top_radius) * slant;
+		if capped {
+			area += PI * (base_radius * base_radius + top_radius * top_radius);
+		}
+		Cone {
+			frame,
+			height,
+			base_radius,
+			top_radius,
+			capped,
+			area,
+			bbox: Aabb::enclosing(&frame.disk_box(0.0, base_radius), &frame.disk_box(height, top_radius)),
+			material,
+		}
+	}
+
+	//how fast the radius changes going up.
+	fn slope(&self) -> f64 {
+		return (self.top_radius - self.base_radius) / self.height;
+	}
+
+	//the side's normal at p, which is on the side. the radius grows by slope for every unit up,
+	//so the normal leans back down by that much.
+	fn side_normal(&self, p: &Point) -> Vector {
+		let rho = f64::sqrt(p.x * p.x + p.y * p.y);
+		if rho == 0.0 {
+			//the tip, there's no good answer so point along the axis.
+			return Vector::new(0.0, 0.0, 1.0);
+		}
+		return Vector::new(p.x / rho, p.y / rho, -self.slope()).normalize();
+	}
+
+	fn sample_point(&self, u: (f64, f64)) -> (Point, Vector) {
+		let slant = f64::sqrt((self.top_radius - self.base_radius).powi(2) + self.height * self.height);
+		let mut areas = vec![PI * (self.base_radius + self.top_radius) * slant];
+		if self.capped {
+			areas.extend([PI * self.base_radius * self.base_radius, PI * self.top_radius * self.top_radius]);
+		}
+		let (piece, u0) = pick_piece(u.0, &areas);
+		match piece {
+			1 => return cap_sample((u0, u.1), 0.0, self.base_radius, -1.0),
+			2 => return cap_sample((u0, u.1), self.height, self.top_radius, 1.0),
+			_ => {
+				//the area of a ring of the side grows with its radius.
+				let z = if (self.top_radius - self.base_radius).abs() < 1e-12 {
+					u0 * self.height
+				} else {
+					let base_squared = self.base_radius * self.base_radius;
+					let radius = f64::sqrt(base_squared + u0 * (self.top_radius * self.top_radius - base_squared));
+					(radius - self.base_radius) / self.slope()
+				};
+				let radius = self.base_radius + self.slope() * z;
+				let phi = 2.0 * PI * u.1;
+				let p = Point::new(radius * phi.cos(), radius * phi.sin(), z);
+				return (p, self.side_normal(&p));
+			}
+		}
+	}
+}
+
+impl Hittable for Cone {
+	fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+		if self.height <= 0.0 {
+			return false;
+		}
+		let local = self.frame.ray_to_local(r);
+		let (o, d) = (local.origin, local.dir);
+		let mut best: Option<LocalHit> = None;
+
+		//x^2 + y^2 = (base_radius + slope z)^2
+		let k = self.slope();
+		let radius_at_origin = self.base_radius + k * o.z;
+		let a = d.x * d.x + d.y * d.y - k * k * d.z * d.z;
+		let half_b = o.x * d.x + o.y * d.y - k * radius_at_origin * d.z;
+		let c = o.x * o.x + o.y * o.y - radius_at_origin * radius_at_origin;
+		if let Some((t0, t1)) = solve_quadratic(a, half_b, c) {
+			for t in [t0, t1] {
+				let p = local.at(t);
+				if p.z < 0.0 || p.z > self.height {
+					continue;
+				}
+				keep_closest(&mut best, LocalHit {
+					t,
+					normal: self.side_normal(&p),
+					u: azimuth(&p),
+					v: p.z / self.height,
+				}, &ray_t);
+			}
+		}
+		if self.capped {
+			for (z, radius, normal_z) in [(0.0, self.base_radius, -1.0), (self.height, self.top_radius, 1.0)] {
+				if radius <= 0.0 {
+					continue;
+				}
+				if let Some(hit) = cap_hit(&local, z, radius, normal_z) {
+					keep_closest(&mut best, hit, &ray_t);
+				}
+			}
+		}
+
+		let Some(hit) = best else {
+			return false;
+		};
+		self.frame.fill_record(r, &hit, self.material, rec);
+		return true;
+	}
+
+	fn bounding_box(&self) -> Aabb {
+		return self.bbox;
+	}
+
+	fn is_emissive(&self) -> bool {
+		return self.material.is_emissive();
+	}
+	fn sample_surface(&self, origin: &Point, sampler: &mut Sampler) -> Option<SurfaceSample> {
+		if self.area <= 0.0 {
+			return None;
+		}
+		let (point, normal) = self.sample_point(sampler.get_2d());
+		return area_light_sample(origin, self.frame.point_to_world(&point), self.frame.basis.local(&normal), self.area);
+	}
+	fn surface_pdf(&self, origin: &Point, direction: &Vector) -> f64 {
+		return area_light_pdf(self, self.area, origin, direction);
+	}
+}
+
+//TORUS
+
+//A ring around center, in the plane facing axis. major is the radius of the ring and minor the
+//radius of the tube, which has to be the smaller one. u goes around the ring and v around the tube,
+//starting on the outside.
+pub struct Torus {
+	frame: Frame,
+	major: f64,
+	minor: f64,
+	area: f64,
+	bbox: Aabb,
+	material: MaterialEnum,
+}
+
+impl Torus {
+	//A minor bigger than major would make a spindle torus, whose tube passes through the axis and
+	//overlaps itself. That isn't supported, minor is cut down to major instead, which makes a horn
+	//torus with the hole closed to a point.
+	pub fn new(center: Point, axis: Vector, major: f64, minor: f64, material: MaterialEnum) -> Torus {
+		let major = major.max(0.0);
+		let minor = minor.clamp(0.0, major);
+		let frame = Frame::new(center, &axis);
+		//the ring's disk, made thicker by the tube along the axis.
+		let ring = frame.disk_box(0.0, major + minor);
+		let axis = frame.basis.w;
+		let thickness = Vector::new(axis.x.abs(), axis.y.abs(), axis.z.abs()) * minor;
+		let bbox = Aabb::from_points(
+			&(Point::new(ring.x.min, ring.y.min, ring.z.min) - thickness),
+			&(Point::new(ring.x.max, ring.y.max, ring.z.max) + thickness),
+		);
+		Torus {
+			frame,
+			major,
+			minor,
+			area: 4.0 * PI * PI * major * minor,
+			bbox,
+			material,
+		}
+	}
+
+	fn normal_at(&self, p: &Point) -> Vector {
+		let rho = f64::sqrt(p.x * p.x + p.y * p.y);
+		if rho == 0.0 {
+			return Vector::new(0.0, 0.0, p.z.signum());
+		}
+		//from the closest point on the ring out to p.
+		let ring_point = Point::new(p.x / rho * self.major, p.y / rho * self.major, 0.0);
+		return (*p - ring_point).normalize();
+	}
+
+	fn tube_angle(&self, p: &Point) -> f64 {
+		let rho = f64::sqrt(p.x * p.x + p.y * p.y);
+		let theta = f64::atan2(p.z, rho - self.major);
+		if theta < 0.0 {
+			return (theta + 2.0 * PI) / (2.0 * PI);
+		}
+		return theta / (2.0 * PI);
+	}
+
+	fn sample_point(&self, u: (f64, f64)) -> (Point, Vector) {
+		//the outside of the tube has more area than the inside, by major + minor cos(theta).
+		//inverts its integral, major theta + minor sin(theta), with newton steps kept inside a bracket.
+		let target = 2.0 * PI * self.major * u.0;
+		let (mut low, mut high) = (0.0, 2.0 * PI);
+		let mut theta = 2.0 * PI * u.0;
+		for _ in 0..50 {
+			let error = self.major * theta + self.minor * theta.sin() - target;
+			if error.abs() < 1e-12 * self.major.max(1.0) {
+				break;
+			}
+			if error > 0.0 {
+				high = theta;
+			} else {
+				low = theta;
+			}
+			let derivative = self.major + self.minor * theta.cos();
+			theta -= error / derivative;
+			if !(theta > low && theta < high) {
+				theta = 0.5 * (low + high);
+			}
+		}
+
+		let phi = 2.0 * PI * u.1;
+		let around = Vector::new(phi.cos(), phi.sin(), 0.0);
+		let normal = around * theta.cos() + Vector::new(0.0, 0.0, theta.sin());
+		return (around * self.major + normal * self.minor, normal);
+	}
+}
+
+impl Hittable for Torus {
+	fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+		let local = self.frame.ray_to_local(r);
+		let length = local.dir.length();
+		let d = local.dir / length;
+		//starts from the point on the line closest to the center, which keeps the quartic well conditioned
+		//for rays from far away. s is the distance from there.
+		let shift = -Vector::dot(&local.origin, &d);
+		let o = local.origin + d * shift;
+
+		//(|p|^2 + major^2 - minor^2)^2 = 4 major^2 (x^2 + y^2) with p = o + s d.
+		let major_squared = self.major * self.major;
+		let od = Vector::dot(&o, &d);
+		let e = o.length_squared() + major_squared - self.minor * self.minor;
+		let planar_dd = d.x * d.x + d.y * d.y;
+		let planar_od = o.x * d.x + o.y * d.y;
+		let planar_oo = o.x * o.x + o.y * o.y;
+		let coefficients = [
+			e * e - 4.0 * major_squared * planar_oo,
+			4.0 * od * e - 8.0 * major_squared * planar_od,
+			4.0 * od * od + 2.0 * e - 4.0 * major_squared * planar_dd,
+			4.0 * od,
+			1.0,
+		];
+
+		let mut best: Option<LocalHit> = None;
+		for s in real_roots(&coefficients) {
+			let t = (s + shift) / length;
+			let p = local.at(t);
+			keep_closest(&mut best, LocalHit {
+				t,
+				normal: self.normal_at(&p),
+				u: azimuth(&p),
+				v: self.tube_angle(&p),
+			}, &ray_t);
+		}
+
+		let Some(hit) = best else {
+			return false;
+		};
+		self.frame.fill_record(r, &hit, self.material, rec);
+		return true;
+	}
+
+	fn bounding_box(&self) -> Aabb {
+		return self.bbox;
+	}
+
+	fn is_emissive(&self) -> bool {
+		return self.material.is_emissive();
+	}
+	fn sample_surface(&self, origin: &Point, sampler: &mut Sampler) -> Option<SurfaceSample> {
+		let (point, normal) = self.sample_point(sampler.get_2d());
+		return area_light_sample(origin, self.frame.point_to_world(&point), self.frame.basis.local(&normal), self.area);
+	}
+	fn surface_pdf(&self, origin: &Point, direction: &Vector) -> f64 {
+		return area_light_pdf(self, self.area, origin, direction);
+	}
+}
+
+//Every real root of the polynomial with the given coefficients, lowest power first, smallest first.
+//Between two roots of the derivative the polynomial only goes one way, so each of those
+//stretches has at most one root, which newton's method finds while bisection keeps it bracketed.
+fn real_roots(coefficients: &[f64]) -> Vec<f64> {
+	let degree = coefficients.len() - 1;
+	if degree == 1 {
+		if coefficients[1] == 0.0 {
+			return Vec::new();
+		}
+		return vec![-coefficients[0] / coefficients[1]];
+	}
+	if degree == 2 {
+		match solve_quadratic(coefficients[2], coefficients[1] / 2.0, coefficients[0]) {
+			Some((t0, t1)) => return vec![t0, t1],
+			None => return Vec::new(),
+		}
+	}
+
+	let derivative: Vec<f64> = coefficients.iter().enumerate().skip(1).map(|(power, c)| c * power as f64).collect();
+	let leading = coefficients[degree];
+	//no root is further from 0 than this.
+	let bound = 1.0 + coefficients[..degree].iter().map(|c| (c / leading).abs()).fold(0.0, f64::max);
+	let mut ends = vec![-bound];
+	ends.extend(real_roots(&derivative).into_iter().filter(|x| x.abs() < bound));
+	ends.push(bound);
+
+	let evaluate = |x: f64| coefficients.iter().rev().fold(0.0, |sum, c| sum * x + c);
+	let slope = |x: f64| derivative.iter().rev().fold(0.0, |sum, c| sum * x + c);
+	let mut roots = Vec::new();
+	for pair in ends.windows(2) {
+		let (mut low, mut high) = (pair[0], pair[1]);
+		let (value_low, value_high) = (evaluate(low), evaluate(high));
+		if value_low == 0.0 {
+			roots.push(low);
+			continue;
+		}
+		if value_low * value_high > 0.0 {
+			continue;
+		}
+		let rising = value_high > value_low;
+		let mut x = 0.5 * (low + high);
+		for _ in 0..100 {
+			let value = evaluate(x);
+			if value == 0.0 {
+				break;
+			}
+			if (value > 0.0) == rising {
+				high = x;
+			} else {
+				low = x;
+			}
+			//newton when it stays inside the bracket, halving it otherwise.
+			let next = x - value / slope(x);
+			if (next - x).abs() < 1e-14 * x.abs().max(1.0) {
+				x = next;
+				break;
+			}
+			x = if next > low && next < high { next } else { 0.5 * (low + high) };
+			if (high - low) < 1e-12 * x.abs().max(1.0) {
+				break;
+			}
+		}
+		roots.push(x);
+	}
+	return roots;
+}
+
+//CAPSULE
+
+//A cylinder from a to b with half spheres on both ends, everything within radius of the segment.
+//u goes around the axis and v along the outline from the pole at a to the pole at b.
+pub struct Capsule {
+	frame: Frame,
+	height: f64,
+	radius: f64,
+	area: f64,
+	bbox: Aabb,
+	material: MaterialEnum,
+}
+
+impl Capsule {
+	pub fn new(a: Point, b: Point, radius: f64, material: MaterialEnum) -> Capsule {
+		let radius = radius.max(0.0);
+		if radius <= 0.0 {
+			//just the segment, there's nothing to hit.
+			return Capsule {
+				frame: Frame::new(a, &Vector::new(0.0, 0.0, 1.0)),
+				height: (b - a).length(),
+				radius,
+				area: 0.0,
+				bbox: Aabb::empty(),
+				material,
+			};
+		}
+		let extent = Vector::new(radius, radius, radius);
+		let bbox = Aabb::enclosing(&Aabb::from_points(&(a - extent), &(a + extent)), &Aabb::from_points(&(b - extent), &(b + extent)));
+		let height = (b - a).length();
+		Capsule {
+			//a capsule around a single point is a sphere, any axis does.
+			frame: Frame::new(a, &if height > 0.0 { b - a } else { Vector::new(0.0, 0.0, 1.0) }),
+			height,
+			radius,
+			area: 4.0 * PI * radius * radius + 2.0 * PI * radius * height,
+			bbox,
+			material,
+		}
+	}
+
+	fn local_hit(&self, local: &Ray, t: f64) -> LocalHit {
+		let p = local.at(t);
+		let closest = Point::new(0.0, 0.0, p.z.clamp(0.0, self.height));
+		let normal = (p - closest) / self.radius;
+		//the length of the outline up to p.
+		let quarter = 0.5 * PI * self.radius;
+		let along;
+		if p.z < 0.0 {
+			along = self.radius * f64::acos(-normal.z.clamp(-1.0, 1.0));
+		} else if p.z > self.height {
+			along = quarter + self.height + self.radius * f64::asin(normal.z.clamp(-1.0, 1.0));
+		} else {
+			along = quarter + p.z;
+		}
+		return LocalHit {
+			t,
+			normal,
+			u: azimuth(&p),
+			v: along / (2.0 * quarter + self.height),
+		};
+	}
+
+	fn sample_point(&self, u: (f64, f64)) -> (Point, Vector) {
+		let (piece, u0) = pick_piece(u.0, &[4.0 * PI * self.radius * self.radius, 2.0 * PI * self.radius * self.height]);
+		if piece == 0 {
+			//the two halves of a sphere, each moved to its end.
+			let normal = sampling::uniform_sphere((u0, u.1));
+			let z = if normal.z > 0.0 { self.height } else { 0.0 };
+			return (normal * self.radius + Vector::new(0.0, 0.0, z), normal);
+		}
+		let phi = 2.0 * PI * u.1;
+		let normal = Vector::new(phi.cos(), phi.sin(), 0.0);
+		return (normal * self.radius + Vector::new(0.0, 0.0, u0 * self.height), normal);
+	}
+}
+
+impl Hittable for Capsule {
+	fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+		if self.radius <= 0.0 {
+			return false;
+		}
+		let local = self.frame.ray_to_local(r);
+		let (o, d) = (local.origin, local.dir);
+		let radius_squared = self.radius * self.radius;
+		let mut best: Option<LocalHit> = None;
+
+		let a = d.x * d.x + d.y * d.y;
+		let half_b = o.x * d.x + o.y * d.y;
+		let c = o.x * o.x + o.y * o.y - radius_squared;
+		if let Some((t0, t1)) = solve_quadratic(a, half_b, c) {
+			for t in [t0, t1] {
+				let z = o.z + t * d.z;
+				if z >= 0.0 && z <= self.height {
+					keep_closest(&mut best, self.local_hit(&local, t), &ray_t);
+				}
+			}
+		}
+		//each end only counts on its own side, the rest of its sphere is inside the tube.
+		for (z_center, below) in [(0.0, true), (self.height, false)] {
+			let oc = o - Point::new(0.0, 0.0, z_center);
+			let Some((t0, t1)) = solve_quadratic(d.length_squared(), Vector::dot(&oc, &d), oc.length_squared() - radius_squared) else {
+				continue;
+			};
+			for t in [t0, t1] {
+				let z = o.z + t * d.z;
+				if (below && z < 0.0) || (!below && z > self.height) {
+					keep_closest(&mut best, self.local_hit(&local, t), &ray_t);
+				}
+			}
+		}
+
+		let Some(hit) = best else {
+			return false;
+		};
+		self.frame.fill_record(r, &hit, self.material, rec);
+		return true;
+	}
+
+	fn bounding_box(&self) -> Aabb {
+		return self.bbox;
+	}
+
+	fn is_emissive(&self) -> bool {
+		return self.material.is_emissive();
+	}
+	fn sample_surface(&self, origin: &Point, sampler: &mut Sampler) -> Option<SurfaceSample> {
+		if self.area <= 0.0 {
+			return None;
+		}
+		let (point, normal) = self.sample_point(sampler.get_2d());
+		return area_light_sample(origin, self.frame.point_to_world(&point), self.frame.basis.local(&normal), self.area);
+	}
+	fn surface_pdf(&self, origin: &Point, direction: &Vector) -> f64 {
+		return area_light_pdf(self, self.area, origin, direction);
+	}
+}
@@ -0,0 +1,203 @@
+//Counters for where a render spends its time.
+//Each render thread counts into its own thread local copy while it traces a tile,
+//and the camera collects them when the tile is done, so counting needs no locking.
+use std::cell::Cell;
+use std::fs::File;
+use std::io::Write;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RayCounters {
+	pub primary_rays: u64,
+	//every bounce after the first hit.
+	pub secondary_rays: u64,
+	//rays towards lights, for next event estimation and punctual lights.
+	pub shadow_rays: u64,
+	//calls to an object's hit, including the ones inside bvhs and instances.
+	pub intersection_tests: u64,
+	pub bvh_node_visits: u64,
+	//paths that were still going when they ran out of depth.
+	pub max_depth_terminations: u64,
+	pub roulette_terminations: u64,
+	//samples whose indirect light was turned down by the firefly clamp.
+	pub clamped_samples: u64,
+	//mirror and glass hits that were blurred by path regularization.
+	pub regularized_bounces: u64,
+}
+
+impl RayCounters {
+	pub const ZERO: RayCounters = RayCounters {
+		primary_rays: 0,
+		secondary_rays: 0,
+		shadow_rays: 0,
+		intersection_tests: 0,
+		bvh_node_visits: 0,
+		max_depth_terminations: 0,
+		roulette_terminations: 0,
+		clamped_samples: 0,
+		regularized_bounces: 0,
+	};
+
+	pub fn add(&mut self, other: &RayCounters) {
+		self.primary_rays += other.primary_rays;
+		self.secondary_rays += other.secondary_rays;
+		self.shadow_rays += other.shadow_rays;
+		self.intersection_tests += other.intersection_tests;
+		self.bvh_node_visits += other.bvh_node_visits;
+		self.max_depth_terminations += other.max_depth_terminations;
+		self.roulette_terminations += other.roulette_terminations;
+		self.clamped_samples += other.clamped_samples;
+		self.regularized_bounces += other.regularized_bounces;
+	}
+}
+
+thread_local! {
+	static COUNTERS: Cell<RayCounters> = const { Cell::new(RayCounters::ZERO) };
+}
+
+//adds to the current thread's counters.
+pub(crate) fn count(update: impl FnOnce(&mut RayCounters)) {
+	COUNTERS.with(|counters| {
+		let mut value = counters.get();
+		update(&mut value);
+		counters.set(value);
+	});
+}
+
+//returns what the current thread has counted since the last call, and starts again from zero.
+pub(crate) fn take_counters() -> RayCounters {
+	return COUNTERS.with(|counters| counters.replace(RayCounters::ZERO));
+}
+
+//How long one tile took, x1 and y1 are exclusive.
+#[derive(Debug, Clone, Copy)]
+pub struct TileStats {
+	pub x0: i32,
+	pub y0: i32,
+	pub x1: i32,
+	pub y1: i32,
+	pub samples: u64,
+	pub time: Duration,
+}
+
+//Everything counted during a render, tiles are in the order they're merged.
+//a render done in several passes lists every tile once per pass.
+#[derive(Debug, Clone, Default)]
+pub struct RenderStats {
+	pub counters: RayCounters,
+	pub wall_time: Duration,
+	pub tiles: Vec<TileStats>,
+}
+
+impl RenderStats {
+	pub fn add(&mut self, other: &RenderStats) {
+		self.counters.add(&other.counters);
+		self.wall_time += other.wall_time;
+		self.tiles.extend_from_slice(&other.tiles);
+	}
+
+	//every camera sample is one path, starting with one primary ray.
+	pub fn samples(&self) -> u64 {
+		return self.counters.primary_rays;
+	}
+	pub fn samples_per_second(&self) -> f64 {
+		return Self::per_second(self.samples(), self.wall_time);
+	}
+	pub fn rays_per_second(&self) -> f64 {
+		return Self::per_second(self.total_rays(), self.wall_time);
+	}
+	pub fn total_rays(&self) -> u64 {
+		return self.counters.primary_rays + self.counters.secondary_rays + self.counters.shadow_rays;
+	}
+	//segments per path, not counting shadow rays.
+	pub fn average_path_length(&self) -> f64 {
+		if self.samples() == 0 {
+			return 0.0;
+		}
+		return (self.counters.primary_rays + self.counters.secondary_rays) as f64 / self.samples() as f64;
+	}
+
+	fn per_second(count: u64, time: Duration) -> f64 {
+		let seconds = time.as_secs_f64();
+		if seconds == 0.0 {
+			return 0.0;
+		}
+		return count as f64 / seconds;
+	}
+
+	//A table for people to read.
+	pub fn summary(&self) -> String {
+		let counters = &self.counters;
+		let mut rows: Vec<(&str, String)> = vec![
+			("wall time", format!("{:.3} s", self.wall_time.as_secs_f64())),
+			("samples", counters.primary_rays.to_string()),
+			("samples / s", format!("{:.0}", self.samples_per_second())),
+			("primary rays", counters.primary_rays.to_string()),
+			("secondary rays", counters.secondary_rays.to_string()),
+			("shadow rays", counters.shadow_rays.to_string()),
+			("rays / s", format!("{:.0}", self.rays_per_second())),
+			("intersection tests", counters.intersection_tests.to_string()),
+			("bvh node visits", counters.bvh_node_visits.to_string()),
+			("average path length", format!("{:.3}", self.average_path_length())),
+			("max depth terminations", counters.max_depth_terminations.to_string()),
+			("roulette terminations", counters.roulette_terminations.to_string()),
+			("clamped samples", counters.clamped_samples.to_string()),
+			("regularized bounces", counters.regularized_bounces.to_string()),
+		];
+
+		if let (Some(fastest), Some(slowest)) = (self.tiles.iter().min_by_key(|tile| tile.time), self.tiles.iter().max_by_key(|tile| tile.time)) {
+			let total: Duration = self.tiles.iter().map(|tile| tile.time).sum();
+			rows.push(("tiles", self.tiles.len().to_string()));
+			rows.push(("tile time min", format!("{:.3} ms", fastest.time.as_secs_f64() * 1000.0)));
+			rows.push(("tile time mean", format!("{:.3} ms", total.as_secs_f64() * 1000.0 / self.tiles.len() as f64)));
+			rows.push(("tile time max", format!("{:.3} ms at ({}, {})", slowest.time.as_secs_f64() * 1000.0, slowest.x0, slowest.y0)));
+		}
+
+		let name_width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+		let value_width = rows.iter().map(|(_, value)| value.len()).max().unwrap_or(0);
+		let line = format!("+{}+{}+\n", "-".repeat(name_width + 2), "-".repeat(value_width + 2));
+		let mut table = line.clone();
+		for (name, value) in &rows {
+			table += &format!("| {name:<name_width$} | {value:>value_width$} |\n");
+		}
+		table += &line;
+		return table;
+	}
+
+	//The same numbers as JSON, plus every tile, for keeping track of performance between versions.
+	pub fn to_json(&self) -> String {
+		let counters = &self.counters;
+		let mut json = String::from("{\n");
+		json += &format!("\t\"wall_time_seconds\": {},\n", self.wall_time.as_secs_f64());
+		json += &format!("\t\"samples\": {},\n", self.samples());
+		json += &format!("\t\"samples_per_second\": {},\n", self.samples_per_second());
+		json += &format!("\t\"primary_rays\": {},\n", counters.primary_rays);
+		json += &format!("\t\"secondary_rays\": {},\n", counters.secondary_rays);
+		json += &format!("\t\"shadow_rays\": {},\n", counters.shadow_rays);
+		json += &format!("\t\"rays_per_second\": {},\n", self.rays_per_second());
+		json += &format!("\t\"intersection_tests\": {},\n", counters.intersection_tests);
+		json += &format!("\t\"bvh_node_visits\": {},\n", counters.bvh_node_visits);
+		json += &format!("\t\"average_path_length\": {},\n", self.average_path_length());
+		json += &format!("\t\"max_depth_terminations\": {},\n", counters.max_depth_terminations);
+		json += &format!("\t\"roulette_terminations\": {},\n", counters.roulette_terminations);
+		json += &format!("\t\"clamped_samples\": {},\n", counters.clamped_samples);
+		json += &format!("\t\"regularized_bounces\": {},\n", counters.regularized_bounces);
+		json += "\t\"tiles\": [";
+		for (index, tile) in self.tiles.iter().enumerate() {
+			if index > 0 {
+				json += ",";
+			}
+			json += &format!(
+				"\n\t\t{{\"x0\": {}, \"y0\": {}, \"x1\": {}, \"y1\": {}, \"samples\": {}, \"seconds\": {}}}",
+				tile.x0, tile.y0, tile.x1, tile.y1, tile.samples, tile.time.as_secs_f64()
+			);
+		}
+		json += "\n\t]\n}\n";
+		return json;
+	}
+
+	pub fn write_json(&self, path: &str) -> std::io::Result<()> {
+		let mut file = File::create(path)?;
+		return file.write_all(self.to_json().as_bytes());
+	}
+}
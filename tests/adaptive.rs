@@ -0,0 +1,104 @@
+#![allow(clippy::needless_return)]
+//Checks adaptive sampling spends its samples where the noise is: pixels that come out the same
+//every sample stop at min_samples, noisy ones carry on to max_samples, and the sample heatmap
+//shows which is which.
+mod common;
+
+use common::{lit_world, small_camera, TempDir};
+use raytracer::*;
+use raytracer::aov::AovSample;
+use raytracer::framebuffer::PixelAccumulator;
+use raytracer::rng::Rng;
+
+use std::fs;
+use std::fs::File;
+
+const MIN_SAMPLES: i32 = 4;
+const MAX_SAMPLES: i32 = 64;
+
+//the top left corner only sees the flat background, the bottom middle sees the lit floor.
+const FLAT: (i32, i32) = (0, 0);
+const NOISY: (i32, i32) = (20, 18);
+
+fn adaptive_camera() -> Camera {
+	let mut camera = small_camera();
+	camera.background = Background::Solid(Color::new(0.3, 0.4, 0.5));
+	camera.adaptive = Some(AdaptiveSampling { min_samples: MIN_SAMPLES, max_samples: MAX_SAMPLES, tolerance: 0.001 });
+	return camera;
+}
+
+#[test]
+fn converged_needs_a_small_enough_interval() {
+	let aov = AovSample::default();
+	let mut flat = PixelAccumulator::default();
+	flat.add_sample(Color::new(0.5, 0.5, 0.5), &aov);
+	//one sample says nothing about the noise.
+	assert!(!flat.converged(0.05));
+	flat.add_sample(Color::new(0.5, 0.5, 0.5), &aov);
+	assert!(flat.converged(0.05));
+
+	let mut rng = Rng::new(1, 0);
+	let mut noisy = PixelAccumulator::default();
+	for _ in 0..16 {
+		let value = rng.random_f64_in_range(0.0, 1.0);
+		noisy.add_sample(Color::new(value, value, value), &aov);
+	}
+	assert!(!noisy.converged(0.05));
+	//the interval narrows with the square root of the sample count, this many get it within 5%.
+	for _ in 0..10_000 {
+		let value = rng.random_f64_in_range(0.0, 1.0);
+		noisy.add_sample(Color::new(value, value, value), &aov);
+	}
+	assert!(noisy.converged(0.05));
+}
+
+#[test]
+fn flat_pixels_stop_early_and_noisy_ones_carry_on() {
+	let camera = adaptive_camera();
+	let framebuffer = camera.render_framebuffer(&lit_world());
+	assert_eq!(framebuffer.sample_count(FLAT.0, FLAT.1), MIN_SAMPLES, "the background should stop at min_samples");
+	assert_eq!(framebuffer.sample_count(NOISY.0, NOISY.1), MAX_SAMPLES, "the lit floor should go on to max_samples");
+	for j in 0..framebuffer.height {
+		for i in 0..framebuffer.width {
+			let count = framebuffer.sample_count(i, j);
+			assert!((MIN_SAMPLES..=MAX_SAMPLES).contains(&count), "({i}, {j}) took {count} samples");
+		}
+	}
+
+	//with a loose tolerance the floor finishes early too, somewhere in between.
+	let mut loose = adaptive_camera();
+	loose.adaptive = Some(AdaptiveSampling { min_samples: MIN_SAMPLES, max_samples: MAX_SAMPLES, tolerance: 0.5 });
+	let count = loose.render_framebuffer(&lit_world()).sample_count(NOISY.0, NOISY.1);
+	assert!(count < MAX_SAMPLES, "the floor took all {count} samples with a loose tolerance");
+}
+
+#[test]
+fn heatmap_shows_the_sample_counts() {
+	let camera = adaptive_camera();
+	let framebuffer = camera.render_framebuffer(&lit_world());
+	let dir = TempDir::new("heatmap_shows_the_sample_counts");
+	let path = dir.file("heatmap.ppm");
+	framebuffer.write_sample_heatmap(&mut File::create(&path).unwrap());
+
+	let text = fs::read_to_string(&path).unwrap();
+	let mut tokens = text.split_whitespace();
+	assert_eq!(tokens.next(), Some("P3"));
+	let mut number = || tokens.next().expect("the heatmap ends early").parse::<i32>().unwrap();
+	assert_eq!((number(), number(), number()), (framebuffer.width, framebuffer.height, 255));
+	let max_count = (0..framebuffer.height)
+		.flat_map(|j| (0..framebuffer.width).map(move |i| (i, j)))
+		.map(|(i, j)| framebuffer.sample_count(i, j))
+		.max()
+		.unwrap();
+	for j in 0..framebuffer.height {
+		for i in 0..framebuffer.width {
+			let level = 255 * framebuffer.sample_count(i, j) / max_count;
+			let gray = (number(), number(), number());
+			assert!((gray.0 - level).abs() <= 1 && gray.0 == gray.1 && gray.1 == gray.2, "({i}, {j}) is {gray:?}, expected {level}");
+		}
+	}
+	//the busiest pixel is white, the background a sixteenth of that.
+	let level = |(i, j): (i32, i32)| 255 * framebuffer.sample_count(i, j) / max_count;
+	assert_eq!(level(NOISY), 255);
+	assert_eq!(level(FLAT), 255 * MIN_SAMPLES / MAX_SAMPLES);
+}
@@ -0,0 +1,188 @@
+#![allow(clippy::needless_return)]
+//Checks the extra passes on one diffuse ball under the sky: depth, normals and albedo are what the
+//ball's geometry says they should be, the lighting passes add up to the image, and write_aov
+//writes them the way it says it does.
+mod common;
+
+use common::TempDir;
+use raytracer::*;
+
+use std::fs;
+use std::fs::File;
+
+const SIZE: i32 = 15;
+const VFOV: f64 = 30.0;
+const CENTER: Point = Point { x: 0.0, y: 0.0, z: -3.0 };
+const RADIUS: f64 = 1.0;
+const ALBEDO: (f64, f64, f64) = (0.8, 0.4, 0.2);
+
+fn render() -> Framebuffer {
+	let mut world = HittableList::new();
+	world.add(CENTER.x, CENTER.y, CENTER.z, RADIUS, MaterialEnum::new_lambertian(ALBEDO.0, ALBEDO.1, ALBEDO.2));
+	let mut camera = Camera::new(1.0, SIZE, 64);
+	camera.seed = 7;
+	camera.vfov = VFOV;
+	camera.initialize();
+	return camera.render_framebuffer(&world);
+}
+
+//the ray through the middle of pixel (i, j), from the camera at the origin looking down -z.
+fn pixel_direction(i: i32, j: i32) -> Vector {
+	let size = 2.0 * (VFOV.to_radians() / 2.0).tan();
+	let x = size * ((i as f64 + 0.5) / SIZE as f64 - 0.5);
+	let y = size * (0.5 - (j as f64 + 0.5) / SIZE as f64);
+	return Vector::new(x, y, -1.0).normalize();
+}
+
+//where the ray through the middle of the pixel hits the ball, if it does.
+fn hit_distance(i: i32, j: i32) -> Option<f64> {
+	let direction = pixel_direction(i, j);
+	let along = Vector::dot(&CENTER, &direction);
+	let squared = RADIUS * RADIUS - (CENTER.length_squared() - along * along);
+	if squared < 0.0 {
+		return None;
+	}
+	return Some(along - squared.sqrt());
+}
+
+//whether all of pixel (i, j) is on the ball, so every sample in it hit the ball. A sample can be
+//up to half a pixel from the middle, the neighbours' middles are a safe distance further.
+fn fully_covered(i: i32, j: i32) -> bool {
+	return (-1..=1).all(|dj| (-1..=1).all(|di| hit_distance(i + di, j + dj).is_some()));
+}
+fn fully_missed(i: i32, j: i32) -> bool {
+	return (-1..=1).all(|dj| (-1..=1).all(|di| hit_distance(i + di, j + dj).is_none()));
+}
+
+#[test]
+fn geometry_passes_match_the_ball() {
+	let framebuffer = render();
+	let mut covered = 0;
+	for j in 0..SIZE {
+		for i in 0..SIZE {
+			let pixel = framebuffer.pixel(i, j);
+			let Some(distance) = hit_distance(i, j) else {
+				if fully_missed(i, j) {
+					assert_eq!(pixel.hit_count, 0, "({i}, {j}) is off the ball");
+					assert_eq!(pixel.depth(), f64::INFINITY);
+					assert_eq!(pixel.albedo(), Color::null_vector());
+				}
+				continue;
+			};
+			if !fully_covered(i, j) {
+				continue;
+			}
+			covered += 1;
+			assert_eq!(pixel.hit_count, pixel.count, "({i}, {j}) is all on the ball");
+			assert_eq!(pixel.object_id, 0);
+			//the samples are spread over the pixel, the ray through its middle only gets close.
+			assert!((pixel.depth() - distance).abs() < 0.01, "({i}, {j}) has depth {}, expected {distance}", pixel.depth());
+			let normal = (pixel_direction(i, j) * distance - CENTER) / RADIUS;
+			assert!((pixel.normal() - normal).length() < 0.02, "({i}, {j}) has normal {:?}, expected {normal:?}", pixel.normal());
+			let albedo = Color::new(ALBEDO.0, ALBEDO.1, ALBEDO.2);
+			assert!((pixel.albedo() - albedo).length() < 1e-12, "({i}, {j}) has albedo {:?}", pixel.albedo());
+		}
+	}
+	assert!(covered > 10, "only {covered} pixels are all on the ball");
+
+	//the middle pixel looks straight at the front of the ball.
+	let middle = framebuffer.pixel(SIZE / 2, SIZE / 2);
+	assert!((middle.depth() - (-CENTER.z - RADIUS)).abs() < 1e-3);
+	assert!((middle.normal() - Vector::new(0.0, 0.0, 1.0)).length() < 1e-3);
+}
+
+#[test]
+fn lighting_passes_add_up_to_the_image() {
+	let framebuffer = render();
+	for j in 0..SIZE {
+		for i in 0..SIZE {
+			let pixel = framebuffer.pixel(i, j);
+			let sum = pixel.emission() + pixel.direct() + pixel.indirect();
+			assert!((sum - pixel.color()).length() < 1e-12 * pixel.color().length().max(1.0), "({i}, {j}) adds up to {sum:?} but is {:?}", pixel.color());
+			//a ball on its own can't see itself, so nothing bounces off anything else.
+			assert_eq!(pixel.indirect(), Color::null_vector(), "({i}, {j})");
+			if pixel.hit_count == pixel.count {
+				//the ball doesn't glow, its light is all from the sky.
+				assert_eq!(pixel.emission(), Color::null_vector(), "({i}, {j})");
+				assert!(pixel.direct().length() > 0.0, "({i}, {j}) isn't lit");
+			}
+			if pixel.hit_count == 0 {
+				//the camera sees the sky straight on.
+				assert_eq!(pixel.direct(), Color::null_vector(), "({i}, {j})");
+				assert!(pixel.emission().length() > 0.0, "({i}, {j}) can't see the sky");
+			}
+		}
+	}
+}
+
+//the numbers in a P3 image after its header.
+fn read_ppm(path: &str) -> Vec<i32> {
+	let text = fs::read_to_string(path).unwrap();
+	let mut tokens = text.split_whitespace();
+	assert_eq!(tokens.next(), Some("P3"));
+	let values: Vec<i32> = tokens.map(|token| token.parse().unwrap()).collect();
+	assert_eq!(&values[..3], &[SIZE, SIZE, 255]);
+	assert_eq!(values.len(), 3 + 3 * (SIZE * SIZE) as usize);
+	return values[3..].to_vec();
+}
+
+#[test]
+fn write_aov_maps_each_pass() {
+	let framebuffer = render();
+	let dir = TempDir::new("write_aov_maps_each_pass");
+	let written = |aov: Aov| -> Vec<i32> {
+		let path = dir.file(&format!("{}.ppm", aov.name()));
+		framebuffer.write_aov(aov, &mut File::create(&path).unwrap());
+		return read_ppm(&path);
+	};
+	let level = |value: f64| (255.999 * value.clamp(0.0, 1.0)) as i32;
+	let gamma = |value: f64| (255.999 * value.sqrt().clamp(0.0, 0.999)) as i32;
+	let rgb = |values: &[i32], i: i32, j: i32| {
+		let at = 3 * (j * SIZE + i) as usize;
+		return (values[at], values[at + 1], values[at + 2]);
+	};
+
+	let max_depth = (0..SIZE)
+		.flat_map(|j| (0..SIZE).map(move |i| (i, j)))
+		.map(|(i, j)| framebuffer.pixel(i, j).depth())
+		.filter(|depth| depth.is_finite())
+		.fold(0.0, f64::max);
+	let (depth, normal, albedo) = (written(Aov::Depth), written(Aov::Normal), written(Aov::Albedo));
+	let (emission, direct, indirect) = (written(Aov::Emission), written(Aov::Direct), written(Aov::Indirect));
+	let (object_id, sample_count) = (written(Aov::ObjectId), written(Aov::SampleCount));
+	for j in 0..SIZE {
+		for i in 0..SIZE {
+			let pixel = framebuffer.pixel(i, j);
+			if pixel.hit_count == 0 {
+				//nothing hit, so nothing to show but the sky it saw.
+				for values in [&depth, &normal, &albedo, &direct, &indirect, &object_id] {
+					assert_eq!(rgb(values, i, j), (0, 0, 0), "({i}, {j})");
+				}
+				let sky = pixel.emission();
+				assert_eq!(rgb(&emission, i, j), (gamma(sky.x), gamma(sky.y), gamma(sky.z)), "({i}, {j})");
+			} else {
+				//depth is linear and the farthest hit is white.
+				let gray = level(pixel.depth() / max_depth);
+				assert_eq!(rgb(&depth, i, j), (gray, gray, gray), "({i}, {j})");
+				let n = pixel.normal();
+				assert_eq!(rgb(&normal, i, j), (level(0.5 * (n.x + 1.0)), level(0.5 * (n.y + 1.0)), level(0.5 * (n.z + 1.0))), "({i}, {j})");
+				//albedo and the lighting get the same gamma as the image.
+				let a = pixel.albedo();
+				assert_eq!(rgb(&albedo, i, j), (gamma(a.x), gamma(a.y), gamma(a.z)), "({i}, {j})");
+				let d = pixel.direct();
+				assert_eq!(rgb(&direct, i, j), (gamma(d.x), gamma(d.y), gamma(d.z)), "({i}, {j})");
+				assert_ne!(rgb(&object_id, i, j), (0, 0, 0), "({i}, {j})");
+			}
+			assert_eq!(rgb(&sample_count, i, j), (255, 255, 255), "every pixel took all its samples");
+		}
+	}
+	//the ball's front is the nearest point, and it faces the camera.
+	let nearest = (0..SIZE)
+		.flat_map(|j| (0..SIZE).map(move |i| (i, j)))
+		.filter(|(i, j)| framebuffer.pixel(*i, *j).hit_count > 0)
+		.map(|(i, j)| rgb(&depth, i, j).0)
+		.min();
+	assert_eq!(nearest, Some(rgb(&depth, SIZE / 2, SIZE / 2).0));
+	let (x, y, z) = rgb(&normal, SIZE / 2, SIZE / 2);
+	assert!((127..=128).contains(&x) && (127..=128).contains(&y) && z == 255, "the front faces ({x}, {y}, {z})");
+}
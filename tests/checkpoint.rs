@@ -0,0 +1,74 @@
+#![allow(clippy::needless_return)]
+//Checks that a render stopped part way and resumed from its checkpoint ends up bit for bit the
+//same as one that ran straight through, and that a checkpoint from other settings is refused.
+mod common;
+
+use common::{assert_identical, lit_world, TempDir};
+use raytracer::*;
+
+use std::io::ErrorKind;
+
+const SAMPLES_PER_PASS: i32 = 2;
+
+//enough samples for a few passes.
+fn small_camera() -> Camera {
+	let mut camera = common::small_camera();
+	camera.samples_per_pixel = 8;
+	return camera;
+}
+
+#[test]
+fn resumed_render_is_identical() {
+	let camera = small_camera();
+	let world = lit_world();
+	let dir = TempDir::new("resumed_render_is_identical");
+	let (image, checkpoint) = (dir.file("straight.ppm"), dir.file("straight.checkpoint"));
+	let straight = camera.render_resumable(&world, &image, &checkpoint, SAMPLES_PER_PASS).unwrap();
+
+	for stop_after in 1..4 {
+		let (image, checkpoint) = (dir.file(&format!("stopped_{stop_after}.ppm")), dir.file(&format!("stopped_{stop_after}.checkpoint")));
+		let partial = camera.render_resumable_for(&world, &image, &checkpoint, SAMPLES_PER_PASS, stop_after).unwrap();
+		assert_eq!(partial.sample_count(0, 0), stop_after * SAMPLES_PER_PASS, "should stop after pass {stop_after}");
+		let resumed = camera.render_resumable(&world, &image, &checkpoint, SAMPLES_PER_PASS).unwrap();
+		assert_identical(&resumed, &straight);
+	}
+}
+
+//one setting changed from small_camera's.
+type Change = fn(&mut Camera);
+
+#[test]
+fn checkpoints_from_other_settings_are_refused() {
+	let world = lit_world();
+	let dir = TempDir::new("checkpoints_from_other_settings_are_refused");
+	let changes: [(&str, Change); 14] = [
+		("lookfrom", |camera| camera.lookfrom = Point::new(0.0, 0.1, 0.0)),
+		("lookat", |camera| camera.lookat = Point::new(0.1, 0.0, -1.0)),
+		("vup", |camera| camera.vup = Vector::new(0.1, 1.0, 0.0)),
+		("vfov", |camera| camera.vfov = 60.0),
+		("defocus_angle", |camera| camera.defocus_angle = 2.0),
+		("focus_dist", |camera| camera.focus_dist = 3.0),
+		("background", |camera| camera.background = Background::Solid(Color::new(0.1, 0.1, 0.1))),
+		("filter", |camera| camera.filter = Filter::new_mitchell(2.0)),
+		("adaptive", |camera| camera.adaptive = Some(AdaptiveSampling { min_samples: 2, max_samples: 8, tolerance: 0.05 })),
+		("max_depth", |camera| camera.max_depth = 3),
+		("depth_limits", |camera| camera.depth_limits.diffuse = 2),
+		("russian_roulette", |camera| camera.russian_roulette = Some(RussianRoulette { min_depth: 1 })),
+		("firefly_clamp", |camera| camera.firefly_clamp = Some(10.0)),
+		("path_regularization", |camera| camera.path_regularization = Some(PathRegularization { angle: 5.0 })),
+	];
+	for (name, change) in changes {
+		let (image, checkpoint) = (dir.file(&format!("{name}.ppm")), dir.file(&format!("{name}.checkpoint")));
+		let camera = small_camera();
+		camera.render_resumable_for(&world, &image, &checkpoint, SAMPLES_PER_PASS, 1).unwrap();
+
+		let mut changed = small_camera();
+		change(&mut changed);
+		match changed.render_resumable(&world, &image, &checkpoint, SAMPLES_PER_PASS) {
+			Ok(_) => panic!("{name}: a checkpoint with a different {name} was carried on"),
+			Err(error) => assert_eq!(error.kind(), ErrorKind::InvalidInput, "{name}"),
+		}
+		//the same settings still carry on.
+		camera.render_resumable(&world, &image, &checkpoint, SAMPLES_PER_PASS).unwrap();
+	}
+}
@@ -0,0 +1,85 @@
+//Helpers the integration tests share, each test crate only uses some of them.
+#![allow(dead_code)]
+use raytracer::*;
+use raytracer::framebuffer::PixelAccumulator;
+use raytracer::rng::Rng;
+
+use std::fs;
+use std::path::PathBuf;
+
+//a point anywhere in the cube from -extent to extent on every axis.
+pub fn random_point(rng: &mut Rng, extent: f64) -> Point {
+	return Point::new(
+		rng.random_f64_in_range(-extent, extent),
+		rng.random_f64_in_range(-extent, extent),
+		rng.random_f64_in_range(-extent, extent),
+	);
+}
+
+//with a little slack, a hit right where the shape touches its box can round to just outside.
+pub fn in_box(bbox: &aabb::Aabb, p: &Point) -> bool {
+	return bbox.x.expand(1e-9).contains(p.x) && bbox.y.expand(1e-9).contains(p.y) && bbox.z.expand(1e-9).contains(p.z);
+}
+
+//a small, quick render of lit_world.
+pub fn small_camera() -> Camera {
+	let mut camera = Camera::new(2.0, 40, 4);
+	camera.seed = 5;
+	camera.max_depth = 6;
+	return camera;
+}
+
+//two diffuse balls, one of them the floor, under a ball of light.
+pub fn lit_world() -> HittableList {
+	let mut world = HittableList::new();
+	world.add(0.0, 0.0, -1.0, 0.5, MaterialEnum::new_lambertian(0.5, 0.5, 0.5));
+	world.add(0.0, -100.5, -1.0, 100.0, MaterialEnum::new_lambertian(0.8, 0.8, 0.8));
+	world.add(0.0, 2.0, -1.0, 0.5, MaterialEnum::new_diffuse_light(4.0, 4.0, 4.0));
+	return world;
+}
+
+//A directory of its own in the temp dir for one test, so tests running at the same time don't
+//share files. It's deleted with everything in it when this goes out of scope.
+pub struct TempDir {
+	pub path: PathBuf,
+}
+
+impl TempDir {
+	pub fn new(name: &str) -> TempDir {
+		let path = std::env::temp_dir().join(format!("raytracer_{}_{name}", std::process::id()));
+		let _ = fs::remove_dir_all(&path);
+		fs::create_dir_all(&path).unwrap();
+		return TempDir { path };
+	}
+
+	//the path of a file in the directory, as a string for the functions that take one.
+	pub fn file(&self, name: &str) -> String {
+		return self.path.join(name).to_string_lossy().into_owned();
+	}
+}
+
+impl Drop for TempDir {
+	fn drop(&mut self) {
+		let _ = fs::remove_dir_all(&self.path);
+	}
+}
+
+//every number a pixel has accumulated, as bits so two renders can be compared exactly.
+pub fn bits(pixel: &PixelAccumulator) -> Vec<u64> {
+	let mut bits: Vec<u64> = Vec::new();
+	for color in [pixel.weighted_sum, pixel.emission_sum, pixel.direct_sum, pixel.indirect_sum, pixel.normal_sum, pixel.albedo_sum] {
+		bits.extend([color.x.to_bits(), color.y.to_bits(), color.z.to_bits()]);
+	}
+	bits.extend([pixel.weight_sum.to_bits(), pixel.mean.to_bits(), pixel.m2.to_bits(), pixel.depth_sum.to_bits()]);
+	bits.extend([pixel.count as u64, pixel.hit_count as u64, pixel.object_id as u64, pixel.material_id as u64]);
+	return bits;
+}
+
+pub fn assert_identical(found: &Framebuffer, expected: &Framebuffer) {
+	assert_eq!((found.width, found.height), (expected.width, expected.height));
+	for j in 0..expected.height {
+		for i in 0..expected.width {
+			assert_eq!(bits(&found.pixel(i, j)), bits(&expected.pixel(i, j)), "pixel ({i}, {j}) differs");
+		}
+	}
+}
@@ -0,0 +1,110 @@
+#![allow(clippy::needless_return)]
+//Checks csg solids against what they're made of: every hit has to be where the ray goes
+//into or out of the combined solid, with the normal facing the ray and the right material.
+mod common;
+
+use common::random_point;
+use raytracer::*;
+use raytracer::csg::CsgOperation;
+use raytracer::hittable::{new_box, Span};
+use raytracer::rng::Rng;
+
+fn inside(center: &Point, radius: f64, point: &Point) -> bool {
+	return (*point - *center).length() < radius;
+}
+
+#[test]
+fn hits_are_on_the_boundary_of_the_result() {
+	let a_center = Point::new(-0.4, 0.0, 0.0);
+	let b_center = Point::new(0.5, 0.2, 0.0);
+	let a_material = MaterialEnum::new_lambertian(0.8, 0.1, 0.1);
+	let b_material = MaterialEnum::new_lambertian(0.1, 0.1, 0.8);
+
+	for operation in [CsgOperation::Union, CsgOperation::Intersection, CsgOperation::Difference] {
+		let csg = Csg::new(operation, Box::new(Sphere::new(a_center, 1.0, a_material)), Box::new(Sphere::new(b_center, 0.8, b_material)));
+		let contains = |point: &Point| {
+			let in_a = inside(&a_center, 1.0, point);
+			let in_b = inside(&b_center, 0.8, point);
+			match operation {
+				CsgOperation::Union => return in_a || in_b,
+				CsgOperation::Intersection => return in_a && in_b,
+				CsgOperation::Difference => return in_a && !in_b,
+			}
+		};
+
+		let mut rng = Rng::new(1, 0);
+		let mut hits = 0;
+		for _ in 0..20_000 {
+			//aimed somewhere near the middle, so enough of them hit the small intersection.
+			let origin = random_point(&mut rng, 3.0);
+			let ray = Ray::new(origin, (random_point(&mut rng, 0.6) - origin).normalize());
+			let mut rec = HitRecord::default();
+			if !csg.hit(&ray, Interval::new(0.001, f64::INFINITY), &mut rec) {
+				continue;
+			}
+			hits += 1;
+			let before = contains(&ray.at(rec.t - 1e-6));
+			let after = contains(&ray.at(rec.t + 1e-6));
+			assert_ne!(before, after, "{operation:?}: hit at t {} isn't on the boundary", rec.t);
+			assert!(Vector::dot(&rec.normal, &ray.dir) <= 0.0, "{operation:?}: normal faces away from the ray");
+			assert_eq!(rec.front_face, after, "{operation:?}: front_face should mean the ray goes in");
+
+			//the point is on one of the spheres, and takes that sphere's material.
+			let on_a = ((rec.hit_point - a_center).length() - 1.0).abs() < 1e-6;
+			let expected = if on_a { a_material.id() } else { b_material.id() };
+			assert_eq!(rec.material.id(), expected, "{operation:?}: wrong material");
+		}
+		assert!(hits > 1000, "{operation:?}: too few hits to say much: {hits}");
+	}
+}
+
+//a sphere drilled out of a sphere, seen from the side the hole is on.
+#[test]
+fn difference_shows_the_inside_of_the_cut() {
+	let a_material = MaterialEnum::new_lambertian(0.8, 0.8, 0.8);
+	let b_material = MaterialEnum::new_metal(0.9, 0.9, 0.9, 0.0);
+	let csg = Csg::difference(
+		Box::new(Sphere::new(Point::null_vector(), 1.0, a_material)),
+		Box::new(Sphere::new(Point::new(1.0, 0.0, 0.0), 1.0, b_material)),
+	);
+	let ray = Ray::new(Point::new(5.0, 0.0, 0.0), Vector::new(-1.0, 0.0, 0.0));
+	let mut rec = HitRecord::default();
+	assert!(csg.hit(&ray, Interval::new(0.001, f64::INFINITY), &mut rec));
+	assert!((rec.t - 5.0).abs() < 1e-9, "should hit the bottom of the cut at x = 0, got t {}", rec.t);
+	assert!(rec.front_face);
+	assert!((rec.normal - Vector::new(1.0, 0.0, 0.0)).length() < 1e-9);
+	assert_eq!(rec.material.id(), b_material.id());
+
+	//and only what's left of a from the other side.
+	let mut spans: Vec<Span> = Vec::new();
+	csg.spans(&ray, &mut spans);
+	assert_eq!(spans.len(), 1);
+	assert!((spans[0].exit.t - 6.0).abs() < 1e-9);
+	assert!((spans[0].exit.outward_normal() - Vector::new(-1.0, 0.0, 0.0)).length() < 1e-9);
+}
+
+#[test]
+fn boxes_report_their_span() {
+	let material = MaterialEnum::new_lambertian(0.5, 0.5, 0.5);
+	let cube = new_box(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0), material);
+	let ray = Ray::new(Point::new(0.2, 0.3, 0.0), Vector::new(0.0, 0.0, 1.0));
+	let mut spans: Vec<Span> = Vec::new();
+	cube.spans(&ray, &mut spans);
+	assert_eq!(spans.len(), 1);
+	//the origin is inside, the span starts behind it.
+	assert!((spans[0].enter.t + 1.0).abs() < 1e-9);
+	assert!((spans[0].exit.t - 1.0).abs() < 1e-9);
+	assert!((spans[0].enter.outward_normal() - Vector::new(0.0, 0.0, -1.0)).length() < 1e-9);
+
+	//a box with a ball cut out of the middle has two spans along the same ray.
+	let hollow = Csg::difference(Box::new(cube), Box::new(Sphere::new(Point::null_vector(), 0.5, material)));
+	let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+	spans.clear();
+	hollow.spans(&ray, &mut spans);
+	let boundaries: Vec<f64> = spans.iter().flat_map(|span| [span.enter.t, span.exit.t]).collect();
+	let expected = [4.0, 4.5, 5.5, 6.0];
+	assert_eq!(boundaries.len(), expected.len());
+	for (found, expected) in boundaries.iter().zip(expected) {
+		assert!((found - expected).abs() < 1e-9, "boundaries {boundaries:?}");
+	}
+}
@@ -0,0 +1,120 @@
+#![allow(clippy::needless_return)]
+//Checks the denoiser on made up framebuffers: a clean image comes through as it was, edges in
+//the albedo and normals stay sharp, and noise on a flat surface gets smoothed away.
+use raytracer::*;
+use raytracer::aov::AovSample;
+use raytracer::framebuffer::PixelAccumulator;
+use raytracer::rng::Rng;
+
+const SIZE: i32 = 24;
+
+//a pixel that took the given gray samples, all of them hitting a surface with this albedo and normal.
+fn pixel(samples: &[f64], albedo: f64, normal: Vector) -> PixelAccumulator {
+	let mut pixel = PixelAccumulator::default();
+	for sample in samples {
+		let color = Color::new(*sample, *sample, *sample);
+		let aov = AovSample {
+			hit: true,
+			depth: 2.0,
+			normal,
+			albedo: Color::new(albedo, albedo, albedo),
+			object_id: 0,
+			material_id: 0,
+			..AovSample::default()
+		};
+		pixel.add_sample(color, &aov);
+		pixel.splat(color, &aov, 1.0);
+	}
+	return pixel;
+}
+
+//fills a framebuffer, make gives each pixel's samples, albedo and normal.
+fn framebuffer(mut make: impl FnMut(i32, i32) -> PixelAccumulator) -> Framebuffer {
+	let mut framebuffer = Framebuffer::new(SIZE, SIZE);
+	for j in 0..SIZE {
+		for i in 0..SIZE {
+			framebuffer.set_pixel(i, j, make(i, j));
+		}
+	}
+	return framebuffer;
+}
+
+//noisy samples around mean, the same spread at every brightness.
+fn noisy(rng: &mut Rng, mean: f64) -> Vec<f64> {
+	return (0..4).map(|_| mean * (1.0 + rng.random_f64_in_range(-0.5, 0.5))).collect();
+}
+
+#[test]
+fn clean_images_pass_through() {
+	let up = Vector::new(0.0, 1.0, 0.0);
+	let input = framebuffer(|i, j| pixel(&[0.4; 8], if (i / 6 + j / 6) % 2 == 0 { 0.8 } else { 0.3 }, up));
+	let output = Denoiser::default().denoise(&input);
+	for j in 0..SIZE {
+		for i in 0..SIZE {
+			let (found, expected) = (output.get(i, j), input.get(i, j));
+			assert!((found - expected).length() < 1e-12, "({i}, {j}) went from {expected:?} to {found:?}");
+		}
+	}
+}
+
+#[test]
+fn edges_stay_sharp() {
+	let mut rng = Rng::new(1, 0);
+	let up = Vector::new(0.0, 1.0, 0.0);
+	let side = Vector::new(1.0, 0.0, 0.0);
+	//the left half is bright paint, the right half dark. Lighting is the same on both.
+	let albedo_edge = framebuffer(|i, _| {
+		let albedo = if i < SIZE / 2 { 0.8 } else { 0.2 };
+		return pixel(&noisy(&mut rng, albedo), albedo, up);
+	});
+	//the same paint, but the right half faces away from the light and gets a third of it.
+	let normal_edge = framebuffer(|i, _| {
+		let (light, normal) = if i < SIZE / 2 { (0.6, up) } else { (0.2, side) };
+		return pixel(&noisy(&mut rng, light), 0.5, normal);
+	});
+	for (name, input, left, right) in [("albedo", albedo_edge, 0.8, 0.2), ("normal", normal_edge, 0.6, 0.2)] {
+		let output = Denoiser::default().denoise(&input);
+		for j in 0..SIZE {
+			//right next to the edge, a blurred edge would be half way between the two sides.
+			let (near_left, near_right) = (output.get(SIZE / 2 - 1, j).x, output.get(SIZE / 2, j).x);
+			assert!((near_left - left).abs() < 0.15 * left, "{name}: the left of the edge went from {left} to {near_left}");
+			assert!((near_right - right).abs() < 0.15 * right, "{name}: the right of the edge went from {right} to {near_right}");
+		}
+	}
+}
+
+//the spread of the pixel colors around what they should be.
+fn variance(framebuffer: &Framebuffer, expected: f64) -> f64 {
+	let mut sum = 0.0;
+	for j in 0..SIZE {
+		for i in 0..SIZE {
+			sum += (framebuffer.get(i, j).x - expected).powi(2);
+		}
+	}
+	return sum / (SIZE * SIZE) as f64;
+}
+
+#[test]
+fn noise_goes_down() {
+	let mut rng = Rng::new(2, 0);
+	let up = Vector::new(0.0, 1.0, 0.0);
+	let input = framebuffer(|_, _| pixel(&noisy(&mut rng, 0.5), 0.7, up));
+	let output = Denoiser::default().denoise(&input);
+	let (before, after) = (variance(&input, 0.5), variance(&output, 0.5));
+	assert!(after < 0.25 * before, "the variance only went from {before} to {after}");
+}
+
+//the variance of a pixel's mean multiplies its sample count by itself, which doesn't fit an i32
+//for long renders.
+#[test]
+fn pixels_with_many_samples_are_filtered() {
+	let up = Vector::new(0.0, 1.0, 0.0);
+	let input = framebuffer(|_, _| {
+		let mut pixel = pixel(&[0.5, 0.5], 0.7, up);
+		pixel.count = 100_000;
+		pixel.m2 = 0.01 * pixel.count as f64;
+		return pixel;
+	});
+	let output = Denoiser::default().denoise(&input);
+	assert!((output.get(3, 3).x - 0.5).abs() < 1e-9);
+}
@@ -0,0 +1,311 @@
+#![allow(clippy::needless_return)]
+//Checks the EXR writer by reading its files back the way a reader would: the magic and version,
+//the header attributes, then every block inflated or un-RLEd, the predictor and byte interleave
+//undone, and the pixels compared bit for bit with what went in.
+use raytracer::exr::{f32_to_half, ExrAttribute, ExrCompression, ExrImage, ExrPixelType};
+use raytracer::rng::Rng;
+
+use std::fs;
+
+const WIDTH: usize = 7;
+//more than one zip block, and the last one not full.
+const HEIGHT: usize = 21;
+
+//a smooth ramp with a noisy patch and a flat run, so the compressors get repeats and literals,
+//and a few values that don't fit a half.
+fn values(seed: u64) -> Vec<f64> {
+	let mut rng = Rng::new(seed, 0);
+	let mut values = Vec::new();
+	for y in 0..HEIGHT {
+		for x in 0..WIDTH {
+			let value = match y {
+				0..=5 => (x + y * WIDTH) as f64 / 40.0,
+				6..=11 => rng.random_f64_in_range(-4.0, 4.0),
+				12..=16 => 0.25,
+				_ => [1e-7, -0.0, 70000.0, 1e30, 1.0 / 3.0, 1e-5, 2.5][x],
+			};
+			values.push(value);
+		}
+	}
+	return values;
+}
+
+fn small_image(compression: ExrCompression) -> ExrImage {
+	let mut image = ExrImage::new(WIDTH as i32, HEIGHT as i32, compression);
+	//added out of order, the file has them sorted by name.
+	image.add_channel("R", ExrPixelType::Half, values(1));
+	image.add_channel("G", ExrPixelType::Float, values(2));
+	image.add_channel("id", ExrPixelType::Uint, values(3).iter().map(|value| (value.abs() * 100.0).floor()).collect());
+	image.add_channel("A", ExrPixelType::Half, values(4));
+	image.add_attribute("samplesPerPixel", ExrAttribute::Int(64));
+	image.add_attribute("sampler", ExrAttribute::String("Sobol".to_string()));
+	return image;
+}
+
+//the bits of one channel's pixels the way the file should hold them, the pixel type's id in the
+//channel list and how many bytes each pixel takes.
+fn expected_bits(image: &ExrImage, name: &str) -> (Vec<u32>, i32, usize) {
+	let channel = image.channels.iter().find(|channel| channel.name == name).unwrap_or_else(|| panic!("no channel {name} was added"));
+	return match channel.pixel_type {
+		ExrPixelType::Uint => (channel.values.iter().map(|value| *value as u32).collect(), 0, 4),
+		ExrPixelType::Half => (channel.values.iter().map(|value| f32_to_half(*value as f32) as u32).collect(), 1, 2),
+		ExrPixelType::Float => (channel.values.iter().map(|value| (*value as f32).to_bits()).collect(), 2, 4),
+	};
+}
+
+struct Reader<'a> {
+	bytes: &'a [u8],
+	position: usize,
+}
+
+impl Reader<'_> {
+	fn take(&mut self, count: usize) -> &[u8] {
+		let bytes = &self.bytes[self.position..self.position + count];
+		self.position += count;
+		return bytes;
+	}
+	fn i32(&mut self) -> i32 {
+		return i32::from_le_bytes(self.take(4).try_into().unwrap());
+	}
+	fn string(&mut self) -> String {
+		let end = self.bytes[self.position..].iter().position(|byte| *byte == 0).unwrap();
+		let string = String::from_utf8(self.take(end).to_vec()).unwrap();
+		self.position += 1;
+		return string;
+	}
+}
+
+//Reads deflate's bit stream, lowest bit of each byte first.
+struct BitReader<'a> {
+	bytes: &'a [u8],
+	bit: usize,
+}
+
+impl BitReader<'_> {
+	fn bits(&mut self, count: u32) -> usize {
+		let mut value = 0;
+		for i in 0..count {
+			let byte = self.bytes[self.bit / 8];
+			value |= (((byte >> (self.bit % 8)) & 1) as usize) << i;
+			self.bit += 1;
+		}
+		return value;
+	}
+	//Huffman codes go from the highest bit down.
+	fn code(&mut self, count: u32) -> usize {
+		let mut code = 0;
+		for _ in 0..count {
+			code = code << 1 | self.bits(1);
+		}
+		return code;
+	}
+	//a symbol of the fixed literal/length code.
+	fn literal(&mut self) -> usize {
+		let code = self.code(7);
+		if code <= 0x17 {
+			return 256 + code;
+		}
+		let code = code << 1 | self.bits(1);
+		if (0x30..=0xbf).contains(&code) {
+			return code - 0x30;
+		}
+		if (0xc0..=0xc7).contains(&code) {
+			return 280 + code - 0xc0;
+		}
+		return 144 + (code << 1 | self.bits(1)) - 0x190;
+	}
+}
+
+const LENGTH_BASE: [usize; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u32; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DISTANCE_BASE: [usize; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+const DISTANCE_EXTRA: [u32; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+//Only what the writer makes: one final block with the fixed codes, checked against its adler32.
+fn zlib_decompress(data: &[u8]) -> Vec<u8> {
+	assert_eq!((data[0] as u32 * 256 + data[1] as u32) % 31, 0, "bad zlib header check");
+	assert_eq!(data[0] & 0x0f, 8, "not deflate");
+	let mut reader = BitReader { bytes: &data[2..], bit: 0 };
+	assert_eq!(reader.bits(1), 1, "not the last block");
+	assert_eq!(reader.bits(2), 1, "not fixed codes");
+	let mut out: Vec<u8> = Vec::new();
+	loop {
+		let symbol = reader.literal();
+		if symbol < 256 {
+			out.push(symbol as u8);
+			continue;
+		}
+		if symbol == 256 {
+			break;
+		}
+		let length = LENGTH_BASE[symbol - 257] + reader.bits(LENGTH_EXTRA[symbol - 257]);
+		let code = reader.code(5);
+		let distance = DISTANCE_BASE[code] + reader.bits(DISTANCE_EXTRA[code]);
+		assert!(distance <= out.len(), "distance {distance} goes back before the start");
+		for _ in 0..length {
+			out.push(out[out.len() - distance]);
+		}
+	}
+	let end = 2 + reader.bit.div_ceil(8);
+	assert_eq!(data.len(), end + 4, "bytes left after the stream");
+	let (mut a, mut b) = (1u32, 0u32);
+	for byte in &out {
+		a = (a + *byte as u32) % 65521;
+		b = (b + a) % 65521;
+	}
+	assert_eq!(u32::from_be_bytes(data[end..].try_into().unwrap()), b << 16 | a, "adler32 doesn't match");
+	return out;
+}
+
+fn rle_decompress(data: &[u8]) -> Vec<u8> {
+	let mut out: Vec<u8> = Vec::new();
+	let mut i = 0;
+	while i < data.len() {
+		let count = data[i] as i8;
+		if count < 0 {
+			let length = -(count as i32) as usize;
+			out.extend_from_slice(&data[i + 1..i + 1 + length]);
+			i += 1 + length;
+		} else {
+			out.extend(std::iter::repeat_n(data[i + 1], count as usize + 1));
+			i += 2;
+		}
+	}
+	return out;
+}
+
+//undoes the predictor, then puts the two halves back into alternating bytes.
+fn unpredict_and_deinterleave(mut bytes: Vec<u8>) -> Vec<u8> {
+	for i in 1..bytes.len() {
+		bytes[i] = bytes[i - 1].wrapping_add(bytes[i]).wrapping_sub(128);
+	}
+	let half = bytes.len().div_ceil(2);
+	let mut out: Vec<u8> = Vec::with_capacity(bytes.len());
+	for i in 0..half {
+		out.push(bytes[i]);
+		if half + i < bytes.len() {
+			out.push(bytes[half + i]);
+		}
+	}
+	return out;
+}
+
+//Reads a file back and checks it holds image, compressed the way the header says.
+fn check_file(bytes: &[u8], image: &ExrImage, compression_id: u8) {
+	let mut reader = Reader { bytes, position: 0 };
+	assert_eq!(reader.take(4), [0x76, 0x2f, 0x31, 0x01], "wrong magic");
+	//version 2, scanline, short names.
+	assert_eq!(reader.i32(), 2, "wrong version");
+
+	let mut attributes: Vec<(String, String, Vec<u8>)> = Vec::new();
+	loop {
+		let name = reader.string();
+		if name.is_empty() {
+			break;
+		}
+		let type_name = reader.string();
+		let size = reader.i32() as usize;
+		attributes.push((name, type_name, reader.take(size).to_vec()));
+	}
+	let attribute = |name: &str, type_name: &str| -> Vec<u8> {
+		let (_, found_type, value) = attributes.iter().find(|(found, _, _)| found == name).unwrap_or_else(|| panic!("no {name} attribute"));
+		assert_eq!(found_type, type_name, "{name} has the wrong type");
+		return value.clone();
+	};
+	let window: Vec<i32> = attribute("dataWindow", "box2i").chunks(4).map(|bytes| i32::from_le_bytes(bytes.try_into().unwrap())).collect();
+	assert_eq!(window, [0, 0, WIDTH as i32 - 1, HEIGHT as i32 - 1]);
+	assert_eq!(attribute("displayWindow", "box2i"), attribute("dataWindow", "box2i"));
+	assert_eq!(attribute("compression", "compression"), [compression_id]);
+	assert_eq!(attribute("lineOrder", "lineOrder"), [0]);
+	assert_eq!(attribute("pixelAspectRatio", "float"), 1.0f32.to_le_bytes());
+	assert_eq!(attribute("samplesPerPixel", "int"), 64i32.to_le_bytes());
+	assert_eq!(attribute("sampler", "string"), b"Sobol");
+
+	//the channel list: name, pixel type, pLinear and reserved bytes, x and y sampling.
+	let list = attribute("channels", "chlist");
+	let mut list_reader = Reader { bytes: &list, position: 0 };
+	let mut channels: Vec<(String, Vec<u32>, usize)> = Vec::new();
+	loop {
+		let name = list_reader.string();
+		if name.is_empty() {
+			break;
+		}
+		let pixel_type = list_reader.i32();
+		assert_eq!(list_reader.take(4), [0, 0, 0, 0]);
+		assert_eq!((list_reader.i32(), list_reader.i32()), (1, 1), "{name} is subsampled");
+		let (bits, expected_type, size) = expected_bits(image, &name);
+		assert_eq!(pixel_type, expected_type, "{name} has the wrong pixel type");
+		channels.push((name, bits, size));
+	}
+	assert_eq!(list_reader.position, list.len(), "bytes left after the channel list");
+	let names: Vec<&str> = channels.iter().map(|(name, _, _)| name.as_str()).collect();
+	assert_eq!(names, ["A", "G", "R", "id"], "channels should be sorted by name");
+
+	let lines_per_block = if compression_id == 3 { 16 } else { 1 };
+	let line_bytes: usize = channels.iter().map(|(_, _, size)| WIDTH * size).sum();
+	let block_count = HEIGHT.div_ceil(lines_per_block);
+	let offsets: Vec<usize> = (0..block_count).map(|_| u64::from_le_bytes(reader.take(8).try_into().unwrap()) as usize).collect();
+	let mut compressed_blocks = 0;
+	for (block, offset) in offsets.into_iter().enumerate() {
+		let mut block_reader = Reader { bytes, position: offset };
+		let y0 = block_reader.i32() as usize;
+		assert_eq!(y0, block * lines_per_block, "block {block} starts on the wrong line");
+		let lines = lines_per_block.min(HEIGHT - y0);
+		let size = block_reader.i32() as usize;
+		let data = block_reader.take(size).to_vec();
+		//a block no smaller than the raw pixels is stored raw.
+		let raw = if size == lines * line_bytes {
+			data
+		} else {
+			compressed_blocks += 1;
+			match compression_id {
+				1 => unpredict_and_deinterleave(rle_decompress(&data)),
+				2 | 3 => unpredict_and_deinterleave(zlib_decompress(&data)),
+				_ => panic!("block {block} has {size} bytes, expected {}", lines * line_bytes),
+			}
+		};
+		assert_eq!(raw.len(), lines * line_bytes, "block {block} decodes to the wrong size");
+
+		let mut pixels = Reader { bytes: &raw, position: 0 };
+		for y in y0..y0 + lines {
+			for (name, bits, size) in &channels {
+				for x in 0..WIDTH {
+					let value = pixels.take(*size);
+					let found = if *size == 2 { u16::from_le_bytes(value.try_into().unwrap()) as u32 } else { u32::from_le_bytes(value.try_into().unwrap()) };
+					assert_eq!(found, bits[y * WIDTH + x], "{name} at ({x}, {y}) decodes to other bits");
+				}
+			}
+		}
+	}
+	if compression_id != 0 {
+		assert!(compressed_blocks > 0, "nothing was compressed");
+	}
+}
+
+#[test]
+fn files_decode_to_the_pixels_written() {
+	for (compression, id) in [(ExrCompression::None, 0), (ExrCompression::Rle, 1), (ExrCompression::Zips, 2), (ExrCompression::Zip, 3)] {
+		let image = small_image(compression);
+		check_file(&image.encode(), &image, id);
+	}
+}
+
+#[test]
+fn written_file_is_the_encoded_image() {
+	let image = small_image(ExrCompression::Zip);
+	let path = std::env::temp_dir().join(format!("raytracer_exr_{}.exr", std::process::id()));
+	image.write(&mut fs::File::create(&path).unwrap()).unwrap();
+	let bytes = fs::read(&path).unwrap();
+	let _ = fs::remove_file(&path);
+	assert_eq!(bytes, image.encode());
+	check_file(&bytes, &image, 3);
+}
+
+#[test]
+fn halves_round_to_nearest_even() {
+	for (value, bits) in [(1.0, 0x3c00), (-2.0, 0xc000), (65504.0, 0x7bff), (65520.0, 0x7c00), (1.0 + 1.0 / 2048.0, 0x3c00), (1.0 + 3.0 / 2048.0, 0x3c02), (5.960_464_5e-8, 0x0001), (2.0e-8, 0x0000)] {
+		assert_eq!(f32_to_half(value), bits, "{value}");
+	}
+	assert_eq!(f32_to_half(f32::INFINITY), 0x7c00);
+	assert_eq!(f32_to_half(f32::NAN) & 0x7e00, 0x7e00);
+}
@@ -0,0 +1,93 @@
+#![allow(clippy::needless_return)]
+//Checks the reconstruction filters: every pixel gets the same total weight from an even spread
+//of samples, samples on pixel edges aren't lost or counted twice, and the filters reach zero at
+//their radius so nothing jumps where a sample stops reaching a pixel.
+use raytracer::*;
+
+fn filters() -> [(&'static str, Filter); 7] {
+	return [
+		("box", Filter::default()),
+		("wide box", Filter::new_box(1.5)),
+		("tent", Filter::new_tent(1.0)),
+		("gaussian", Filter::new_gaussian(1.5)),
+		("mitchell", Filter::new_mitchell(2.0)),
+		("lanczos", Filter::new_lanczos(3.0)),
+		("narrow lanczos", Filter::new_lanczos(1.0)),
+	];
+}
+
+//the total weight pixel (i, j) gets from a sample at (x, y), the way the film splats it.
+fn weight(filter: &Filter, i: i32, j: i32, x: f64, y: f64) -> f64 {
+	return filter.evaluate(i as f64 + 0.5 - x, j as f64 + 0.5 - y);
+}
+
+//Samples on a grid steps to a pixel, edges included, over a film big enough that the pixels
+//in the middle get every sample that can reach them.
+#[test]
+fn every_pixel_gets_the_same_weight() {
+	let steps = 8;
+	let size = 12;
+	for (name, filter) in filters() {
+		let mut totals = Vec::new();
+		for (i, j) in [(5, 5), (6, 5), (5, 6), (6, 7)] {
+			let mut total = 0.0;
+			for sy in 0..size * steps {
+				for sx in 0..size * steps {
+					total += weight(&filter, i, j, sx as f64 / steps as f64, sy as f64 / steps as f64);
+				}
+			}
+			totals.push(total);
+		}
+		for total in &totals {
+			assert!((total - totals[0]).abs() < 1e-9 * totals[0].abs(), "{name}: pixel totals {totals:?}");
+		}
+		assert!(totals[0] > 0.0, "{name}: the pixels get no weight");
+	}
+}
+
+//A box of radius 0.5, a tent of radius 1 and the Mitchell filter with b + 2c = 1 share each sample
+//out with weights that add up to 1 wherever it lands, so the image has no grid pattern in it.
+#[test]
+fn each_sample_is_shared_out_in_full() {
+	let steps = 8;
+	for (name, filter) in [("box", Filter::default()), ("tent", Filter::new_tent(1.0)), ("mitchell", Filter::new_mitchell(2.0))] {
+		for sy in 0..=steps {
+			for sx in 0..=steps {
+				let (x, y) = (5.0 + sx as f64 / steps as f64, 5.0 + sy as f64 / steps as f64);
+				let mut total = 0.0;
+				for j in 0..12 {
+					for i in 0..12 {
+						total += weight(&filter, i, j, x, y);
+					}
+				}
+				assert!((total - 1.0).abs() < 1e-9, "{name}: a sample at ({x}, {y}) is shared out with total weight {total}");
+			}
+		}
+	}
+}
+
+#[test]
+fn samples_on_an_edge_count_once() {
+	let filter = Filter::default();
+	//right between pixels 4 and 5, it belongs to 5 like the rest of [5, 6).
+	assert_eq!(weight(&filter, 4, 0, 5.0, 0.5), 0.0);
+	assert_eq!(weight(&filter, 5, 0, 5.0, 0.5), 1.0);
+	assert_eq!(weight(&filter, 0, 4, 0.5, 5.0), 0.0);
+	assert_eq!(weight(&filter, 0, 5, 0.5, 5.0), 1.0);
+}
+
+#[test]
+fn filters_reach_zero_at_their_radius() {
+	for (name, filter) in [("mitchell", Filter::new_mitchell(2.0)), ("lanczos", Filter::new_lanczos(3.0)), ("narrow lanczos", Filter::new_lanczos(1.0))] {
+		let radius = match filter {
+			Filter::Mitchell { radius, .. } | Filter::Lanczos { radius, .. } => radius,
+			_ => unreachable!(),
+		};
+		for (x, y) in [(radius, 0.0), (-radius, 0.0), (0.0, radius), (radius, radius), (radius + 0.1, 0.0)] {
+			assert_eq!(filter.evaluate(x, y), 0.0, "{name}: {} at ({x}, {y})", filter.evaluate(x, y));
+		}
+		//and get there smoothly.
+		let inside = filter.evaluate(radius - 1e-6, 0.0);
+		assert!(inside.abs() < 1e-4, "{name}: {inside} just inside the radius");
+	}
+}
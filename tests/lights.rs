@@ -0,0 +1,288 @@
+#![allow(clippy::needless_return)]
+//Checks that light sampling and bsdf sampling, and the renderer that weights the two together,
+//all come out the same when one light hides part of another, and that the point, spot and sun
+//lights light a diffuse floor the way the analytic answers say they should. Lights inside groups
+//and instances have to be sampled the same as ones added on their own.
+use raytracer::*;
+use raytracer::instance::{RotateY, Translate};
+use raytracer::rng::Rng;
+use raytracer::sampler::Sampler;
+use raytracer::sampling;
+
+use std::sync::Arc;
+
+const SAMPLES: usize = 200_000;
+const ALBEDO: f64 = 0.5;
+
+//a floor with a small bright ball above it, and a bigger dimmer one straight behind that, so
+//every direction towards the small ball would also reach the big one if the small one weren't there.
+fn two_lights_in_a_row() -> HittableList {
+	let mut world = HittableList::new();
+	world.add_quad(Point::new(-50.0, 0.0, -50.0), Vector::new(0.0, 0.0, 100.0), Vector::new(100.0, 0.0, 0.0), MaterialEnum::new_lambertian(ALBEDO, ALBEDO, ALBEDO));
+	world.add(0.0, 2.0, 0.0, 1.0, MaterialEnum::new_diffuse_light(4.0, 4.0, 4.0));
+	world.add(0.0, 8.0, 0.0, 4.5, MaterialEnum::new_diffuse_light(2.0, 2.0, 2.0));
+	return world;
+}
+
+//the light leaving the floor at the origin, as seen from straight above. A ball of radius r at distance d
+//fills a cone with sin^2 of its half angle (r / d)^2, and a diffuse floor reflects albedo times the
+//radiance times that, so the big ball adds only the ring around the small one.
+fn expected() -> f64 {
+	let small = (1.0_f64 / 2.0).powi(2);
+	let big = (4.5_f64 / 8.0).powi(2);
+	return ALBEDO * (4.0 * small + 2.0 * (big - small));
+}
+
+//the emission the first thing along the ray gives off, none when it's the floor or nothing.
+fn emission_along(world: &HittableList, ray: &Ray, max: f64) -> f64 {
+	let mut rec = HitRecord::default();
+	if !world.hit(ray, Interval::new(0.001, max), &mut rec) {
+		return 0.0;
+	}
+	return rec.material.emitted(&rec).x;
+}
+
+//one pixel looking straight down at the floor under the lights, change adjusts the camera's settings.
+fn render_floor(world: &HittableList, max_depth: i32, samples: i32, change: impl Fn(&mut Camera)) -> f64 {
+	let mut camera = Camera::new(1.0, 1, samples);
+	camera.seed = 3;
+	camera.max_depth = max_depth;
+	camera.vfov = 1.0;
+	camera.background = Background::Solid(Color::null_vector());
+	camera.lookfrom = Point::new(0.0, 0.5, 0.0);
+	camera.lookat = Point::null_vector();
+	camera.vup = Vector::new(0.0, 0.0, -1.0);
+	change(&mut camera);
+	camera.initialize();
+	return camera.render_framebuffer(world).get(0, 0).x;
+}
+
+#[test]
+fn hidden_lights_are_not_counted_twice() {
+	let world = two_lights_in_a_row();
+	let origin = Point::null_vector();
+
+	//only light sampling: a point on one of the lights, which counts if nothing is in front of it.
+	let mut sampler = Sampler::new(SamplerKind::Independent, 1, 1);
+	sampler.start_pixel_sample(0, 0, 0);
+	let mut light_sampled = 0.0;
+	for _ in 0..SAMPLES {
+		let Some(sample) = world.sample_light(&origin, &mut sampler) else {
+			continue;
+		};
+		let to_light = sample.point - origin;
+		let distance = to_light.length();
+		let direction = to_light / distance;
+		if direction.y <= 0.0 {
+			continue;
+		}
+		let mut rec = HitRecord::default();
+		world.hit(&Ray::new(origin, direction), Interval::new(0.001, f64::INFINITY), &mut rec);
+		if (rec.t - distance).abs() > 1e-6 * distance {
+			continue;
+		}
+		light_sampled += rec.material.emitted(&rec).x * (ALBEDO / PI) * direction.y / sample.pdf;
+	}
+	light_sampled /= SAMPLES as f64;
+
+	//only bsdf sampling: cosine weighted directions, the cosine and pdf cancel down to the albedo.
+	let mut rng = Rng::new(2, 0);
+	let mut bsdf_sampled = 0.0;
+	for _ in 0..SAMPLES {
+		let local = sampling::cosine_hemisphere((rng.random_f64(), rng.random_f64()));
+		let direction = Vector::new(local.x, local.z, local.y);
+		bsdf_sampled += ALBEDO * emission_along(&world, &Ray::new(origin, direction), f64::INFINITY);
+	}
+	bsdf_sampled /= SAMPLES as f64;
+
+	//the renderer uses both, weighted by multiple importance sampling, looking down at the same spot.
+	let rendered = render_floor(&world, 2, 20_000, |_| {});
+
+	let expected = expected();
+	for (name, found) in [("light sampling", light_sampled), ("bsdf sampling", bsdf_sampled), ("the renderer", rendered)] {
+		assert!((found - expected).abs() < 0.02 * expected, "{name} gives {found:.4}, expected {expected:.4}");
+	}
+}
+
+//The floor is the only thing that reflects, so every path that finds a light has two segments, the one
+//to the floor and the one to the light. Any depth that allows those two has to give the same image,
+//the last hit a path is allowed can't lose the share of its light that bsdf sampling would have found.
+#[test]
+fn one_bounce_is_the_same_at_any_depth() {
+	let world = two_lights_in_a_row();
+	let expected = render_floor(&world, 2, 2_000, |_| {});
+	assert!(expected > 0.0);
+	for max_depth in 3..=6 {
+		let found = render_floor(&world, max_depth, 2_000, |_| {});
+		assert!((found - expected).abs() <= 1e-12 * expected, "max_depth {max_depth} gives {found}, max_depth 2 gives {expected}");
+	}
+	let found = render_floor(&world, 8, 2_000, |camera| camera.depth_limits.diffuse = 1);
+	assert!((found - expected).abs() <= 1e-12 * expected, "one diffuse bounce gives {found}, expected {expected}");
+	//a single segment only reaches the floor, none of the light it reflects.
+	assert_eq!(render_floor(&world, 1, 2_000, |_| {}), 0.0);
+}
+
+//PUNCTUAL LIGHTS
+
+//a plain diffuse floor through the origin, to be lit by the punctual lights alone.
+fn floor_with(light: LightEnum) -> HittableList {
+	let mut world = HittableList::new();
+	world.add_quad(Point::new(-50.0, 0.0, -50.0), Vector::new(0.0, 0.0, 100.0), Vector::new(100.0, 0.0, 0.0), MaterialEnum::new_lambertian(ALBEDO, ALBEDO, ALBEDO));
+	world.add_light(light);
+	return world;
+}
+
+//the floor at x along the x axis, seen from straight above.
+fn render_floor_at(world: &HittableList, x: f64) -> f64 {
+	return render_floor(world, 2, 16, |camera| {
+		camera.lookfrom = Point::new(x, 0.5, 0.0);
+		camera.lookat = Point::new(x, 0.0, 0.0);
+		camera.vfov = 0.1;
+	});
+}
+
+#[test]
+fn point_light_falls_off_with_the_square_of_the_distance() {
+	let intensity = 3.0;
+	let light = LightEnum::new_point(Point::new(0.0, 2.0, 0.0), Color::new(intensity, intensity, intensity));
+	let mut sampler = Sampler::new(SamplerKind::Independent, 1, 1);
+	sampler.start_pixel_sample(0, 0, 0);
+	for distance in [0.5, 1.0, 2.0, 7.0] {
+		let sample = light.sample_li(&Point::new(0.0, 2.0 - distance, 0.0), &mut sampler).unwrap();
+		assert!((sample.li.x - intensity / (distance * distance)).abs() < 1e-12, "{} at distance {distance}", sample.li.x);
+		assert!((sample.distance - distance).abs() < 1e-12);
+		assert!((sample.direction - Vector::new(0.0, 1.0, 0.0)).length() < 1e-12);
+	}
+
+	//on a diffuse floor that's albedo / pi times the intensity, times the cosine at the floor, over
+	//the distance squared. With the light h above the floor and d away that's h / d^3.
+	let world = floor_with(light);
+	for x in [0.0, 1.0, 2.5] {
+		let d = f64::sqrt(x * x + 4.0);
+		let expected = ALBEDO / PI * intensity * 2.0 / (d * d * d);
+		let found = render_floor_at(&world, x);
+		assert!((found - expected).abs() < 0.01 * expected, "{x} along the floor gives {found:.5}, expected {expected:.5}");
+	}
+}
+
+#[test]
+fn spot_light_stops_at_its_cone() {
+	let intensity = 3.0;
+	//straight down from 2 up, full strength out to 20 degrees and nothing past 30.
+	let light = LightEnum::new_spot(Point::new(0.0, 2.0, 0.0), Point::null_vector(), Color::new(intensity, intensity, intensity), 30.0, 20.0);
+	let world = floor_with(light);
+	let edge = |degrees: f64| 2.0 * degrees_to_radians(degrees).tan();
+
+	//inside the inner cone it's a point light.
+	for x in [0.0, 0.9 * edge(20.0)] {
+		let d = f64::sqrt(x * x + 4.0);
+		let expected = ALBEDO / PI * intensity * 2.0 / (d * d * d);
+		let found = render_floor_at(&world, x);
+		assert!((found - expected).abs() < 0.01 * expected, "{x} along the floor gives {found:.5}, expected {expected:.5}");
+	}
+	//in the soft edge it's somewhere between.
+	let x = edge(25.0);
+	let d = f64::sqrt(x * x + 4.0);
+	let found = render_floor_at(&world, x);
+	assert!(found > 0.0 && found < ALBEDO / PI * intensity * 2.0 / (d * d * d), "the soft edge gives {found}");
+	//and past the cone nothing at all.
+	for x in [1.02 * edge(30.0), 2.0, 10.0] {
+		assert_eq!(render_floor_at(&world, x), 0.0, "{x} along the floor is outside the cone");
+	}
+}
+
+#[test]
+fn sun_lights_the_floor_by_the_cosine() {
+	let irradiance = 2.0;
+	for (angle, diameter) in [(0.0, 0.0), (40.0, 0.0), (40.0, 5.0)] {
+		let theta = degrees_to_radians(angle);
+		let direction = Vector::new(theta.sin(), -theta.cos(), 0.0);
+		let world = floor_with(LightEnum::new_directional(direction, Color::new(irradiance, irradiance, irradiance), diameter));
+		//the same everywhere on the floor, a sun has no distance to fall off with.
+		for x in [0.0, 20.0] {
+			let expected = ALBEDO / PI * irradiance * theta.cos();
+			let found = render_floor_at(&world, x);
+			assert!((found - expected).abs() < 0.01 * expected, "sun {angle} degrees from straight up gives {found:.5}, expected {expected:.5}");
+		}
+	}
+}
+
+//LIGHTS IN GROUPS
+
+const LIGHT_SIZE: f64 = 0.2;
+
+fn light_material() -> MaterialEnum {
+	return MaterialEnum::new_diffuse_light(20.0, 20.0, 20.0);
+}
+
+//a small square light facing down at the given height.
+fn light_at(height: f64) -> Quad {
+	let corner = Point::new(-LIGHT_SIZE / 2.0, height, -LIGHT_SIZE / 2.0);
+	return Quad::new(corner, Vector::new(LIGHT_SIZE, 0.0, 0.0), Vector::new(0.0, 0.0, LIGHT_SIZE), light_material());
+}
+
+//the floor under a light that's somewhere inside light.
+fn light_floor(light: Box<dyn Hittable>) -> HittableList {
+	let mut world = HittableList::new();
+	world.add_quad(Point::new(-50.0, 0.0, -50.0), Vector::new(0.0, 0.0, 100.0), Vector::new(100.0, 0.0, 0.0), MaterialEnum::new_lambertian(ALBEDO, ALBEDO, ALBEDO));
+	world.raw_add(light);
+	return world;
+}
+
+//the same light at height 1, placed a few different ways.
+fn placed_lights() -> Vec<(&'static str, HittableList)> {
+	let up = Vector::new(0.0, 1.0, 0.0);
+	let wall = Quad::new(Point::new(10.0, 0.0, -1.0), Vector::new(0.0, 0.0, 2.0), Vector::new(0.0, 2.0, 0.0), MaterialEnum::new_lambertian(ALBEDO, ALBEDO, ALBEDO));
+	return vec![
+		("flat", light_floor(Box::new(light_at(1.0)))),
+		("bvh", light_floor(Box::new(Bvh::new(vec![Box::new(light_at(1.0))])))),
+		("translated", light_floor(Box::new(Translate::new(Arc::new(light_at(0.0)), up)))),
+		//the square turned a quarter turn is the same square.
+		("rotated", light_floor(Box::new(RotateY::new(Arc::new(light_at(1.0)), 90.0)))),
+		("translated bvh", light_floor(Box::new(Translate::new(Arc::new(Bvh::new(vec![Box::new(light_at(0.0))])), up)))),
+		//only the light gets sampled, not the wall off to the side.
+		("bvh with a wall", light_floor(Box::new(Bvh::new(vec![Box::new(wall), Box::new(light_at(1.0))])))),
+	];
+}
+
+//Emitters inside a group or an instance have to be found by light sampling like any other, and
+//sample_surface and surface_pdf have to agree on the way through.
+#[test]
+fn grouped_lights_are_sampled() {
+	let origin = Point::new(0.3, 0.0, -0.2);
+	for (name, world) in placed_lights() {
+		assert_eq!(world.light_count(), 1, "{name}: the light wasn't registered");
+		let mut sampler = Sampler::new(SamplerKind::Independent, 1, 1);
+		sampler.start_pixel_sample(0, 0, 0);
+		for _ in 0..1_000 {
+			let sample = world.sample_light(&origin, &mut sampler).expect("there's a light to sample");
+			let p = sample.point;
+			assert!((p.y - 1.0).abs() < 1e-5 && p.x.abs() <= LIGHT_SIZE / 2.0 + 1e-9 && p.z.abs() <= LIGHT_SIZE / 2.0 + 1e-9, "{name}: {p:?} isn't on the light");
+			assert!((sample.normal - Vector::new(0.0, -1.0, 0.0)).length() < 1e-9, "{name}: the light should face down");
+
+			let direction = (p - origin).normalize();
+			let mut rec = HitRecord::default();
+			assert!(world.hit(&Ray::new(origin, direction), Interval::new(0.001, f64::INFINITY), &mut rec));
+			let pdf = world.light_pdf(rec.object_id as usize, &origin, &direction);
+			assert!((pdf - sample.pdf).abs() <= 1e-6 * pdf, "{name}: light_pdf {pdf} but sampled with {}", sample.pdf);
+		}
+	}
+}
+
+//A light in a group that light sampling can't see still converges, but only through the few bsdf
+//samples that happen to hit it, so it comes out far noisier than the flat one.
+#[test]
+fn grouped_lights_converge_like_flat_ones() {
+	let worlds = placed_lights();
+	let estimates = |world: &HittableList| -> Vec<f64> {
+		return (0..8).map(|seed| render_floor(world, 2, 256, |camera| camera.seed = seed)).collect();
+	};
+	let flat = estimates(&worlds[0].1);
+	let expected = flat.iter().sum::<f64>() / flat.len() as f64;
+	assert!(expected > 0.0);
+	for (name, world) in &worlds[1..] {
+		for found in estimates(world) {
+			assert!((found - expected).abs() < 0.02 * expected, "{name}: gives {found:.5}, the flat light {expected:.5}");
+		}
+	}
+}
@@ -0,0 +1,330 @@
+#![allow(clippy::needless_return)]
+//Statistical checks that the materials are physically plausible: they never reflect more light
+//than they receive, their eval is symmetric, what they sample agrees with their eval and pdf,
+//and a sphere in a uniform white environment ("white furnace") comes out the brightness it should.
+//Every estimate uses a fixed seed, so these pass or fail the same way on every run.
+use raytracer::*;
+use raytracer::color::luminance;
+use raytracer::material::ScatterSample;
+use raytracer::sampler::Sampler;
+use raytracer::sampling;
+
+const SAMPLES: usize = 200_000;
+
+//a hit at the origin on a surface facing +z.
+fn hit_record(material: MaterialEnum) -> HitRecord {
+	return HitRecord {
+		hit_point: Point::null_vector(),
+		normal: Vector::new(0.0, 0.0, 1.0),
+		material,
+		front_face: true,
+		..Default::default()
+	};
+}
+
+//the direction back towards the viewer, theta degrees away from the normal.
+fn outgoing(theta: f64) -> Vector {
+	let theta = degrees_to_radians(theta);
+	return Vector::new(theta.sin(), 0.0, theta.cos());
+}
+
+//a ray arriving at the origin from direction wo.
+fn incoming_ray(wo: &Vector) -> Ray {
+	return Ray::new(*wo, *wo * -1.0);
+}
+
+fn sampler(seed: u64) -> Sampler {
+	let mut sampler = Sampler::new(SamplerKind::Independent, seed, 1);
+	sampler.start_pixel_sample(0, 0, 0);
+	return sampler;
+}
+
+//The mean of the values and the half width of its 99.9% confidence interval.
+struct Estimate {
+	mean: f64,
+	half_width: f64,
+}
+
+fn estimate(values: &[f64]) -> Estimate {
+	let n = values.len() as f64;
+	let mean = values.iter().sum::<f64>() / n;
+	let variance = values.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / (n - 1.0);
+	return Estimate {
+		mean,
+		half_width: 3.29 * (variance / n).sqrt(),
+	};
+}
+
+fn assert_close(name: &str, estimate: &Estimate, expected: f64) {
+	//a little slack on top of the interval for values that have no variance at all.
+	let tolerance = estimate.half_width + 1e-9;
+	assert!(
+		(estimate.mean - expected).abs() <= tolerance,
+		"{name}: estimated {:.5} +- {:.5}, expected {expected:.5}",
+		estimate.mean,
+		estimate.half_width
+	);
+}
+
+//reflectance of one channel along wo, estimated from the material's own sampling.
+//samples that are absorbed count as 0.
+fn sampled_reflectance(material: MaterialEnum, wo: &Vector, seed: u64) -> Estimate {
+	let rec = hit_record(material);
+	let ray = incoming_ray(wo);
+	let mut sampler = sampler(seed);
+	let values: Vec<f64> = (0..SAMPLES)
+		.map(|_| match material.sample(&ray, &rec, &mut sampler) {
+			Some(sample) => luminance(&sample.weight),
+			None => 0.0,
+		})
+		.collect();
+	return estimate(&values);
+}
+
+//the same reflectance, but integrating eval * cos over uniformly picked directions,
+//so it checks eval without trusting the material's sampling.
+fn integrated_reflectance(material: MaterialEnum, wo: &Vector, seed: u64) -> Estimate {
+	let rec = hit_record(material);
+	let mut sampler = sampler(seed);
+	let values: Vec<f64> = (0..SAMPLES)
+		.map(|_| {
+			let wi = sampling::uniform_sphere(sampler.get_2d());
+			let cos_theta = wi.z.abs();
+			return luminance(&material.eval(wo, &wi, &rec)) * cos_theta / sampling::uniform_sphere_pdf();
+		})
+		.collect();
+	return estimate(&values);
+}
+
+//LAMBERTIAN
+
+#[test]
+fn lambertian_reflects_its_albedo() {
+	let material = MaterialEnum::new_lambertian(0.8, 0.8, 0.8);
+	for theta in [0.0, 30.0, 60.0, 85.0] {
+		let wo = outgoing(theta);
+		assert_close(&format!("sampled at {theta}"), &sampled_reflectance(material, &wo, 1), 0.8);
+		assert_close(&format!("integrated at {theta}"), &integrated_reflectance(material, &wo, 2), 0.8);
+	}
+}
+
+#[test]
+fn lambertian_samples_agree_with_eval_and_pdf() {
+	let material = MaterialEnum::new_lambertian(0.2, 0.5, 0.8);
+	let rec = hit_record(material);
+	let wo = outgoing(40.0);
+	let ray = incoming_ray(&wo);
+	let mut sampler = sampler(3);
+
+	for _ in 0..10_000 {
+		let sample: ScatterSample = material.sample(&ray, &rec, &mut sampler).expect("lambertian always scatters");
+		let cos_theta = Vector::dot(&sample.direction, &rec.normal);
+		assert!(cos_theta >= 0.0, "sampled below the surface");
+		assert!(!sample.lobe.is_delta());
+		if sample.pdf < 1e-6 {
+			continue;
+		}
+		let pdf = material.pdf(&wo, &sample.direction, &rec);
+		assert!((pdf - sample.pdf).abs() <= 1e-9 * pdf.max(1.0), "pdf {} but sampled with {}", pdf, sample.pdf);
+		let expected: Color = material.eval(&wo, &sample.direction, &rec) * (cos_theta / sample.pdf);
+		assert!((expected - sample.weight).length() <= 1e-6, "weight doesn't match eval * cos / pdf");
+	}
+}
+
+//the cosine weighted pdf has to integrate to 1, over the hemisphere only.
+#[test]
+fn lambertian_pdf_integrates_to_one() {
+	let material = MaterialEnum::new_lambertian(0.5, 0.5, 0.5);
+	let rec = hit_record(material);
+	let wo = outgoing(20.0);
+	let mut sampler = sampler(4);
+	let values: Vec<f64> = (0..SAMPLES)
+		.map(|_| {
+			let wi = sampling::uniform_sphere(sampler.get_2d());
+			return material.pdf(&wo, &wi, &rec) / sampling::uniform_sphere_pdf();
+		})
+		.collect();
+	assert_close("pdf integral", &estimate(&values), 1.0);
+}
+
+//METAL
+
+#[test]
+fn metal_never_scatters_below_the_surface() {
+	for fuzz in [0.0, 0.3, 1.0] {
+		let material = MaterialEnum::new_metal(0.9, 0.9, 0.9, fuzz);
+		let rec = hit_record(material);
+		for theta in [0.0, 45.0, 80.0, 89.0] {
+			let wo = outgoing(theta);
+			let ray = incoming_ray(&wo);
+			let mut sampler = sampler(5);
+			for _ in 0..10_000 {
+				if let Some(sample) = material.sample(&ray, &rec, &mut sampler) {
+					assert!(Vector::dot(&sample.direction, &rec.normal) > 0.0, "fuzz {fuzz} at {theta} degrees went below the surface");
+				}
+			}
+		}
+	}
+}
+
+#[test]
+fn metal_conserves_energy() {
+	for fuzz in [0.0, 0.3, 1.0] {
+		let material = MaterialEnum::new_metal(0.9, 0.9, 0.9, fuzz);
+		for theta in [0.0, 45.0, 80.0] {
+			let reflectance = sampled_reflectance(material, &outgoing(theta), 6);
+			assert!(reflectance.mean <= 0.9 + 1e-9, "fuzz {fuzz} at {theta} degrees reflects {}", reflectance.mean);
+			if fuzz == 0.0 {
+				assert_close(&format!("mirror at {theta}"), &reflectance, 0.9);
+			}
+		}
+	}
+}
+
+//DIELECTRIC
+
+#[test]
+fn dielectric_reflects_and_transmits_everything() {
+	let material = MaterialEnum::new_dielectric(1.5);
+	for theta in [0.0, 45.0, 80.0, 89.0] {
+		assert_close(&format!("glass at {theta}"), &sampled_reflectance(material, &outgoing(theta), 7), 1.0);
+	}
+}
+
+//at normal incidence glass reflects ((1 - 1.5) / (1 + 1.5))^2 = 4% of the light.
+#[test]
+fn dielectric_fresnel_at_normal_incidence() {
+	let material = MaterialEnum::new_dielectric(1.5);
+	let rec = hit_record(material);
+	let wo = outgoing(0.0);
+	let ray = incoming_ray(&wo);
+	let mut sampler = sampler(8);
+	let values: Vec<f64> = (0..SAMPLES)
+		.map(|_| {
+			let sample = material.sample(&ray, &rec, &mut sampler).unwrap();
+			if Vector::dot(&sample.direction, &rec.normal) > 0.0 {
+				return 1.0;
+			}
+			return 0.0;
+		})
+		.collect();
+	assert_close("reflected fraction", &estimate(&values), 0.04);
+}
+
+//REGULARIZATION
+
+//a regularized mirror or glass spreads its delta directions over a cone, which has to behave
+//like any other sampled lobe.
+#[test]
+fn regularized_samples_agree_with_eval_and_pdf() {
+	let cos_max = degrees_to_radians(10.0).cos();
+	let materials = [
+		MaterialEnum::new_metal(0.9, 0.6, 0.3, 0.0).regularized(cos_max).unwrap(),
+		MaterialEnum::new_dielectric(1.5).regularized(cos_max).unwrap(),
+	];
+	let mut sampler = sampler(11);
+	for material in materials {
+		let rec = hit_record(material);
+		for theta in [0.0, 45.0, 80.0] {
+			let wo = outgoing(theta);
+			let ray = incoming_ray(&wo);
+			for _ in 0..10_000 {
+				let Some(sample) = material.sample(&ray, &rec, &mut sampler) else {
+					continue;
+				};
+				assert!(!sample.lobe.is_delta());
+				let pdf = material.pdf(&wo, &sample.direction, &rec);
+				assert!((pdf - sample.pdf).abs() <= 1e-9 * pdf.max(1.0), "pdf {} but sampled with {}", pdf, sample.pdf);
+				let cos_theta = Vector::dot(&sample.direction, &rec.normal).abs();
+				let expected: Color = material.eval(&wo, &sample.direction, &rec) * (cos_theta / sample.pdf);
+				assert!((expected - sample.weight).length() <= 1e-6, "weight doesn't match eval * cos / pdf at {theta}");
+			}
+		}
+	}
+}
+
+#[test]
+fn regularized_glass_still_loses_nothing() {
+	let material = MaterialEnum::new_dielectric(1.5).regularized(degrees_to_radians(10.0).cos()).unwrap();
+	for theta in [0.0, 45.0] {
+		assert_close(&format!("integrated at {theta}"), &integrated_reflectance(material, &outgoing(theta), 12), 1.0);
+	}
+	assert!(MaterialEnum::new_lambertian(0.5, 0.5, 0.5).regularized(0.9).is_none());
+}
+
+//RECIPROCITY
+
+#[test]
+fn eval_is_reciprocal() {
+	let materials = [
+		MaterialEnum::new_lambertian(0.3, 0.6, 0.9),
+		MaterialEnum::new_metal(0.9, 0.9, 0.9, 0.5),
+		MaterialEnum::new_dielectric(1.5),
+		MaterialEnum::new_diffuse_light(1.0, 1.0, 1.0),
+	];
+	let mut sampler = sampler(9);
+	for material in materials {
+		let rec = hit_record(material);
+		for _ in 0..10_000 {
+			let wo = sampling::uniform_sphere(sampler.get_2d());
+			let wi = sampling::uniform_sphere(sampler.get_2d());
+			let forward: Color = material.eval(&wo, &wi, &rec);
+			let backward: Color = material.eval(&wi, &wo, &rec);
+			//a one sided brdf is reciprocal over directions on the same side.
+			if wo.z * wi.z <= 0.0 {
+				continue;
+			}
+			assert!((forward - backward).length() <= 1e-12, "eval isn't symmetric");
+		}
+	}
+}
+
+//WHITE FURNACE
+
+//Renders a sphere filling the view inside a white environment and returns the brightness of
+//every pixel whose samples all hit the sphere.
+fn furnace(material: MaterialEnum, max_depth: i32) -> Vec<f64> {
+	let mut camera = Camera::new(1.0, 24, 16);
+	camera.background = Background::Solid(Color::new(1.0, 1.0, 1.0));
+	camera.max_depth = max_depth;
+	camera.seed = 10;
+
+	let mut world = HittableList::new();
+	world.add(0.0, 0.0, -3.0, 1.0, material);
+	let framebuffer = camera.render_framebuffer(&world);
+
+	let mut values: Vec<f64> = Vec::new();
+	for j in 0..framebuffer.height {
+		for i in 0..framebuffer.width {
+			let pixel = framebuffer.pixel(i, j);
+			if pixel.hit_count == pixel.count {
+				values.push(luminance(&pixel.color()));
+			}
+		}
+	}
+	assert!(values.len() > 20, "the sphere should cover the middle of the image");
+	return values;
+}
+
+//a convex sphere only ever bounces light once before it's back in the environment,
+//so the sphere shows exactly its albedo.
+#[test]
+fn white_furnace_lambertian() {
+	assert_close("lambertian sphere", &estimate(&furnace(MaterialEnum::new_lambertian(0.8, 0.8, 0.8), 10)), 0.8);
+	assert_close("white lambertian sphere", &estimate(&furnace(MaterialEnum::new_lambertian(1.0, 1.0, 1.0), 10)), 1.0);
+}
+
+#[test]
+fn white_furnace_metal() {
+	assert_close("mirror sphere", &estimate(&furnace(MaterialEnum::new_metal(0.7, 0.7, 0.7, 0.0), 10)), 0.7);
+	let rough = estimate(&furnace(MaterialEnum::new_metal(0.7, 0.7, 0.7, 0.5), 10));
+	assert!(rough.mean <= 0.7 + rough.half_width, "rough metal sphere is brighter than its albedo: {}", rough.mean);
+}
+
+//glass neither absorbs nor emits, so it disappears, apart from the little light
+//still bouncing around inside when the depth runs out.
+#[test]
+fn white_furnace_glass() {
+	let glass = estimate(&furnace(MaterialEnum::new_dielectric(1.5), 64));
+	assert!((glass.mean - 1.0).abs() <= glass.half_width + 1e-3, "glass sphere came out {:.5} +- {:.5}", glass.mean, glass.half_width);
+}
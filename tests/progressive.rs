@@ -0,0 +1,77 @@
+#![allow(clippy::needless_return)]
+//Checks that rendering in passes only changes when the image gets written: the image on disk is
+//the one from the last finished pass, and once all the passes are done the framebuffer is bit for
+//bit the one a single render with the same samples gives.
+mod common;
+
+use common::{assert_identical, lit_world, TempDir};
+use raytracer::*;
+
+use std::fs;
+use std::fs::File;
+
+const SAMPLES: i32 = 8;
+
+fn small_camera() -> Camera {
+	let mut camera = common::small_camera();
+	camera.samples_per_pixel = SAMPLES;
+	return camera;
+}
+
+//the bytes write_ppm gives for framebuffer.
+fn ppm(framebuffer: &Framebuffer, dir: &TempDir) -> Vec<u8> {
+	let path = dir.file("expected.ppm");
+	framebuffer.write_ppm(&mut File::create(&path).unwrap());
+	return fs::read(&path).unwrap();
+}
+
+//one setting on top of small_camera's.
+type Setup = fn(&mut Camera);
+
+#[test]
+fn passes_add_up_to_a_single_render() {
+	let world = lit_world();
+	//the default box filter keeps each sample in its own pixel. A wider one adds up the splats that
+	//cross tile edges in another order when the render is split up, so that's only the same to rounding.
+	let setups: [Setup; 3] = [
+		|_| {},
+		|camera| camera.adaptive = Some(AdaptiveSampling { min_samples: 2, max_samples: SAMPLES, tolerance: 0.05 }),
+		|camera| camera.denoiser = Some(Denoiser::default()),
+	];
+	for setup in setups {
+		let mut camera = small_camera();
+		setup(&mut camera);
+		let expected = camera.render_framebuffer(&world);
+		//3 doesn't divide the samples, so the last pass is a short one.
+		for samples_per_pass in [1, 3, SAMPLES, 100] {
+			let dir = TempDir::new(&format!("passes_add_up_to_a_single_render_{samples_per_pass}"));
+			let path = dir.file("image.ppm");
+			fs::write(&path, "not an image").unwrap();
+			let found = camera.render_progressive(&world, &path, samples_per_pass).unwrap();
+			assert_identical(&found, &expected);
+			assert!(fs::read(&path).unwrap() == ppm(&expected, &dir), "the image on disk isn't the finished one with {samples_per_pass} samples per pass");
+		}
+	}
+}
+
+//render_resumable_for stops after a given pass, which shows what render_progressive leaves on disk
+//by then: they write their images the same way.
+#[test]
+fn image_is_rewritten_after_every_pass() {
+	let world = lit_world();
+	let samples_per_pass = 2;
+	let dir = TempDir::new("image_is_rewritten_after_every_pass");
+	let (path, checkpoint) = (dir.file("image.ppm"), dir.file("image.checkpoint"));
+	let mut previous = Vec::new();
+	for pass in 1..=SAMPLES / samples_per_pass {
+		small_camera().render_resumable_for(&world, &path, &checkpoint, samples_per_pass, 1).unwrap();
+		let written = fs::read(&path).unwrap();
+		assert!(written != previous, "pass {pass} didn't change the image");
+
+		//the same as a render with only the samples done so far.
+		let mut so_far = Framebuffer::new(40, 20);
+		small_camera().render_pass(&world, &mut so_far, 0, pass * samples_per_pass);
+		assert!(written == ppm(&so_far, &dir), "the image after pass {pass} has the wrong samples");
+		previous = written;
+	}
+}
@@ -0,0 +1,154 @@
+#![allow(clippy::needless_return)]
+//Renders a few small scenes with fixed seeds and compares them against the images in tests/reference.
+//A scene fails when the RMSE against its reference, in 8 bit steps, is above TOLERANCE.
+//the render and an amplified difference image are then left in target/tmp/reference.
+//
+//After a change that is meant to alter the images, regenerate the references with
+//	UPDATE_REFERENCES=1 cargo test --test reference
+//and look at them before committing.
+use raytracer::*;
+
+use std::fs;
+use std::fs::File;
+use std::path::PathBuf;
+
+//renders are deterministic, this only has to absorb floating point differences between platforms.
+const TOLERANCE: f64 = 1.0;
+
+struct Image {
+	width: usize,
+	height: usize,
+	values: Vec<u8>,
+}
+
+fn read_ppm(bytes: &[u8]) -> Image {
+	let text = String::from_utf8_lossy(bytes);
+	let mut tokens = text.split_whitespace();
+	assert_eq!(tokens.next(), Some("P3"), "not a P3 ppm");
+	let mut number = || tokens.next().expect("ppm ends early").parse::<usize>().expect("bad number in ppm");
+	let width = number();
+	let height = number();
+	let _max = number();
+	let values = (0..width * height * 3).map(|_| number() as u8).collect();
+	return Image { width, height, values };
+}
+
+fn output_dir() -> PathBuf {
+	let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("reference");
+	fs::create_dir_all(&dir).unwrap();
+	return dir;
+}
+
+fn reference_path(name: &str) -> PathBuf {
+	return PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("reference").join(format!("{name}.ppm"));
+}
+
+fn check_scene(name: &str, camera: &Camera, world: &HittableList) {
+	let output_path = output_dir().join(format!("{name}.ppm"));
+	camera.render(world, &mut File::create(&output_path).unwrap());
+	let rendered_bytes = fs::read(&output_path).unwrap();
+
+	let reference_path = reference_path(name);
+	if std::env::var_os("UPDATE_REFERENCES").is_some() {
+		fs::write(&reference_path, &rendered_bytes).unwrap();
+		return;
+	}
+	let Ok(reference_bytes) = fs::read(&reference_path) else {
+		panic!("{name}: no reference at {}, run with UPDATE_REFERENCES=1 to make one", reference_path.display());
+	};
+
+	let rendered = read_ppm(&rendered_bytes);
+	let reference = read_ppm(&reference_bytes);
+	assert_eq!((rendered.width, rendered.height), (reference.width, reference.height), "{name}: image size changed");
+
+	let squared_error: f64 = rendered.values.iter().zip(&reference.values)
+		.map(|(a, b)| (*a as f64 - *b as f64).powi(2))
+		.sum();
+	let rmse = (squared_error / rendered.values.len() as f64).sqrt();
+	if rmse <= TOLERANCE {
+		return;
+	}
+
+	//the difference times 4, so small changes are still visible.
+	let diff_path = output_dir().join(format!("{name}_diff.ppm"));
+	let mut diff = format!("P3\n{} {}\n255\n", rendered.width, rendered.height);
+	for pixel in rendered.values.chunks(3).zip(reference.values.chunks(3)) {
+		let channels: Vec<String> = (0..3).map(|c| ((pixel.0[c] as i32 - pixel.1[c] as i32).abs() * 4).min(255).to_string()).collect();
+		diff.push_str(&channels.join(" "));
+		diff.push('\n');
+	}
+	fs::write(&diff_path, diff).unwrap();
+	panic!("{name}: RMSE {rmse:.3} is above {TOLERANCE}, render in {} and difference in {}", output_path.display(), diff_path.display());
+}
+
+//SCENES
+
+fn small_camera(aspect_ratio: f64, width: i32, samples_per_pixel: i32) -> Camera {
+	let mut camera = Camera::new(aspect_ratio, width, samples_per_pixel);
+	camera.seed = 1234;
+	return camera;
+}
+
+//the scene from main().
+#[test]
+fn three_spheres() {
+	let camera = small_camera(16.0 / 9.0, 96, 32);
+	let mut world = HittableList::new();
+	world.add(0.0, 0.0, -1.2, 0.5, MaterialEnum::new_lambertian(0.1, 0.2, 0.5));
+	world.add(1.0, 0.0, -1.0, 0.5, MaterialEnum::new_metal(0.6, 0.2, 0.8, 1.0));
+	world.add(-1.0, 0.0, -1.0, 0.5, MaterialEnum::new_dielectric(1.50));
+	world.add(-1.0, 0.0, -1.0, 0.4, MaterialEnum::new_dielectric(1.00 / 1.50));
+	world.add(0.0, -100.5, -1.0, 100.0, MaterialEnum::new_lambertian(0.8, 0.8, 0.0));
+	check_scene("three_spheres", &camera, &world);
+}
+
+#[test]
+fn cornell_box() {
+	let mut camera = small_camera(1.0, 64, 64);
+	camera.background = Background::Solid(Color::null_vector());
+
+	let mut world = HittableList::new();
+	let red = MaterialEnum::new_lambertian(0.65, 0.05, 0.05);
+	let white = MaterialEnum::new_lambertian(0.73, 0.73, 0.73);
+	let green = MaterialEnum::new_lambertian(0.12, 0.45, 0.15);
+	world.add_quad(Point::new(-1.0, -1.0, -1.0), Vector::new(0.0, 0.0, -2.0), Vector::new(0.0, 2.0, 0.0), red);
+	world.add_quad(Point::new(1.0, -1.0, -1.0), Vector::new(0.0, 2.0, 0.0), Vector::new(0.0, 0.0, -2.0), green);
+	world.add_quad(Point::new(-1.0, -1.0, -1.0), Vector::new(2.0, 0.0, 0.0), Vector::new(0.0, 0.0, -2.0), white);
+	world.add_quad(Point::new(-1.0, 1.0, -1.0), Vector::new(0.0, 0.0, -2.0), Vector::new(2.0, 0.0, 0.0), white);
+	world.add_quad(Point::new(-1.0, -1.0, -3.0), Vector::new(2.0, 0.0, 0.0), Vector::new(0.0, 2.0, 0.0), white);
+	world.add_quad(Point::new(-0.2, 0.99, -1.8), Vector::new(0.0, 0.0, -0.4), Vector::new(0.4, 0.0, 0.0), MaterialEnum::new_diffuse_light(15.0, 15.0, 15.0));
+	world.add(-0.4, -0.6, -2.2, 0.4, MaterialEnum::new_lambertian(0.5, 0.5, 0.5));
+	world.add(0.4, -0.6, -1.8, 0.4, MaterialEnum::new_metal(0.8, 0.8, 0.8, 0.3));
+	check_scene("cornell_box", &camera, &world);
+}
+
+#[test]
+fn glass_sphere() {
+	let mut camera = small_camera(4.0 / 3.0, 64, 64);
+	camera.lookfrom = Point::new(0.0, 0.6, 1.5);
+	camera.lookat = Point::new(0.0, 0.0, -1.0);
+	camera.vfov = 45.0;
+	camera.initialize();
+
+	let mut world = HittableList::new();
+	let checker = Texture::new_checker(0.25, Color::new(0.9, 0.9, 0.9), Color::new(0.2, 0.3, 0.1));
+	world.add(0.0, -100.5, -1.0, 100.0, MaterialEnum::new_textured(checker));
+	world.add(0.0, 0.0, -1.0, 0.5, MaterialEnum::new_dielectric(1.5));
+	check_scene("glass_sphere", &camera, &world);
+}
+
+#[test]
+fn textured_plane() {
+	let mut camera = small_camera(4.0 / 3.0, 64, 32);
+	camera.lookfrom = Point::new(0.0, 2.0, 2.0);
+	camera.lookat = Point::new(0.0, 0.0, -1.0);
+	camera.vfov = 50.0;
+	camera.initialize();
+
+	let mut world = HittableList::new();
+	let squares = Texture::new_uv_checker(8.0, 8.0, Color::new(0.8, 0.1, 0.1), Color::new(0.9, 0.9, 0.9));
+	world.add_quad(Point::new(-2.0, 0.0, 1.0), Vector::new(4.0, 0.0, 0.0), Vector::new(0.0, 0.0, -4.0), MaterialEnum::new_textured(squares));
+	let stripes = Texture::new_uv_checker(16.0, 1.0, Color::new(0.1, 0.2, 0.7), Color::new(0.9, 0.8, 0.2));
+	world.add(0.0, 0.5, -1.0, 0.5, MaterialEnum::new_textured(stripes));
+	check_scene("textured_plane", &camera, &world);
+}
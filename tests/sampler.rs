@@ -0,0 +1,110 @@
+#![allow(clippy::needless_return)]
+//Checks the samplers hand out numbers in [0,1), the same ones for the same seed, and that the
+//low discrepancy ones really do spread a pixel's samples out evenly.
+use raytracer::*;
+use raytracer::sampler::Sampler;
+
+const KINDS: [SamplerKind; 4] = [SamplerKind::Independent, SamplerKind::Stratified, SamplerKind::Halton, SamplerKind::Sobol];
+
+//every number one camera sample uses: pixel, lens, time and a few bounces, the last one past
+//the dimensions that are set aside for bounces so the random fallback gets used too.
+fn camera_sample(sampler: &mut Sampler, i: i32, j: i32, sample_index: i32) -> Vec<f64> {
+	sampler.start_pixel_sample(i, j, sample_index);
+	let mut values = Vec::new();
+	let (x, y) = sampler.get_pixel_2d();
+	let (u, v) = sampler.get_lens_2d();
+	values.extend([x, y, u, v, sampler.get_time_1d()]);
+	for bounce in 0..12 {
+		sampler.start_bounce(bounce);
+		for _ in 0..5 {
+			let (a, b) = sampler.get_2d();
+			values.extend([sampler.get_1d(), a, b]);
+		}
+	}
+	return values;
+}
+
+#[test]
+fn every_dimension_is_in_the_unit_interval() {
+	for kind in KINDS {
+		//more samples than it was set up for as well, those fall back to plain random numbers.
+		let mut sampler = Sampler::new(kind, 7, 16);
+		for sample_index in 0..24 {
+			for value in camera_sample(&mut sampler, 3, 5, sample_index) {
+				assert!((0.0..1.0).contains(&value), "{kind:?}: {value} is outside [0, 1)");
+			}
+		}
+	}
+}
+
+#[test]
+fn the_same_seed_gives_the_same_sequence() {
+	for kind in KINDS {
+		let mut first = Sampler::new(kind, 42, 16);
+		let mut second = Sampler::new(kind, 42, 16);
+		let mut other_seed = Sampler::new(kind, 43, 16);
+		//in a different order too, a sample doesn't depend on the ones before it.
+		for sample_index in (0..16).rev() {
+			let expected = camera_sample(&mut first, 1, 2, sample_index);
+			assert_eq!(camera_sample(&mut second, 1, 2, sample_index), expected, "{kind:?}: same seed, different numbers");
+			assert_ne!(camera_sample(&mut other_seed, 1, 2, sample_index), expected, "{kind:?}: the seed changes nothing");
+		}
+		assert_ne!(camera_sample(&mut first, 2, 2, 0), camera_sample(&mut second, 1, 2, 0), "{kind:?}: neighbouring pixels get the same numbers");
+	}
+}
+
+//the pixel samples of one pixel, every sample it was set up for.
+fn pixel_samples(kind: SamplerKind, count: i32) -> Vec<(f64, f64)> {
+	let mut sampler = Sampler::new(kind, 9, count);
+	return (0..count)
+		.map(|sample_index| {
+			sampler.start_pixel_sample(4, 1, sample_index);
+			return sampler.get_pixel_2d();
+		})
+		.collect();
+}
+
+//true when each cell of a columns by rows grid over the unit square has exactly one point.
+fn one_per_cell(points: &[(f64, f64)], columns: usize, rows: usize) -> bool {
+	let mut cells = vec![0; columns * rows];
+	for &(x, y) in points {
+		cells[(y * rows as f64) as usize * columns + (x * columns as f64) as usize] += 1;
+	}
+	return cells.iter().all(|&count| count == 1);
+}
+
+#[test]
+fn sobol_fills_every_elementary_interval() {
+	for log_count in 0..=8 {
+		let count = 1 << log_count;
+		let points = pixel_samples(SamplerKind::Sobol, count);
+		//every way of cutting the square into count boxes of power of two sides.
+		for log_columns in 0..=log_count {
+			let (columns, rows) = (1 << log_columns, 1 << (log_count - log_columns));
+			assert!(one_per_cell(&points, columns, rows), "{count} samples: a {columns} by {rows} grid has a cell without exactly one");
+		}
+	}
+}
+
+#[test]
+fn halton_fills_every_elementary_interval() {
+	//the first dimension is base 2, so power of two counts are stratified along it.
+	for log_count in 0..=8 {
+		let count = 1 << log_count;
+		let points = pixel_samples(SamplerKind::Halton, count);
+		assert!(one_per_cell(&points, count as usize, 1), "{count} samples aren't stratified in x");
+	}
+	//and the second is base 3, so 2^a 3^b samples put one in each 2^a by 3^b box.
+	for (columns, rows) in [(2, 3), (4, 9), (8, 9), (16, 27)] {
+		let points = pixel_samples(SamplerKind::Halton, columns * rows);
+		assert!(one_per_cell(&points, columns as usize, rows as usize), "a {columns} by {rows} grid has a cell without exactly one");
+	}
+}
+
+#[test]
+fn stratified_puts_one_sample_in_each_stratum() {
+	for side in 1..=8 {
+		let points = pixel_samples(SamplerKind::Stratified, side * side);
+		assert!(one_per_cell(&points, side as usize, side as usize), "{side} by {side} strata");
+	}
+}
@@ -0,0 +1,87 @@
+#![allow(clippy::needless_return)]
+//Checks the built in scenes load, and that the bvh and the instance wrappers
+//find exactly the same hits as testing every object directly.
+mod common;
+
+use common::random_point;
+use raytracer::*;
+use raytracer::instance::{RotateY, Translate};
+use raytracer::rng::Rng;
+use raytracer::scene::{load_scene, SCENE_NAMES};
+
+use std::sync::Arc;
+
+#[test]
+fn every_scene_loads() {
+	for name in SCENE_NAMES {
+		let scene = load_scene(name).unwrap_or_else(|| panic!("{name} is listed but doesn't load"));
+		assert!(!scene.world.is_empty(), "{name} is empty");
+		assert!(scene.camera.image_height() > 0);
+	}
+	assert!(load_scene("no_such_scene").is_none());
+}
+
+//a mix of every shape, the same objects in the same order for the same seed.
+fn random_world(seed: u64) -> HittableList {
+	let mut rng = Rng::new(seed, 0);
+	let material = MaterialEnum::new_lambertian(0.5, 0.5, 0.5);
+	let mut world = HittableList::new();
+	for _ in 0..200 {
+		let center = random_point(&mut rng, 10.0);
+		match rng.next_u32() % 3 {
+			0 => world.add(center.x, center.y, center.z, rng.random_f64_in_range(0.1, 1.0), material),
+			1 => world.add_quad(center, random_point(&mut rng, 1.0), random_point(&mut rng, 1.0), material),
+			_ => world.add_triangle(center, center + random_point(&mut rng, 1.0), center + random_point(&mut rng, 1.0), material),
+		}
+	}
+	return world;
+}
+
+#[test]
+fn bvh_finds_the_same_hits() {
+	let linear = random_world(1);
+	let mut accelerated = random_world(1);
+	accelerated.build_bvh();
+	assert!(accelerated.has_bvh());
+
+	let mut rng = Rng::new(2, 0);
+	let mut hits = 0;
+	for _ in 0..20_000 {
+		let ray = Ray::new(random_point(&mut rng, 12.0), random_point(&mut rng, 1.0));
+		let mut expected = HitRecord::default();
+		let mut found = HitRecord::default();
+		let hit = linear.hit(&ray, Interval::new(0.001, f64::INFINITY), &mut expected);
+		assert_eq!(hit, accelerated.hit(&ray, Interval::new(0.001, f64::INFINITY), &mut found));
+		if hit {
+			hits += 1;
+			assert_eq!(expected.t, found.t);
+			assert_eq!(expected.object_id, found.object_id);
+		}
+	}
+	assert!(hits > 1000, "too few rays hit anything to say much: {hits}");
+}
+
+#[test]
+fn instances_hit_like_the_moved_object() {
+	let material = MaterialEnum::new_lambertian(0.5, 0.5, 0.5);
+	let offset = Vector::new(3.0, -1.0, 2.0);
+	let shape: Arc<dyn Hittable> = Arc::new(Sphere::new(Point::new(1.0, 0.0, 0.0), 0.5, material));
+	//turning by 90 degrees takes (1, 0, 0) to (0, 0, -1).
+	let rotated: Arc<dyn Hittable> = Arc::new(RotateY::new(shape, 90.0));
+	let instance = Translate::new(rotated, offset);
+	let moved = Sphere::new(Point::new(0.0, 0.0, -1.0) + offset, 0.5, material);
+
+	let mut rng = Rng::new(3, 0);
+	for _ in 0..10_000 {
+		let ray = Ray::new(random_point(&mut rng, 4.0) + offset, random_point(&mut rng, 1.0));
+		let mut expected = HitRecord::default();
+		let mut found = HitRecord::default();
+		let hit = moved.hit(&ray, Interval::new(0.001, f64::INFINITY), &mut expected);
+		assert_eq!(hit, instance.hit(&ray, Interval::new(0.001, f64::INFINITY), &mut found));
+		if hit {
+			assert!((expected.t - found.t).abs() < 1e-9);
+			assert!((expected.hit_point - found.hit_point).length() < 1e-9);
+			assert!((expected.normal - found.normal).length() < 1e-9);
+		}
+	}
+}
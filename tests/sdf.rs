@@ -0,0 +1,180 @@
+#![allow(clippy::needless_return)]
+//Checks sphere tracing against the distance functions themselves: a hit has to be on the
+//surface with nothing inside the shape before it, and a miss can't pass through the shape.
+//Emissive ones glow but aren't sampled as lights.
+mod common;
+
+use common::{in_box, random_point};
+use raytracer::*;
+use raytracer::instance::Translate;
+use raytracer::rng::Rng;
+
+use std::sync::Arc;
+
+//makers rather than shapes, each test needs one copy for the SdfShape and one to ask for distances.
+type MakeSdf = fn() -> Sdf;
+
+fn shapes() -> Vec<(&'static str, MakeSdf)> {
+	return vec![
+		("round box", || Sdf::new_round_box(Point::new(0.1, 0.0, -0.2), Vector::new(0.8, 0.5, 0.6), 0.15)),
+		("smooth union", || Sdf::new_smooth_union(
+			Sdf::new_sphere(Point::new(-0.5, 0.0, 0.0), 0.6),
+			Sdf::new_capsule(Point::new(0.0, -0.6, 0.3), Point::new(0.7, 0.6, -0.2), 0.3),
+			0.4,
+		)),
+		("repeat", || Sdf::new_repeat(Sdf::new_sphere(Point::null_vector(), 0.3), Vector::new(0.8, 0.0, 0.8), [3, 1, 2])),
+		("twist", || Sdf::new_twist(Sdf::new_round_box(Point::null_vector(), Vector::new(0.6, 1.0, 0.3), 0.05), 1.5)),
+		("displace", || Sdf::new_displace(Sdf::new_torus(Point::null_vector(), 0.9, 0.35), 0.08, 9.0)),
+	];
+}
+
+#[test]
+fn hits_are_the_first_zero_crossing() {
+	let material = MaterialEnum::new_lambertian(0.5, 0.5, 0.5);
+	for (name, make) in shapes() {
+		let mut shape = SdfShape::new(make(), material);
+		//plenty of steps, so rays grazing the bumps of the displacement don't give up before they get past.
+		shape.max_steps = 1024;
+		let sdf = make();
+		let bbox = shape.bounding_box();
+		let mut rng = Rng::new(1, 0);
+		let mut hits = 0;
+		for _ in 0..5_000 {
+			let origin = random_point(&mut rng, 2.5);
+			let ray = Ray::new(origin, random_point(&mut rng, 0.8) - origin);
+			//where the ray starts is at t_min, the origin itself can be on the other side of a surface.
+			let started_inside = sdf.distance(&ray.at(0.001)) < 0.0;
+			let mut rec = HitRecord::default();
+			let found = shape.hit(&ray, Interval::new(0.001, f64::INFINITY), &mut rec);
+			//how far along the ray to look for the shape: up to the hit, or through the whole box.
+			let end = if found { rec.t } else { 5.0 };
+			for step in 1..200 {
+				let p = ray.at(0.001 + (end - 0.001) * step as f64 / 200.0);
+				//a few times epsilon of slack, right at the surface the sign can go either way.
+				let distance = if started_inside { -sdf.distance(&p) } else { sdf.distance(&p) };
+				assert!(distance > -1e-3, "{name}: the ray went through the surface at {p:?} before t {end}");
+			}
+			if !found {
+				continue;
+			}
+			hits += 1;
+			assert!(sdf.distance(&rec.hit_point).abs() < 1e-3, "{name}: hit {:?} isn't on the surface", rec.hit_point);
+			assert!(in_box(&bbox, &rec.hit_point), "{name}: hit outside the bounding box");
+			assert!((rec.normal.length() - 1.0).abs() < 1e-9, "{name}: normal isn't unit length");
+			assert!(Vector::dot(&rec.normal, &ray.dir) <= 0.0, "{name}: normal faces away from the ray");
+			assert_eq!(rec.front_face, !started_inside, "{name}: front_face should mean the ray goes in");
+		}
+		assert!(hits > 500, "{name}: too few hits to say much: {hits}");
+	}
+}
+
+//an sdf sphere should look just like the analytic one, and sit in a list with other shapes.
+#[test]
+fn sphere_matches_the_analytic_sphere() {
+	let material = MaterialEnum::new_lambertian(0.5, 0.5, 0.5);
+	let center = Point::new(0.2, -0.1, 0.3);
+	let traced = SdfShape::new(Sdf::new_sphere(center, 0.7), material);
+	let analytic = Sphere::new(center, 0.7, material);
+	let mut rng = Rng::new(2, 0);
+	for _ in 0..5_000 {
+		let origin = random_point(&mut rng, 3.0);
+		let ray = Ray::new(origin, random_point(&mut rng, 1.0) - origin);
+		let (mut found, mut expected) = (HitRecord::default(), HitRecord::default());
+		let hit = traced.hit(&ray, Interval::new(0.001, f64::INFINITY), &mut found);
+		if !analytic.hit(&ray, Interval::new(0.001, f64::INFINITY), &mut expected) {
+			continue;
+		}
+		//rays that only graze the sphere can run out of steps.
+		if !hit {
+			let closest = ray.at(Vector::dot(&(center - origin), &ray.dir) / ray.dir.length_squared());
+			assert!((closest - center).length() > 0.69, "missed a ray through the middle");
+			continue;
+		}
+		//tracing stops within epsilon of the surface, which can be a little way short of it along the ray.
+		assert!(((found.hit_point - center).length() - 0.7).abs() < 1e-3, "hit {:?} isn't on the sphere", found.hit_point);
+		assert!(found.t <= expected.t + 1e-9, "stopped behind the surface");
+		let outward = (found.hit_point - center).normalize();
+		assert!((found.normal - outward * if found.front_face { 1.0 } else { -1.0 }).length() < 1e-6, "central differences give the wrong normal");
+		assert_eq!(found.front_face, expected.front_face);
+	}
+
+	let mut world = HittableList::new();
+	world.add(0.0, 0.0, -3.0, 1.0, material);
+	world.raw_add(Box::new(SdfShape::new(Sdf::new_sphere(Point::new(0.0, 0.0, -1.0), 0.5), MaterialEnum::new_metal(0.9, 0.9, 0.9, 0.0))));
+	let mut rec = HitRecord::default();
+	assert!(world.hit(&Ray::new(Point::new(0.0, 0.0, 1.0), Vector::new(0.0, 0.0, -1.0)), Interval::new(0.001, f64::INFINITY), &mut rec));
+	assert!((rec.t - 1.5).abs() < 1e-3, "the sdf sphere is in front, got t {}", rec.t);
+}
+
+//the copies reach half a row either side of the origin, plus the inner shape's own size.
+#[test]
+fn repeat_box_fits_the_copies() {
+	let sphere = || Sdf::new_sphere(Point::null_vector(), 0.3);
+	let bbox = Sdf::new_repeat(sphere(), Vector::new(0.8, 0.5, -0.8), [3, 1, 2]).bounding_box();
+	for (axis, expected) in [(bbox.x, 1.1), (bbox.y, 0.3), (bbox.z, 0.7)] {
+		assert!((axis.min + expected).abs() < 1e-12 && (axis.max - expected).abs() < 1e-12, "expected +-{expected}, got {axis:?}");
+	}
+}
+
+//a box shape fills its bounding box, so rays coming in from outside are already on the surface where
+//they enter the box, and have to hit it there from the outside.
+#[test]
+fn rays_entering_a_filled_box_hit_its_side() {
+	let material = MaterialEnum::new_lambertian(0.5, 0.5, 0.5);
+	let shape = SdfShape::new(Sdf::new_round_box(Point::null_vector(), Vector::new(0.5, 0.5, 0.5), 0.0), material);
+	let mut rng = Rng::new(3, 0);
+	for _ in 0..1_000 {
+		let target = Point::new(0.5, rng.random_f64_in_range(-0.45, 0.45), rng.random_f64_in_range(-0.45, 0.45));
+		let origin = target + Vector::new(1.0, 0.0, 0.0) + random_point(&mut rng, 0.4);
+		let ray = Ray::new(origin, target - origin);
+		let mut rec = HitRecord::default();
+		assert!(shape.hit(&ray, Interval::new(0.001, f64::INFINITY), &mut rec), "missed the side at {target:?}");
+		assert!((rec.hit_point.x - 0.5).abs() < 1e-3, "hit {:?} isn't on the side it came in through", rec.hit_point);
+		assert!(rec.front_face, "a ray from outside hit the inside of the box");
+	}
+}
+
+//points can't be picked evenly on a distance function's surface, so an emissive one stays out of the
+//lights, on its own or inside a group, and its light only arrives through the paths that hit it.
+#[test]
+fn emissive_shapes_are_not_sampled_as_lights() {
+	let glow = MaterialEnum::new_diffuse_light(3.0, 3.0, 3.0);
+	let ball = |height: f64| SdfShape::new(Sdf::new_sphere(Point::new(0.0, height, 0.0), 1.0), glow);
+	let floor = |light: Box<dyn Hittable>| {
+		let mut world = HittableList::new();
+		world.add_quad(Point::new(-50.0, 0.0, -50.0), Vector::new(0.0, 0.0, 100.0), Vector::new(100.0, 0.0, 0.0), MaterialEnum::new_lambertian(0.5, 0.5, 0.5));
+		world.raw_add(light);
+		return world;
+	};
+	let worlds = [
+		floor(Box::new(ball(2.0))),
+		floor(Box::new(Bvh::new(vec![Box::new(ball(2.0))]))),
+		floor(Box::new(Translate::new(Arc::new(ball(0.0)), Vector::new(0.0, 2.0, 0.0)))),
+	];
+	for world in &worlds {
+		assert_eq!(world.light_count(), 0);
+	}
+
+	//one pixel looking down at the floor right under the ball, or up at the ball itself.
+	let render = |world: &HittableList, lookat: Point| -> f64 {
+		let mut camera = Camera::new(1.0, 1, 4_096);
+		camera.seed = 2;
+		camera.max_depth = 2;
+		camera.vfov = 1.0;
+		camera.background = Background::Solid(Color::null_vector());
+		camera.lookfrom = Point::new(0.0, 0.5, 0.0);
+		camera.lookat = lookat;
+		camera.vup = Vector::new(0.0, 0.0, -1.0);
+		camera.initialize();
+		return camera.render_framebuffer(world).get(0, 0).x;
+	};
+	//the same ball as a plain sphere, which is a light.
+	let sphere = floor(Box::new(Sphere::new(Point::new(0.0, 2.0, 0.0), 1.0, glow)));
+	assert_eq!(sphere.light_count(), 1);
+	let expected = render(&sphere, Point::null_vector());
+	for world in &worlds {
+		assert!((render(world, Point::new(0.0, 2.0, 0.0)) - 3.0).abs() < 1e-9, "the ball doesn't glow");
+		let found = render(world, Point::null_vector());
+		assert!((found - expected).abs() < 0.1 * expected, "the floor gets {found:.4}, with a sphere light {expected:.4}");
+	}
+}
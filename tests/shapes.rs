@@ -0,0 +1,257 @@
+#![allow(clippy::needless_return)]
+//Checks the analytic shapes against a plain inside test for each one: hits have to be the first
+//place the ray crosses into or out of the solid, normals have to point out of it, and surface
+//samples have to be on the surface, spread evenly and agree with surface_pdf.
+mod common;
+
+use common::{in_box, random_point};
+use raytracer::*;
+use raytracer::rng::Rng;
+use raytracer::sampler::Sampler;
+
+//A shape and an independent way of telling whether a point is inside it.
+struct Solid {
+	name: &'static str,
+	shape: Box<dyn Hittable>,
+	inside: Box<dyn Fn(&Point) -> bool>,
+}
+
+//how far p is along the axis from a to b, and how far from the axis.
+fn axial(p: &Point, a: &Point, b: &Point) -> (f64, f64) {
+	let axis = (*b - *a).normalize();
+	let z = Vector::dot(&(*p - *a), &axis);
+	let rho = (*p - *a - axis * z).length();
+	return (z, rho);
+}
+
+fn solids() -> Vec<Solid> {
+	let material = MaterialEnum::new_lambertian(0.5, 0.5, 0.5);
+	let mut solids: Vec<Solid> = Vec::new();
+
+	let (a, b) = (Point::new(0.3, -0.5, 0.2), Point::new(-0.4, 0.8, 0.5));
+	let height = (b - a).length();
+	solids.push(Solid {
+		name: "cylinder",
+		shape: Box::new(Cylinder::new(a, b, 0.6, material)),
+		inside: Box::new(move |p| {
+			let (z, rho) = axial(p, &a, &b);
+			return rho < 0.6 && z > 0.0 && z < height;
+		}),
+	});
+
+	for (name, top_radius) in [("cut off cone", 0.2), ("pointed cone", 0.0)] {
+		let (a, b) = (Point::new(0.0, -0.7, 0.0), Point::new(0.5, 0.6, -0.3));
+		let height = (b - a).length();
+		solids.push(Solid {
+			name,
+			shape: Box::new(Cone::new(a, b, 0.8, top_radius, material)),
+			inside: Box::new(move |p| {
+				let (z, rho) = axial(p, &a, &b);
+				return z > 0.0 && z < height && rho < 0.8 + (top_radius - 0.8) * z / height;
+			}),
+		});
+	}
+
+	let (center, axis) = (Point::new(0.1, 0.2, -0.1), Vector::new(0.3, 1.0, 0.2));
+	solids.push(Solid {
+		name: "torus",
+		shape: Box::new(Torus::new(center, axis, 1.0, 0.3, material)),
+		inside: Box::new(move |p| {
+			let (z, rho) = axial(p, &center, &(center + axis));
+			return (rho - 1.0) * (rho - 1.0) + z * z < 0.3 * 0.3;
+		}),
+	});
+
+	let (a, b) = (Point::new(-0.6, -0.3, 0.0), Point::new(0.5, 0.4, 0.2));
+	let height = (b - a).length();
+	solids.push(Solid {
+		name: "capsule",
+		shape: Box::new(Capsule::new(a, b, 0.4, material)),
+		inside: Box::new(move |p| {
+			let axis = (b - a) / height;
+			let closest = a + axis * Vector::dot(&(*p - a), &axis).clamp(0.0, height);
+			return (*p - closest).length() < 0.4;
+		}),
+	});
+	return solids;
+}
+
+#[test]
+fn hits_are_the_first_crossing() {
+	for solid in solids() {
+		let mut rng = Rng::new(1, 0);
+		let bbox = solid.shape.bounding_box();
+		let mut hits = 0;
+		let mut wrong_normals = 0;
+		for _ in 0..20_000 {
+			let origin = random_point(&mut rng, 2.5);
+			let ray = Ray::new(origin, (random_point(&mut rng, 0.8) - origin).normalize());
+			let started_inside = (solid.inside)(&origin);
+			let mut rec = HitRecord::default();
+			//the usual 0.001 would skip surfaces right next to the origin.
+			if !solid.shape.hit(&ray, Interval::new(1e-9, f64::INFINITY), &mut rec) {
+				//then the ray never gets into the solid.
+				for step in 1..200 {
+					let p = ray.at(step as f64 * 0.05);
+					assert_eq!((solid.inside)(&p), started_inside, "{}: missed the solid at {:?}", solid.name, p);
+				}
+				continue;
+			}
+			hits += 1;
+
+			//nothing crossed before the hit.
+			for step in 1..20 {
+				let p = ray.at(rec.t * step as f64 / 20.0);
+				assert_eq!((solid.inside)(&p), started_inside, "{}: hit at t {} isn't the first crossing", solid.name, rec.t);
+			}
+			assert!((rec.normal.length() - 1.0).abs() < 1e-9, "{}: normal isn't unit length", solid.name);
+			assert!(Vector::dot(&rec.normal, &ray.dir) <= 0.0, "{}: normal faces away from the ray", solid.name);
+			assert!((0.0..=1.0).contains(&rec.u) && (0.0..=1.0).contains(&rec.v), "{}: uv ({}, {}) outside [0, 1]", solid.name, rec.u, rec.v);
+			assert!(in_box(&bbox, &rec.hit_point), "{}: hit outside the bounding box", solid.name);
+
+			//a little way along the outward normal is outside, and a little way back is inside.
+			//right on a rim both sides of the normal can be outside, so a few are let through.
+			let outward = rec.outward_normal();
+			if (solid.inside)(&(rec.hit_point + outward * 1e-5)) || !(solid.inside)(&(rec.hit_point - outward * 1e-5)) {
+				wrong_normals += 1;
+			}
+		}
+		assert!(hits > 2000, "{}: too few hits to say much: {hits}", solid.name);
+		assert!(wrong_normals * 1000 < hits, "{}: {wrong_normals} of {hits} normals don't point out", solid.name);
+	}
+}
+
+#[test]
+fn open_cylinder_has_no_caps() {
+	let material = MaterialEnum::new_lambertian(0.5, 0.5, 0.5);
+	let tube = Cylinder::new_open(Point::new(0.0, 0.0, 0.0), Point::new(0.0, 2.0, 0.0), 0.5, material);
+	let mut rec = HitRecord::default();
+	//straight down the middle goes through.
+	assert!(!tube.hit(&Ray::new(Point::new(0.0, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0)), Interval::new(0.001, f64::INFINITY), &mut rec));
+	//slanted in through the open top, it hits the inside of the wall.
+	assert!(tube.hit(&Ray::new(Point::new(0.0, 3.0, 0.0), Vector::new(0.2, -1.0, 0.0)), Interval::new(0.001, f64::INFINITY), &mut rec));
+	assert!(!rec.front_face);
+	assert!((rec.hit_point.x - 0.5).abs() < 1e-9);
+	assert!((rec.normal - Vector::new(-1.0, 0.0, 0.0)).length() < 1e-9);
+}
+
+//a cone or cylinder with its top on its base has no side, and one with no radius or a capsule with
+//no radius is just a line, so there's nothing to hit or sample.
+#[test]
+fn degenerate_shapes_are_empty() {
+	let material = MaterialEnum::new_lambertian(0.5, 0.5, 0.5);
+	let base = Point::new(0.3, 0.0, -0.2);
+	let top = Point::new(0.3, 1.0, -0.2);
+	let shapes: Vec<(&str, Box<dyn Hittable>)> = vec![
+		("flat cone", Box::new(Cone::new(base, base, 0.8, 0.4, material))),
+		("flat cylinder", Box::new(Cylinder::new(base, base, 0.8, material))),
+		("flat open cylinder", Box::new(Cylinder::new_open(base, base, 0.8, material))),
+		("thin cylinder", Box::new(Cylinder::new(base, top, 0.0, material))),
+		("thin capsule", Box::new(Capsule::new(base, top, 0.0, material))),
+		("thin round capsule", Box::new(Capsule::new(base, base, 0.0, material))),
+	];
+	for (name, shape) in shapes {
+		let bbox = shape.bounding_box();
+		assert!(bbox.x.min > bbox.x.max && bbox.y.min > bbox.y.max && bbox.z.min > bbox.z.max, "{name}: the box should be empty: {bbox:?}");
+		let mut rng = Rng::new(4, 0);
+		let mut rec = HitRecord::default();
+		for _ in 0..1_000 {
+			let origin = random_point(&mut rng, 2.0);
+			//aimed right at the base and the top, where a line would be.
+			for target in [base, top] {
+				assert!(!shape.hit(&Ray::new(origin, target - origin), Interval::new(0.001, f64::INFINITY), &mut rec), "{name}: hit something");
+			}
+		}
+		let mut sampler = Sampler::new(SamplerKind::Independent, 1, 1);
+		sampler.start_pixel_sample(0, 0, 0);
+		assert!(shape.sample_surface(&Point::new(0.0, 2.0, 0.0), &mut sampler).is_none(), "{name}: sampled a point");
+	}
+}
+
+//a tube wider than the ring would pass through the axis, it's made as wide as the ring instead.
+#[test]
+fn spindle_torus_becomes_a_horn_torus() {
+	let material = MaterialEnum::new_lambertian(0.5, 0.5, 0.5);
+	let (center, axis) = (Point::new(0.1, 0.2, -0.1), Vector::new(0.3, 1.0, 0.2));
+	let spindle = Torus::new(center, axis, 1.0, 1.5, material);
+	let horn = Torus::new(center, axis, 1.0, 1.0, material);
+	assert_eq!(format!("{:?}", spindle.bounding_box()), format!("{:?}", horn.bounding_box()));
+	let mut rng = Rng::new(5, 0);
+	for _ in 0..1_000 {
+		let origin = random_point(&mut rng, 4.0);
+		let ray = Ray::new(origin, (random_point(&mut rng, 1.5) - origin).normalize());
+		let (mut found, mut expected) = (HitRecord::default(), HitRecord::default());
+		let hit = spindle.hit(&ray, Interval::new(0.001, f64::INFINITY), &mut found);
+		assert_eq!(hit, horn.hit(&ray, Interval::new(0.001, f64::INFINITY), &mut expected));
+		if hit {
+			assert_eq!(found.t, expected.t);
+		}
+	}
+}
+
+//Light samples are on the surface, and when nothing is in front of them, surface_pdf
+//gives the same pdf for the direction the sample was taken in.
+#[test]
+fn surface_samples_agree_with_surface_pdf() {
+	for solid in solids() {
+		let bbox = solid.shape.bounding_box();
+		let mut sampler = Sampler::new(SamplerKind::Independent, 2, 1);
+		sampler.start_pixel_sample(0, 0, 0);
+		let origin = Point::new(0.4, 2.8, 2.1);
+		let mut visible = 0;
+		for _ in 0..5_000 {
+			let sample = solid.shape.sample_surface(&origin, &mut sampler).expect("every shape can be sampled");
+			assert!(in_box(&bbox, &sample.point), "{}: sample outside the bounding box", solid.name);
+			assert!((solid.inside)(&(sample.point - sample.normal * 1e-5)), "{}: sample isn't on the surface", solid.name);
+			assert!(!(solid.inside)(&(sample.point + sample.normal * 1e-5)), "{}: sample normal points in", solid.name);
+
+			let to_point = sample.point - origin;
+			let direction = to_point.normalize();
+			let mut rec = HitRecord::default();
+			solid.shape.hit(&Ray::new(origin, direction), Interval::new(0.001, f64::INFINITY), &mut rec);
+			if (rec.hit_point - sample.point).length() > 1e-6 {
+				continue;
+			}
+			visible += 1;
+			let pdf = solid.shape.surface_pdf(&origin, &direction);
+			assert!((pdf - sample.pdf).abs() <= 1e-6 * pdf.max(1.0), "{}: surface_pdf {pdf} but sampled with {}", solid.name, sample.pdf);
+		}
+		assert!(visible > 500, "{}: too few visible samples: {visible}", solid.name);
+	}
+}
+
+//the outside half of a torus' tube has more area than the inside half, and samples have to follow that.
+#[test]
+fn samples_are_spread_by_area() {
+	let material = MaterialEnum::new_lambertian(0.5, 0.5, 0.5);
+	let (major, minor) = (1.0, 0.4);
+	let torus = Torus::new(Point::null_vector(), Vector::new(0.0, 0.0, 1.0), major, minor, material);
+	let cylinder = Cylinder::new(Point::null_vector(), Point::new(0.0, 0.0, 2.0), 0.5, material);
+	let mut sampler = Sampler::new(SamplerKind::Independent, 3, 1);
+	sampler.start_pixel_sample(0, 0, 0);
+	let origin = Point::new(5.0, 0.0, 0.0);
+	let samples = 100_000;
+
+	let mut outside = 0;
+	let mut on_caps = 0;
+	for _ in 0..samples {
+		let sample = torus.sample_surface(&origin, &mut sampler).unwrap();
+		let rho = (sample.point.x * sample.point.x + sample.point.y * sample.point.y).sqrt();
+		if rho > major {
+			outside += 1;
+		}
+		let sample = cylinder.sample_surface(&origin, &mut sampler).unwrap();
+		if sample.normal.z.abs() > 0.5 {
+			on_caps += 1;
+		}
+	}
+
+	//the outer half has pi major + 2 minor of the 2 pi major, the caps 2 pi r^2 of 2 pi r^2 + 2 pi r h.
+	let expected_outside = (PI * major + 2.0 * minor) / (2.0 * PI * major);
+	let expected_caps = (0.5 * 0.5) / (0.5 * 0.5 + 0.5 * 2.0);
+	for (name, found, expected) in [("torus outside", outside, expected_outside), ("cylinder caps", on_caps, expected_caps)] {
+		let fraction = found as f64 / samples as f64;
+		let half_width = 3.29 * (expected * (1.0 - expected) / samples as f64).sqrt();
+		assert!((fraction - expected).abs() <= half_width, "{name}: {fraction:.4} of the samples, expected {expected:.4}");
+	}
+}
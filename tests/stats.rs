@@ -0,0 +1,90 @@
+#![allow(clippy::needless_return)]
+//Checks the render statistics count what actually happened.
+mod common;
+
+use common::{lit_world, small_camera};
+use raytracer::*;
+
+#[test]
+fn every_sample_is_counted_once() {
+	let camera = small_camera();
+	let (_, stats) = camera.render_with_stats(&lit_world());
+	let pixels = (camera.image_width * camera.image_height()) as u64;
+	assert_eq!(stats.samples(), pixels * camera.samples_per_pixel as u64);
+	assert_eq!(stats.tiles.iter().map(|tile| tile.samples).sum::<u64>(), stats.samples());
+	let tile_pixels: i32 = stats.tiles.iter().map(|tile| (tile.x1 - tile.x0) * (tile.y1 - tile.y0)).sum();
+	assert_eq!(tile_pixels as u64, pixels);
+
+	assert!(stats.counters.shadow_rays > 0, "a lit diffuse scene should trace shadow rays");
+	assert!(stats.average_path_length() >= 1.0 && stats.average_path_length() <= camera.max_depth as f64);
+	assert_eq!(stats.counters.bvh_node_visits, 0);
+}
+
+#[test]
+fn bvh_visits_are_counted_and_save_tests() {
+	let camera = small_camera();
+	let linear = camera.render_with_stats(&lit_world()).1;
+	let mut world = lit_world();
+	world.build_bvh();
+	let accelerated = camera.render_with_stats(&world).1;
+
+	assert!(accelerated.counters.bvh_node_visits > 0);
+	//the same rays get traced either way, only the work per ray changes.
+	assert_eq!(accelerated.counters.primary_rays, linear.counters.primary_rays);
+	assert_eq!(accelerated.counters.secondary_rays, linear.counters.secondary_rays);
+	assert!(accelerated.counters.intersection_tests <= linear.counters.intersection_tests);
+}
+
+#[test]
+fn depth_limits_and_roulette_end_paths() {
+	let mut camera = small_camera();
+	camera.max_depth = 50;
+	camera.russian_roulette = None;
+	let full = camera.render_with_stats(&lit_world()).1;
+	assert_eq!(full.counters.roulette_terminations, 0);
+
+	camera.russian_roulette = Some(RussianRoulette { min_depth: 2 });
+	let roulette = camera.render_with_stats(&lit_world()).1;
+	assert!(roulette.counters.roulette_terminations > 0);
+	assert!(roulette.counters.secondary_rays < full.counters.secondary_rays);
+	assert_eq!(roulette.samples(), full.samples());
+
+	//every bounce in this scene is diffuse, so one bounce means no path gets past its second segment.
+	camera.depth_limits.diffuse = 1;
+	let limited = camera.render_with_stats(&lit_world()).1;
+	assert!(limited.average_path_length() <= 2.0);
+	assert!(limited.counters.max_depth_terminations > 0);
+}
+
+#[test]
+fn clamping_and_regularization_are_counted() {
+	let mut world = lit_world();
+	world.add(1.0, 0.0, -1.0, 0.4, MaterialEnum::new_dielectric(1.5));
+	let mut camera = small_camera();
+	let plain = camera.render_with_stats(&world).1;
+	assert_eq!(plain.counters.clamped_samples, 0);
+	assert_eq!(plain.counters.regularized_bounces, 0);
+
+	camera.firefly_clamp = Some(0.01);
+	camera.path_regularization = Some(PathRegularization { angle: 5.0 });
+	let (framebuffer, stats) = camera.render_with_stats(&world);
+	assert!(stats.counters.clamped_samples > 0);
+	assert!(stats.counters.regularized_bounces > 0);
+	let clamped = (0..framebuffer.height).flat_map(|j| (0..framebuffer.width).map(move |i| (i, j)))
+		.all(|(i, j)| {
+			let indirect = framebuffer.pixel(i, j).indirect();
+			return indirect.x.max(indirect.y).max(indirect.z) <= 0.01 + 1e-9;
+		});
+	assert!(clamped, "indirect light got past the clamp");
+}
+
+#[test]
+fn stats_file_has_every_counter() {
+	let camera = small_camera();
+	let (_, stats) = camera.render_with_stats(&lit_world());
+	let json = stats.to_json();
+	for key in ["wall_time_seconds", "samples_per_second", "primary_rays", "secondary_rays", "shadow_rays", "intersection_tests", "bvh_node_visits", "average_path_length", "max_depth_terminations", "roulette_terminations", "clamped_samples", "regularized_bounces", "tiles"] {
+		assert!(json.contains(&format!("\"{key}\"")), "{key} is missing from the stats file");
+	}
+	assert!(json.contains(&format!("\"primary_rays\": {},", stats.counters.primary_rays)));
+}
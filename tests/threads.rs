@@ -0,0 +1,33 @@
+#![allow(clippy::needless_return)]
+//Checks that a render only depends on its seed: however many threads share the tiles, and in
+//whatever order they happen to finish them, every pixel comes out bit for bit the same.
+mod common;
+
+use common::{assert_identical, lit_world, small_camera};
+use raytracer::*;
+
+//one setting on top of small_camera's, each of them changes how pixels and tiles depend on each other.
+type Setup = fn(&mut Camera);
+
+#[test]
+fn thread_count_does_not_change_the_image() {
+	let world = lit_world();
+	let setups: [Setup; 3] = [
+		|_| {},
+		//a wide filter splats samples across tile edges, so the tiles' order of merging matters.
+		|camera| camera.filter = Filter::new_mitchell(2.0),
+		|camera| camera.adaptive = Some(AdaptiveSampling { min_samples: 2, max_samples: 16, tolerance: 0.05 }),
+	];
+	for setup in setups {
+		let mut camera = small_camera();
+		setup(&mut camera);
+		camera.threads = 1;
+		let expected = camera.render_framebuffer(&world);
+		//more threads than tiles too, some of them never get one.
+		for threads in [4, 4, 3, 64] {
+			camera.threads = threads;
+			let found = camera.render_framebuffer(&world);
+			assert_identical(&found, &expected);
+		}
+	}
+}
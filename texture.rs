@@ -0,0 +1,145 @@
+use crate::Color;
+use crate::Point;
+use crate::Vector;
+use crate::PI;
+use crate::noise;
+
+//Colors that change over a surface. Kept Copy like the materials that hold them.
+#[derive(Debug, Clone, Copy)]
+pub enum Texture {
+	Solid(Color),
+	//a checkerboard of cubes in world space, so it doesn't depend on how the surface is mapped.
+	Checker {
+		inverse_scale: f64,
+		even: Color,
+		odd: Color,
+	},
+	//a checkerboard over the surface's own u and v, with u_count by v_count squares.
+	UvChecker {
+		u_count: f64,
+		v_count: f64,
+		even: Color,
+		odd: Color,
+	},
+	//veins of color bent by turbulence, scale is how many veins fit in a unit.
+	Marble {
+		scale: f64,
+		color: Color,
+	},
+	//a made up planet with oceans, land, deserts and ice caps, mapped over a sphere's u and v.
+	Earth,
+}
+
+impl Texture {
+	//scale is the size of one cube.
+	pub fn new_checker(scale: f64, even: Color, odd: Color) -> Texture {
+		Texture::Checker {
+			inverse_scale: 1.0 / scale,
+			even,
+			odd,
+		}
+	}
+	pub fn new_uv_checker(u_count: f64, v_count: f64, even: Color, odd: Color) -> Texture {
+		Texture::UvChecker {
+			u_count,
+			v_count,
+			even,
+			odd,
+		}
+	}
+
+	pub fn new_marble(scale: f64, color: Color) -> Texture {
+		Texture::Marble {
+			scale,
+			color,
+		}
+	}
+
+	pub fn value(&self, u: f64, v: f64, p: &Point) -> Color {
+		match self {
+			Texture::Solid(color) => return *color,
+			Texture::Checker { inverse_scale, even, odd } => {
+				let x = (inverse_scale * p.x).floor() as i64;
+				let y = (inverse_scale * p.y).floor() as i64;
+				let z = (inverse_scale * p.z).floor() as i64;
+				if (x + y + z) % 2 == 0 {
+					return *even;
+				}
+				return *odd;
+			}
+			Texture::UvChecker { u_count, v_count, even, odd } => {
+				let i = (u * u_count).floor() as i64;
+				let j = (v * v_count).floor() as i64;
+				if (i + j) % 2 == 0 {
+					return *even;
+				}
+				return *odd;
+			}
+			Texture::Marble { scale, color } => {
+				let phase = scale * p.z + 10.0 * noise::turbulence(p, 7);
+				return *color * (0.5 * (1.0 + phase.sin()));
+			}
+			Texture::Earth => return Self::earth(u, v),
+		}
+	}
+
+	//The noise is looked up on the unit sphere that u and v came from,
+	//so the planet is seamless and the same whatever size the sphere is.
+	fn earth(u: f64, v: f64) -> Color {
+		let theta = v * PI;
+		let phi = u * 2.0 * PI;
+		let direction = Vector::new(-phi.cos() * theta.sin(), -theta.cos(), phi.sin() * theta.sin());
+		let latitude = direction.y.abs();
+
+		let elevation = noise::fractal_sum(&(direction * 2.5), 7);
+		//the caps reach further down where the noise is higher.
+		if latitude + 0.1 * noise::noise(&(direction * 6.0)) > 0.88 {
+			return Color::new(0.9, 0.92, 0.95);
+		}
+
+		let sea_level = 0.05;
+		if elevation < sea_level {
+			let deep = Color::new(0.01, 0.05, 0.2);
+			let shallow = Color::new(0.05, 0.25, 0.45);
+			let t = ((elevation - sea_level) * 4.0 + 1.0).clamp(0.0, 1.0);
+			return deep * (1.0 - t) + shallow * t;
+		}
+
+		let forest = Color::new(0.1, 0.3, 0.08);
+		let desert = Color::new(0.65, 0.55, 0.35);
+		let mountain = Color::new(0.4, 0.35, 0.3);
+		//deserts in a band on either side of the equator.
+		let dryness = 1.0 - ((latitude - 0.3).abs() * 10.0).min(1.0) + 0.5 * noise::noise(&(direction * 8.0));
+		let land = forest * (1.0 - dryness.clamp(0.0, 1.0)) + desert * dryness.clamp(0.0, 1.0);
+		let height = ((elevation - sea_level - 0.3) * 3.0).clamp(0.0, 1.0);
+		return land * (1.0 - height) + mountain * height;
+	}
+
+	//the parameters as plain numbers, for telling materials apart.
+	pub fn hash_values(&self) -> Vec<u64> {
+		let color = |c: &Color| [c.x.to_bits(), c.y.to_bits(), c.z.to_bits()];
+		let mut values: Vec<u64> = Vec::new();
+		match self {
+			Texture::Solid(c) => {
+				values.push(0);
+				values.extend(color(c));
+			}
+			Texture::Checker { inverse_scale, even, odd } => {
+				values.extend([1, inverse_scale.to_bits()]);
+				values.extend(color(even));
+				values.extend(color(odd));
+			}
+			Texture::UvChecker { u_count, v_count, even, odd } => {
+				values.extend([2, u_count.to_bits(), v_count.to_bits()]);
+				values.extend(color(even));
+				values.extend(color(odd));
+			}
+			Texture::Marble { scale, color: c } => {
+				values.extend([3, scale.to_bits()]);
+				values.extend(color(c));
+			}
+			Texture::Earth => values.push(4),
+		}
+		return values;
+	}
+}
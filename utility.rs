@@ -0,0 +1,9 @@
+//UTILITY
+
+pub static INFINITY: f64 = f64::INFINITY;
+pub static PI: f64 = std::f64::consts::PI;
+
+
+pub fn degrees_to_radians(degrees: f64) -> f64 {
+	return (degrees * PI) / 180.0;
+}
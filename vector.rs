@@ -1,5 +1,7 @@
 use std::ops::{Add, Sub, AddAssign, Index, IndexMut, Mul, MulAssign, Div, DivAssign};
-use crate::random_f64_in_range;
+use crate::sampler::Sampler;
+use crate::onb::Onb;
+use crate::sampling;
 
 #[derive(Debug, PartialEq, Copy, Clone, Default)]
 pub struct Vector {
@@ -42,7 +44,7 @@ impl Vector {
 		return *self / len;
 	}
 	pub fn print(&self){
-		println!("{}", format!("x:{0}, y:{1}, z:{2}", self.x, self.y, self.z));
+		println!("x:{0}, y:{1}, z:{2}", self.x, self.y, self.z);
 	}
 
 	pub fn null_vector() -> Vector {
@@ -70,22 +72,21 @@ impl Vector {
 		return Vector::new(0.0, 0.0, -1.0);
 	}
 
-	pub fn random_vector() -> Vector {
+	pub fn random_vector(sampler: &mut Sampler) -> Vector {
 		return Vector {
-			x: random_f64_in_range(-1.0,1.0),
-			y: random_f64_in_range(-1.0,1.0),
-			z: random_f64_in_range(-1.0,1.0),
+			x: sampler.random_f64_in_range(-1.0,1.0),
+			y: sampler.random_f64_in_range(-1.0,1.0),
+			z: sampler.random_f64_in_range(-1.0,1.0),
 		}
 	}
-	pub fn random_normal_vector() -> Vector {
-		return Self::random_vector().normalize();
+	//uniformly distributed on the unit sphere, normalizing random_vector isn't
+	//since the corners of the cube get picked more often.
+	pub fn random_normal_vector(sampler: &mut Sampler) -> Vector {
+		return sampling::uniform_sphere(sampler.get_2d());
 	}
-	pub fn random_on_hemisphere(normal: &Vector) -> Vector {
-		let on_unit_sphere = Self::random_normal_vector();
-		if Self::dot(&on_unit_sphere, normal) > 0.0 {
-			return on_unit_sphere;
-		}
-		return -1.0 * on_unit_sphere;
+	pub fn random_on_hemisphere(normal: &Vector, sampler: &mut Sampler) -> Vector {
+		let uvw = Onb::new(normal);
+		return uvw.local(&sampling::uniform_hemisphere(sampler.get_2d()));
 	}
 	pub fn reflect(v: &Vector, n: &Vector) -> Vector {
 		return *v - 2.0 * Vector::dot(v, n) * *n;
@@ -215,6 +216,13 @@ impl MulAssign<i32> for Vector {
 	}
 }
 // a / b
+impl Div<Vector> for Vector {
+	type Output = Self;
+
+	fn div(self, rhs: Self) -> Self::Output {
+		Self {x: self.x / rhs.x, y: self.y / rhs.y, z: self.z / rhs.z}
+	}
+}
 impl Div<f64> for Vector {
 	type Output = Self;
 